@@ -31,10 +31,11 @@ impl<DB: DatabaseTrait> BlockImporter<DB> {
             return Err(BlockImportError::NotChainedBlock);
         }
         let res = self.validate_block(&block)?;
-        if res.success {
-            if let Err(_e) = self.provider.import_new_block(block) {
-                return Err(BlockImportError::ProviderError);
-            }
+        if !res.success {
+            return Err(BlockImportError::InvalidBlock);
+        }
+        if let Err(_e) = self.provider.import_new_block(block) {
+            return Err(BlockImportError::ProviderError);
         }
 
         Ok(())
@@ -50,7 +51,7 @@ impl<DB: DatabaseTrait> BlockImporter<DB> {
             Err(_e) => return Err(BlockImportError::ProviderError),
         };
 
-        let mut executor = Executor::new(executable_state);
+        let mut executor = Executor::new(executable_state, self.provider.chain_id());
 
         // validate block with state
         match executor.execute_block(&block) {
@@ -77,3 +78,33 @@ impl<DB: DatabaseTrait> BlockImporter<DB> {
         Ok(BlockValidationResult { success, error })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use database::immemorydb::InMemoryDB;
+    use primitives::types::{Address, U256};
+    use provider::test_utils::empty_block;
+
+    use super::*;
+
+    #[test]
+    fn test_invalid_block_is_rejected() {
+        let provider = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 0);
+        let importer = BlockImporter::new(provider.clone());
+        let block = empty_block(&provider, Address::random());
+
+        // Claims fees its empty body never paid
+        let mut invalid = block.clone();
+        invalid.header.total_fee = U256::from(1);
+        assert!(matches!(
+            importer.import_new_block(invalid),
+            Err(BlockImportError::InvalidBlock)
+        ));
+        assert_eq!(provider.block_number(), 0);
+
+        importer.import_new_block(block).unwrap();
+        assert_eq!(provider.block_number(), 1);
+    }
+}
@@ -184,6 +184,14 @@ impl<DB: DatabaseTrait> ConsensusEngine<DB> {
                                             );
                                             continue;
                                         }
+                                        // Not stored, and neither relayed nor used to drop pool transactions
+                                        BlockImportError::InvalidBlock => {
+                                            error!(
+                                                height = &block.header.height,
+                                                "Rejected invalid block."
+                                            );
+                                            continue;
+                                        }
                                         _ => {
                                             error!(
                                                 error = ?e,
@@ -60,6 +60,7 @@ impl Miner {
                             hasher.update(payload_header.previous_hash.hash());
                             hasher.update(payload_header.transaction_root);
                             hasher.update(payload_header.state_root);
+                            hasher.update(payload_header.receipts_root);
                             hasher.update(payload_header.timestamp.to_be_bytes());
                             hasher.update(payload_header.proposer.get_addr());
                             hasher.update(payload_header.difficulty.to_be_bytes());
@@ -0,0 +1,110 @@
+//! Compares the reads of the validator, payload builder and network loop on MDBX with and without `CachedDB`.
+//!
+//! cargo bench -p database --bench cached_db
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use database::{
+    cache::CachedDB, genesis::genesis_accounts_info, mdbx::MDBX,
+    test_utils::append_blocks_with_txs, traits::DatabaseTrait,
+};
+
+const BLOCKS: u64 = 16;
+const TXS_PER_BLOCK: u64 = 200;
+const ITERATIONS: u32 = 20_000;
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("pint-bench-cached-db-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let db = MDBX::genesis_state(&dir, 0).unwrap();
+    append_blocks_with_txs(&db, BLOCKS, TXS_PER_BLOCK);
+    let address = genesis_accounts_info()[0].0;
+
+    let cached = CachedDB::new(db.clone());
+    for (name, mdbx, with_cache) in [
+        (
+            "get_latest_block_header",
+            bench(|| {
+                black_box(db.get_latest_block_header());
+            }),
+            bench(|| {
+                black_box(cached.get_latest_block_header());
+            }),
+        ),
+        (
+            "latest_block_number",
+            bench(|| {
+                black_box(db.latest_block_number());
+            }),
+            bench(|| {
+                black_box(cached.latest_block_number());
+            }),
+        ),
+        (
+            "basic",
+            bench(|| {
+                black_box(db.basic(&address).unwrap());
+            }),
+            bench(|| {
+                black_box(cached.basic(&address).unwrap());
+            }),
+        ),
+        (
+            "get_block",
+            bench(|| {
+                black_box(db.get_block(BLOCKS).unwrap());
+            }),
+            bench(|| {
+                black_box(cached.get_block(BLOCKS).unwrap());
+            }),
+        ),
+    ] {
+        println!(
+            "{name:<24} mdbx {:>9} ns/op   cached {:>9} ns/op   x{:.1}",
+            mdbx.as_nanos(),
+            with_cache.as_nanos(),
+            mdbx.as_secs_f64() / with_cache.as_secs_f64()
+        );
+    }
+
+    let stats = cached.stats();
+    println!(
+        "latest   {:?} hit rate {:.4}",
+        stats.latest,
+        stats.latest.hit_rate()
+    );
+    println!(
+        "headers  {:?} hit rate {:.4}",
+        stats.headers,
+        stats.headers.hit_rate()
+    );
+    println!(
+        "blocks   {:?} hit rate {:.4}",
+        stats.blocks,
+        stats.blocks.hit_rate()
+    );
+    println!(
+        "accounts {:?} hit rate {:.4}",
+        stats.accounts,
+        stats.accounts.hit_rate()
+    );
+
+    drop(cached);
+    drop(db);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// Mean time of one call
+fn bench(mut f: impl FnMut()) -> Duration {
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use lru::LruCache;
+use parking_lot::Mutex;
+use primitives::{
+    block::{Block, Header},
+    receipt::TransactionReceipt,
+    transaction::SignedTransaction,
+    types::{Account, Address, B256, BlockHash, TxHash},
+    world::World,
+};
+
+use crate::{
+    changeset::AccountChangeSet,
+    history::{AccountTransaction, TxPosition},
+    prune::PruneMode,
+    traits::{DatabaseTrait, TrieUpdate},
+};
+
+pub const DEFAULT_HEADER_CACHE_SIZE: usize = 1024;
+pub const DEFAULT_BLOCK_CACHE_SIZE: usize = 128;
+pub const DEFAULT_ACCOUNT_CACHE_SIZE: usize = 8192;
+
+/// Entries kept by each cache of a `CachedDB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSizes {
+    pub headers: usize,
+    pub blocks: usize,
+    pub accounts: usize,
+}
+
+impl Default for CacheSizes {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_HEADER_CACHE_SIZE,
+            blocks: DEFAULT_BLOCK_CACHE_SIZE,
+            accounts: DEFAULT_ACCOUNT_CACHE_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CachedDBStats {
+    pub latest: CacheStats,
+    pub headers: CacheStats,
+    pub blocks: CacheStats,
+    pub accounts: CacheStats,
+}
+
+#[derive(Debug)]
+struct Entries<K: Hash + Eq, V> {
+    lru: LruCache<K, V>,
+    // Bumped by every invalidation. A value read from the database before it is not kept.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct Cache<K: Hash + Eq, V> {
+    entries: Mutex<Entries<K, V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> {
+    fn new(size: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                lru: LruCache::new(NonZeroUsize::new(size).unwrap_or(NonZeroUsize::MIN)),
+                generation: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // `load` runs without the lock. Only what `keep` accepts is cached.
+    fn get_or_load<E>(
+        &self,
+        key: K,
+        load: impl FnOnce() -> Result<V, E>,
+        keep: impl FnOnce(&V) -> bool,
+    ) -> Result<V, E> {
+        let generation = {
+            let mut entries = self.entries.lock();
+            if let Some(value) = entries.lru.get(&key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value.clone());
+            }
+            entries.generation
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let value = load()?;
+        if keep(&value) {
+            let mut entries = self.entries.lock();
+            if entries.generation == generation {
+                entries.lru.put(key, value.clone());
+            }
+        }
+        Ok(value)
+    }
+
+    fn clear(&self) {
+        let mut entries = self.entries.lock();
+        entries.lru.clear();
+        entries.generation += 1;
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Caches {
+    latest: Cache<(), u64>,
+    headers: Cache<u64, Option<Header>>,
+    blocks: Cache<u64, Option<Block>>,
+    // Accounts at the latest block. Absent accounts are cached as None.
+    accounts: Cache<Address, Option<Account>>,
+}
+
+/// Read-through LRU caches for the latest block number, headers, blocks and latest accounts.
+/// Every write must go through the wrapper, which drops what the write may have changed.
+/// Clones share the caches.
+#[derive(Debug, Clone)]
+pub struct CachedDB<DB: DatabaseTrait> {
+    db: DB,
+    caches: Arc<Caches>,
+}
+
+impl<DB: DatabaseTrait> CachedDB<DB> {
+    pub fn new(db: DB) -> Self {
+        Self::with_sizes(db, CacheSizes::default())
+    }
+
+    pub fn with_sizes(db: DB, sizes: CacheSizes) -> Self {
+        Self {
+            db,
+            caches: Arc::new(Caches {
+                latest: Cache::new(1),
+                headers: Cache::new(sizes.headers),
+                blocks: Cache::new(sizes.blocks),
+                accounts: Cache::new(sizes.accounts),
+            }),
+        }
+    }
+
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+
+    pub fn stats(&self) -> CachedDBStats {
+        CachedDBStats {
+            latest: self.caches.latest.stats(),
+            headers: self.caches.headers.stats(),
+            blocks: self.caches.blocks.stats(),
+            accounts: self.caches.accounts.stats(),
+        }
+    }
+
+    // A new block only moves the latest height and changes accounts
+    fn invalidate_latest_state(&self) {
+        self.caches.latest.clear();
+        self.caches.accounts.clear();
+    }
+
+    // Blocks were removed or replaced
+    fn invalidate_all(&self) {
+        self.invalidate_latest_state();
+        self.caches.headers.clear();
+        self.caches.blocks.clear();
+    }
+}
+
+impl<DB: DatabaseTrait> DatabaseTrait for CachedDB<DB> {
+    fn latest_block_number(&self) -> u64 {
+        let Ok(latest) = self.caches.latest.get_or_load::<Infallible>(
+            (),
+            || Ok(self.db.latest_block_number()),
+            |_| true,
+        );
+        latest
+    }
+
+    fn basic(&self, address: &Address) -> Result<Option<Account>, Box<dyn std::error::Error>> {
+        self.caches
+            .accounts
+            .get_or_load(*address, || self.db.basic(address), |_| true)
+    }
+
+    fn get_state(
+        &self,
+        block_no: u64,
+    ) -> Result<(Option<HashMap<Address, Account>>, Option<World>), Box<dyn std::error::Error>>
+    {
+        self.db.get_state(block_no)
+    }
+
+    fn get_account_changes(
+        &self,
+        block_no: u64,
+    ) -> Result<AccountChangeSet, Box<dyn std::error::Error>> {
+        self.db.get_account_changes(block_no)
+    }
+
+    fn get_world(&self, block_no: u64) -> Result<Option<World>, Box<dyn std::error::Error>> {
+        self.db.get_world(block_no)
+    }
+
+    // Missing blocks are not cached, they may be imported later
+    fn get_block(&self, block_no: u64) -> Result<Option<Block>, Box<dyn std::error::Error>> {
+        self.caches.blocks.get_or_load(
+            block_no,
+            || self.db.get_block(block_no),
+            |block| block.is_some(),
+        )
+    }
+
+    fn get_block_by_hash(
+        &self,
+        hash: BlockHash,
+    ) -> Result<Option<Block>, Box<dyn std::error::Error>> {
+        self.db.get_block_by_hash(hash)
+    }
+
+    fn get_transaction_by_hash(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(SignedTransaction, u64)>, Box<dyn std::error::Error>> {
+        self.db.get_transaction_by_hash(hash)
+    }
+
+    fn get_header(&self, block_no: u64) -> Result<Option<Header>, Box<dyn std::error::Error>> {
+        self.caches.headers.get_or_load(
+            block_no,
+            || self.db.get_header(block_no),
+            |header| header.is_some(),
+        )
+    }
+
+    fn update(
+        &self,
+        changed_accounts: HashMap<Address, Account>,
+        new_field_state: World,
+        new_block: Block,
+        trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let res = self
+            .db
+            .update(changed_accounts, new_field_state, new_block, trie, receipts);
+        self.invalidate_latest_state();
+        res
+    }
+
+    fn get_latest_block_header(&self) -> Header {
+        match self.get_header(self.latest_block_number()) {
+            Ok(Some(header)) => header,
+            _ => self.db.get_latest_block_header(),
+        }
+    }
+
+    fn get_trie_node(
+        &self,
+        hash: B256,
+    ) -> Result<Option<(B256, B256)>, Box<dyn std::error::Error>> {
+        self.db.get_trie_node(hash)
+    }
+
+    fn get_trie_root(&self, block_no: u64) -> Result<Option<B256>, Box<dyn std::error::Error>> {
+        self.db.get_trie_root(block_no)
+    }
+
+    fn update_trie(
+        &self,
+        block_no: u64,
+        root: B256,
+        nodes: Vec<(B256, [u8; 64])>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.db.update_trie(block_no, root, nodes)
+    }
+
+    fn get_receipt(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(TransactionReceipt, u64)>, Box<dyn std::error::Error>> {
+        self.db.get_receipt(hash)
+    }
+
+    fn prune_mode(&self) -> PruneMode {
+        self.db.prune_mode()
+    }
+
+    fn oldest_state(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        self.db.oldest_state()
+    }
+
+    fn prune_history(&self, before: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        self.db.prune_history(before)
+    }
+
+    fn import_snapshot(
+        &self,
+        accounts: HashMap<Address, Account>,
+        world: World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Header,
+        trie: TrieUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let res = self
+            .db
+            .import_snapshot(accounts, world, block, receipts, parent, trie);
+        self.invalidate_all();
+        res
+    }
+
+    fn snapshot_height(&self) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        self.db.snapshot_height()
+    }
+
+    fn unwind_to(&self, height: u64) -> Result<Vec<Block>, Box<dyn std::error::Error>> {
+        let res = self.db.unwind_to(height);
+        self.invalidate_all();
+        res
+    }
+
+    fn account_transactions(
+        &self,
+        address: &Address,
+        from: TxPosition,
+        limit: usize,
+    ) -> Result<Vec<AccountTransaction>, Box<dyn std::error::Error>> {
+        self.db.account_transactions(address, from, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        immemorydb::InMemoryDB,
+        test_utils::{append_blocks, shared_address},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_cache_hits_and_invalidation() {
+        let db = CachedDB::new(Arc::new(InMemoryDB::genesis_state()));
+        append_blocks(&db, 3);
+        let before = db.stats();
+
+        let header = db.get_latest_block_header();
+        assert_eq!(header.height, 3);
+        assert_eq!(
+            db.get_latest_block_header().calculate_hash(),
+            header.calculate_hash()
+        );
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 3);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 3);
+        let stats = db.stats();
+        assert_eq!(stats.latest.hits - before.latest.hits, 1);
+        assert_eq!(stats.headers.hits - before.headers.hits, 1);
+        assert_eq!(stats.accounts.hits - before.accounts.hits, 1);
+
+        // A new block moves the latest height and the accounts it changed
+        append_blocks(&db, 1);
+        assert_eq!(db.latest_block_number(), 4);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 4);
+        assert_eq!(
+            db.get_header(3).unwrap().unwrap().calculate_hash(),
+            header.calculate_hash()
+        );
+
+        // Unwound blocks are gone from every cache
+        db.unwind_to(2).unwrap();
+        assert_eq!(db.latest_block_number(), 2);
+        assert_eq!(db.get_latest_block_header().height, 2);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 2);
+        assert!(db.get_block(4).is_err());
+
+        // Read again from the database once the block is added again
+        append_blocks(&db, 1);
+        let misses = db.stats().headers.misses;
+        assert_eq!(db.get_header(3).unwrap().unwrap().height, 3);
+        assert_eq!(db.get_header(3).unwrap().unwrap().height, 3);
+        assert_eq!(db.stats().headers.misses, misses + 1);
+    }
+}
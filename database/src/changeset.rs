@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use primitives::types::{Account, Address};
+
+/// Accounts changed by one block, with their value before the block.
+/// `None` means the block created the account.
+pub type AccountChangeSet = HashMap<Address, Option<Account>>;
+
+/// Accounts that differ between two full states
+pub fn account_changes(
+    before: &HashMap<Address, Account>,
+    after: &HashMap<Address, Account>,
+) -> AccountChangeSet {
+    let mut changes = AccountChangeSet::new();
+    for (address, account) in after.iter() {
+        let previous = before.get(address).copied();
+        if previous != Some(*account) {
+            changes.insert(*address, previous);
+        }
+    }
+    for (address, account) in before.iter() {
+        if !after.contains_key(address) {
+            changes.insert(*address, Some(*account));
+        }
+    }
+    changes
+}
+
+/// Change set of a block that sets `changed` on top of a state, looking up the
+/// previous value of the changed accounts only
+pub fn changes_of<E>(
+    changed: &HashMap<Address, Account>,
+    mut previous: impl FnMut(&Address) -> Result<Option<Account>, E>,
+) -> Result<AccountChangeSet, E> {
+    let mut changes = AccountChangeSet::new();
+    for (address, account) in changed.iter() {
+        let before = previous(address)?;
+        if before != Some(*account) {
+            changes.insert(*address, before);
+        }
+    }
+    Ok(changes)
+}
+
+/// Undoes one block on top of the state right after it
+pub fn revert_changes(accounts: &mut HashMap<Address, Account>, changes: &AccountChangeSet) {
+    for (address, previous) in changes.iter() {
+        match previous {
+            Some(account) => accounts.insert(*address, *account),
+            None => accounts.remove(address),
+        };
+    }
+}
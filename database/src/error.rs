@@ -1,3 +1,4 @@
+use primitives::types::{B256, ChainId};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,6 +9,26 @@ pub enum DatabaseError {
     DataNotExists,
     #[error("Database error itself")]
     DBError,
-    #[error("Cannot Remove! Only latest can be removed")]
-    CannotRemove,
+    #[error("State at block {0} has been pruned")]
+    Pruned(u64),
+    #[error("Invalid prune mode: {0}")]
+    InvalidPruneMode(String),
+    #[error("Database schema version {found} is newer than the supported version {supported}")]
+    UnsupportedSchemaVersion { found: u32, supported: u32 },
+    #[error("No migration from database schema version {from} to {to}")]
+    NoMigration { from: u32, to: u32 },
+    #[error("Database was written before the canonical block encoding and cannot be migrated. Sync a new one")]
+    LegacyEncoding,
+    #[error("Database belongs to genesis {found}, expected {expected}")]
+    GenesisMismatch { expected: B256, found: B256 },
+    #[error("Database belongs to chain id {found}, expected {expected}")]
+    ChainIdMismatch { expected: ChainId, found: ChainId },
+    #[error("Invalid or missing metadata: {0}")]
+    InvalidMetadata(String),
+    #[error("A snapshot can only be imported into a database without blocks")]
+    NotEmpty,
+    #[error("Segment I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Corrupted record in segment {segment} at offset {offset}")]
+    CorruptedSegment { segment: u32, offset: u64 },
 }
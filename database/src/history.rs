@@ -0,0 +1,149 @@
+use primitives::{
+    multisig::multisig_address,
+    transaction::{SignedTransaction, Transaction},
+    types::{Address, TxHash},
+};
+
+/// Place of a transaction in the chain. Orders transactions as they were executed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TxPosition {
+    pub block_no: u64,
+    pub index: u32,
+}
+
+impl TxPosition {
+    pub fn new(block_no: u64, index: u32) -> Self {
+        Self { block_no, index }
+    }
+}
+
+/// One entry of an account's transaction history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountTransaction {
+    pub position: TxPosition,
+    pub hash: TxHash,
+}
+
+/// Accounts whose history lists the transaction: its sender and every account it pays or creates.
+/// The sender of a multisig transfer is the multisig account. A signature that does not recover leaves
+/// out the sender, and with it the account a multisig registration creates.
+pub fn transaction_accounts(signed: &SignedTransaction) -> Vec<Address> {
+    let sender = match signed.transaction() {
+        Transaction::MultisigTransfer(tx) => Some(tx.from),
+        _ => signed.recover_signer().ok(),
+    };
+    let mut accounts: Vec<Address> = sender.into_iter().collect();
+    let mut push = |address: Address| {
+        if !accounts.contains(&address) {
+            accounts.push(address);
+        }
+    };
+    match signed.transaction() {
+        Transaction::Transfer(tx) => push(tx.to),
+        Transaction::Data(_) => {}
+        Transaction::BatchTransfer(tx) => tx.outputs.iter().for_each(|output| push(output.to)),
+        Transaction::RegisterMultisig(tx) => {
+            if let Some(sender) = sender {
+                push(multisig_address(&sender, tx.nonce));
+            }
+        }
+        Transaction::MultisigTransfer(tx) => push(tx.to),
+    }
+    accounts
+}
+
+/// History entries added by the body of the block stored at `block_no`
+pub fn block_history(
+    block_no: u64,
+    body: &[SignedTransaction],
+) -> Vec<(Address, AccountTransaction)> {
+    let mut entries = Vec::new();
+    for (index, signed) in body.iter().enumerate() {
+        let entry = AccountTransaction {
+            position: TxPosition::new(block_no, index as u32),
+            hash: signed.hash,
+        };
+        for address in transaction_accounts(signed) {
+            entries.push((address, entry));
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{
+        signature::Signature,
+        test_utils::sign,
+        transaction::{TransferOutput, TxBatchTransfer, TxMultisigTransfer, TxRegisterMultisig},
+        types::U256,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_multisig_sender_is_the_multisig_account() {
+        let (from, to) = (Address::random(), Address::random());
+        let tx = Transaction::MultisigTransfer(TxMultisigTransfer {
+            chain_id: 0,
+            nonce: 0,
+            from,
+            to,
+            fee: 1,
+            value: U256::from(1),
+            data: Vec::new(),
+        });
+        let hash = tx.encode_for_signing();
+        let signed = SignedTransaction::new(tx, Signature::dummy(), hash);
+        assert_eq!(transaction_accounts(&signed), vec![from, to]);
+
+        let entries = block_history(7, &[signed]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].1.position, TxPosition::new(7, 0));
+    }
+
+    #[test]
+    fn test_batch_transfer_lists_every_output() {
+        let (alice, bob) = (Address::random(), Address::random());
+        let outputs = [alice, bob, alice]
+            .into_iter()
+            .map(|to| TransferOutput {
+                to,
+                value: U256::from(1),
+            })
+            .collect();
+        let recovered = sign(
+            "pint",
+            Transaction::BatchTransfer(TxBatchTransfer {
+                chain_id: 0,
+                nonce: 0,
+                fee: 1,
+                data: Vec::new(),
+                outputs,
+            }),
+        );
+        assert_eq!(
+            transaction_accounts(recovered.tx()),
+            vec![recovered.signer(), alice, bob]
+        );
+    }
+
+    #[test]
+    fn test_multisig_registration_lists_the_created_account() {
+        let recovered = sign(
+            "pint",
+            Transaction::RegisterMultisig(TxRegisterMultisig {
+                chain_id: 0,
+                nonce: 4,
+                fee: 1,
+                threshold: 1,
+                owners: vec![Address::random()],
+            }),
+        );
+        let sender = recovered.signer();
+        assert_eq!(
+            transaction_accounts(recovered.tx()),
+            vec![sender, multisig_address(&sender, 4)]
+        );
+    }
+}
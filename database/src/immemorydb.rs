@@ -1,25 +1,44 @@
 use std::{
     collections::{BTreeMap, HashMap},
+    convert::Infallible,
     sync::Arc,
 };
 
 use parking_lot::RwLock;
 use primitives::{
     block::{Block, Header},
+    receipt::TransactionReceipt,
     transaction::SignedTransaction,
-    types::{Account, Address},
+    types::{Account, Address, B256, TxHash},
     world::World,
 };
 use tracing::warn;
 
-use crate::{error::DatabaseError, genesis::genesis_accounts_info, traits::DatabaseTrait};
+use crate::{
+    changeset::{AccountChangeSet, changes_of, revert_changes},
+    error::DatabaseError,
+    genesis::genesis_accounts_info,
+    history::{AccountTransaction, TxPosition, block_history},
+    prune::{PruneMode, TrieNodeMap, add_trie_root, release_trie_root},
+    traits::{DatabaseTrait, TrieUpdate},
+};
 
 #[derive(Debug)]
 pub struct InMemoryDB {
-    accounts: RwLock<BTreeMap<u64, HashMap<Address, Account>>>,
+    // Accounts at the latest block, and what every block changed
+    accounts: RwLock<HashMap<Address, Account>>,
+    account_changes: RwLock<BTreeMap<u64, AccountChangeSet>>,
     field: RwLock<BTreeMap<u64, World>>,
     blockchain: RwLock<BTreeMap<u64, Block>>,
     latest: RwLock<u64>,
+    trie_nodes: RwLock<TrieNodeMap>,
+    trie_roots: RwLock<BTreeMap<u64, B256>>,
+    receipts: RwLock<HashMap<TxHash, TransactionReceipt>>,
+    account_transactions: RwLock<BTreeMap<(Address, TxPosition), TxHash>>,
+    snapshot_height: RwLock<Option<u64>>,
+    // Header of the snapshot's parent, whose block is not stored
+    snapshot_parent: RwLock<Option<Header>>,
+    prune_mode: PruneMode,
 }
 
 impl InMemoryDB {
@@ -34,9 +53,6 @@ impl InMemoryDB {
     }
 
     pub fn new() -> Self {
-        let mut accounts: BTreeMap<u64, HashMap<Address, Account>> = BTreeMap::new();
-        accounts.insert(0 as u64, HashMap::new());
-
         let mut field: BTreeMap<u64, World> = BTreeMap::new();
         field.insert(0, World::new());
 
@@ -45,24 +61,116 @@ impl InMemoryDB {
         blockchain.insert(0, genesis_block);
 
         Self {
-            accounts: RwLock::new(accounts),
+            accounts: RwLock::new(HashMap::new()),
+            account_changes: RwLock::new(BTreeMap::new()),
             field: RwLock::new(field),
             blockchain: RwLock::new(blockchain),
             latest: RwLock::new(0),
+            trie_nodes: RwLock::new(HashMap::new()),
+            trie_roots: RwLock::new(BTreeMap::new()),
+            receipts: RwLock::new(HashMap::new()),
+            account_transactions: RwLock::new(BTreeMap::new()),
+            snapshot_height: RwLock::new(None),
+            snapshot_parent: RwLock::new(None),
+            prune_mode: PruneMode::default(),
         }
     }
 
+    pub fn with_prune_mode(mut self, prune_mode: PruneMode) -> Self {
+        self.prune_mode = prune_mode;
+        self
+    }
+
     pub fn add_account(
         &mut self,
         address: Address,
         account: Account,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut state = self.accounts.write();
+        self.accounts.write().insert(address, account);
+        Ok(())
+    }
 
-        let latest_accounts = state.entry(*self.latest.read()).or_default();
-        latest_accounts.insert(address, account);
+    // `update` without the log line. Returns the height of the new block.
+    pub(crate) fn append_block(
+        &self,
+        changed_accounts: HashMap<Address, Account>,
+        new_field_state: World,
+        block: Block,
+        trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>,
+    ) -> u64 {
+        let mut latest = self.latest.write();
+        *latest += 1;
+        let mut accounts = self.accounts.write();
+        let Ok(changes) = changes_of(&changed_accounts, |address| {
+            Ok::<_, Infallible>(accounts.get(address).copied())
+        });
+        self.account_changes.write().insert(*latest, changes);
+        accounts.extend(changed_accounts);
 
-        Ok(())
+        let mut field = self.field.write();
+        field.insert(*latest, new_field_state);
+
+        let mut blockchain = self.blockchain.write();
+        self.insert_trie(*latest, trie);
+        let mut stored = self.receipts.write();
+        for receipt in receipts {
+            stored.insert(receipt.tx_hash, receipt);
+        }
+        let mut account_transactions = self.account_transactions.write();
+        for (address, entry) in block_history(*latest, &block.body) {
+            account_transactions.insert((address, entry.position), entry.hash);
+        }
+        blockchain.insert(*latest, block);
+        *latest
+    }
+
+    fn insert_trie(&self, block_no: u64, trie: TrieUpdate) {
+        let mut trie_nodes = self.trie_nodes.write();
+        let Ok(()) = add_trie_root(&mut *trie_nodes, trie.root, trie.nodes);
+        if let Some(replaced) = self.trie_roots.write().insert(block_no, trie.root) {
+            let Ok(()) = release_trie_root(&mut *trie_nodes, replaced);
+        }
+    }
+
+    // `unwind_to` without the log line
+    pub(crate) fn remove_blocks(&self, height: u64) -> Result<Vec<Block>, DatabaseError> {
+        // Taken in the order update and prune_history use, and held until everything is removed
+        let mut latest = self.latest.write();
+        let mut accounts = self.accounts.write();
+        let mut field = self.field.write();
+        let mut account_changes = self.account_changes.write();
+        let mut blockchain = self.blockchain.write();
+        let mut trie_nodes = self.trie_nodes.write();
+        let mut trie_roots = self.trie_roots.write();
+        let mut receipts = self.receipts.write();
+        let mut account_transactions = self.account_transactions.write();
+
+        if height < field.keys().next().copied().unwrap_or(0) {
+            return Err(DatabaseError::Pruned(height));
+        }
+        if height >= *latest {
+            return Ok(Vec::new());
+        }
+
+        for changes in account_changes.split_off(&(height + 1)).values().rev() {
+            revert_changes(&mut accounts, changes);
+        }
+        field.split_off(&(height + 1));
+        for root in trie_roots.split_off(&(height + 1)).into_values() {
+            let Ok(()) = release_trie_root(&mut *trie_nodes, root);
+        }
+        let blocks: Vec<Block> = blockchain.split_off(&(height + 1)).into_values().collect();
+        for block in blocks.iter() {
+            for tx in block.body.iter() {
+                receipts.remove(&tx.hash);
+            }
+            for (address, entry) in block_history(block.header.height, &block.body) {
+                account_transactions.remove(&(address, entry.position));
+            }
+        }
+        *latest = height;
+        Ok(blocks)
     }
 }
 
@@ -72,11 +180,7 @@ impl DatabaseTrait for Arc<InMemoryDB> {
     }
 
     fn basic(&self, address: &Address) -> Result<Option<Account>, Box<dyn std::error::Error>> {
-        let mut state = self.accounts.write();
-
-        let latest_accounts: &mut HashMap<Address, Account> =
-            state.entry(self.latest_block_number()).or_default();
-        Ok(latest_accounts.get(address).or(None).cloned())
+        Ok(self.accounts.read().get(address).cloned())
     }
 
     fn get_state(
@@ -84,10 +188,17 @@ impl DatabaseTrait for Arc<InMemoryDB> {
         block_no: u64,
     ) -> Result<(Option<HashMap<Address, Account>>, Option<World>), Box<dyn std::error::Error>>
     {
-        let accounts = self.accounts.read();
+        if block_no < self.oldest_state()? {
+            return Err(Box::new(DatabaseError::Pruned(block_no)));
+        }
         let mut account_base = None;
-        if let Some(state_account) = accounts.get(&block_no) {
-            account_base = Some(state_account.clone());
+        if block_no <= self.latest_block_number() {
+            let mut accounts = self.accounts.read().clone();
+            let changes = self.account_changes.read();
+            for (_, block_changes) in changes.range(block_no + 1..).rev() {
+                revert_changes(&mut accounts, block_changes);
+            }
+            account_base = Some(accounts);
         }
 
         let field = self.field.read();
@@ -99,6 +210,24 @@ impl DatabaseTrait for Arc<InMemoryDB> {
         Ok((account_base, field_base))
     }
 
+    fn get_account_changes(
+        &self,
+        block_no: u64,
+    ) -> Result<AccountChangeSet, Box<dyn std::error::Error>> {
+        if block_no <= self.oldest_state()? {
+            return Err(Box::new(DatabaseError::Pruned(block_no.saturating_sub(1))));
+        }
+        let changes = self.account_changes.read();
+        Ok(changes.get(&block_no).cloned().unwrap_or_default())
+    }
+
+    fn get_world(&self, block_no: u64) -> Result<Option<World>, Box<dyn std::error::Error>> {
+        if block_no < self.oldest_state()? {
+            return Err(Box::new(DatabaseError::Pruned(block_no)));
+        }
+        Ok(self.field.read().get(&block_no).cloned())
+    }
+
     fn get_block(
         &self,
         block_no: u64,
@@ -131,9 +260,11 @@ impl DatabaseTrait for Arc<InMemoryDB> {
     ) -> Result<Option<Header>, Box<dyn std::error::Error + 'static>> {
         let blockchain = self.blockchain.read();
         if let Some(block) = blockchain.get(&block_no) {
-            Ok(Some(block.header().clone()))
-        } else {
-            Err(Box::new(DatabaseError::DataNotExists))
+            return Ok(Some(block.header().clone()));
+        }
+        match self.snapshot_parent.read().as_ref() {
+            Some(parent) if parent.height == block_no => Ok(Some(parent.clone())),
+            _ => Err(Box::new(DatabaseError::DataNotExists)),
         }
     }
 
@@ -146,44 +277,154 @@ impl DatabaseTrait for Arc<InMemoryDB> {
 
     fn update(
         &self,
-        new_account_state: HashMap<Address, Account>,
+        changed_accounts: HashMap<Address, Account>,
         new_field_state: World,
         block: Block,
+        trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut latest = self.latest.write();
-        *latest += 1;
-        let mut state = self.accounts.write();
-        state.insert(*latest, new_account_state);
+        let latest = self.append_block(changed_accounts, new_field_state, block, trie, receipts);
+        warn!(block_height = ?latest, "DB updated new block.");
 
+        Ok(())
+    }
+
+    fn prune_mode(&self) -> PruneMode {
+        self.prune_mode
+    }
+
+    fn oldest_state(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(self.field.read().keys().next().copied().unwrap_or(0))
+    }
+
+    fn prune_history(&self, before: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        let before = before.min(self.latest_block_number());
         let mut field = self.field.write();
-        field.insert(*latest, new_field_state);
+        let kept = field.split_off(&before);
+        let pruned = field.len() as u64;
+        *field = kept;
+        // The change sets up to `before` are only needed to rebuild the pruned states
+        self.account_changes
+            .write()
+            .retain(|block_no, _| *block_no > before);
+        let mut trie_nodes = self.trie_nodes.write();
+        let mut trie_roots = self.trie_roots.write();
+        let kept = trie_roots.split_off(&before);
+        for root in std::mem::replace(&mut *trie_roots, kept).into_values() {
+            let Ok(()) = release_trie_root(&mut *trie_nodes, root);
+        }
+        Ok(pruned)
+    }
 
+    fn import_snapshot(
+        &self,
+        accounts: HashMap<Address, Account>,
+        world: World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Header,
+        trie: TrieUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut latest = self.latest.write();
+        if *latest != 0 {
+            return Err(Box::new(DatabaseError::NotEmpty));
+        }
+        let height = block.header.height;
+        // Genesis state is replaced, the genesis block stays as the chain identity
+        *self.accounts.write() = accounts;
+        self.account_changes.write().clear();
+        *self.field.write() = BTreeMap::from([(height, world)]);
+        self.trie_nodes.write().clear();
+        self.trie_roots.write().clear();
         let mut blockchain = self.blockchain.write();
-        blockchain.insert(*latest, block);
-        warn!(block_height = ?latest, "DB updated new block.");
-
+        self.insert_trie(height, trie);
+        let mut stored = self.receipts.write();
+        for receipt in receipts {
+            stored.insert(receipt.tx_hash, receipt);
+        }
+        let mut account_transactions = self.account_transactions.write();
+        for (address, entry) in block_history(height, &block.body) {
+            account_transactions.insert((address, entry.position), entry.hash);
+        }
+        blockchain.insert(height, block);
+        *self.snapshot_height.write() = Some(height);
+        *self.snapshot_parent.write() = Some(parent);
+        *latest = height;
         Ok(())
     }
 
-    fn remove_data(&self, height: u64) -> Result<(), Box<dyn std::error::Error>> {
-        let latest = self.latest.read();
-        if *latest != height {
-            return Err(Box::new(DatabaseError::CannotRemove));
+    fn snapshot_height(&self) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        Ok(*self.snapshot_height.read())
+    }
+
+    fn unwind_to(&self, height: u64) -> Result<Vec<Block>, Box<dyn std::error::Error>> {
+        let blocks = self.remove_blocks(height)?;
+        if !blocks.is_empty() {
+            warn!(height, removed = blocks.len(), "DB unwound blocks.");
         }
-        let cur = *latest;
+        Ok(blocks)
+    }
 
-        let mut accounts = self.accounts.write();
-        accounts.remove(&cur);
-        let mut field = self.field.write();
-        field.remove(&cur);
-        let mut blockchain = self.blockchain.write();
-        blockchain.remove(&cur);
-        let mut latest = self.latest.write();
-        *latest -= 1;
+    fn account_transactions(
+        &self,
+        address: &Address,
+        from: TxPosition,
+        limit: usize,
+    ) -> Result<Vec<AccountTransaction>, Box<dyn std::error::Error>> {
+        let account_transactions = self.account_transactions.read();
+        let entries = account_transactions
+            .range((*address, from)..)
+            .take_while(|((entry_address, _), _)| entry_address == address)
+            .take(limit)
+            .map(|((_, position), hash)| AccountTransaction {
+                position: *position,
+                hash: *hash,
+            })
+            .collect();
+        Ok(entries)
+    }
 
+    fn get_trie_node(
+        &self,
+        hash: B256,
+    ) -> Result<Option<(B256, B256)>, Box<dyn std::error::Error>> {
+        Ok(self
+            .trie_nodes
+            .read()
+            .get(&hash)
+            .map(|(children, _)| *children))
+    }
+
+    fn get_trie_root(&self, block_no: u64) -> Result<Option<B256>, Box<dyn std::error::Error>> {
+        Ok(self.trie_roots.read().get(&block_no).copied())
+    }
+
+    fn update_trie(
+        &self,
+        block_no: u64,
+        root: B256,
+        nodes: Vec<(B256, [u8; 64])>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.insert_trie(block_no, TrieUpdate { root, nodes });
         Ok(())
     }
 
+    fn get_receipt(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(TransactionReceipt, u64)>, Box<dyn std::error::Error>> {
+        let block_no = match self.get_transaction_by_hash(hash)? {
+            Some((_, block_no)) => block_no,
+            None => return Ok(None),
+        };
+        Ok(self
+            .receipts
+            .read()
+            .get(&hash)
+            .cloned()
+            .map(|receipt| (receipt, block_no)))
+    }
+
     fn get_transaction_by_hash(
         &self,
         hash: primitives::types::TxHash,
@@ -199,3 +440,106 @@ impl DatabaseTrait for Arc<InMemoryDB> {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{
+        append_blocks, check_account_changes, check_prune_sweeps_trie_nodes,
+        check_unwind_drops_trie_nodes, shared_address,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_unwind_removes_blocks_and_state_together() {
+        let db = Arc::new(InMemoryDB::genesis_state());
+        append_blocks(&db, 4);
+        let state_at_1 = db.get_state(1).unwrap();
+        let removed: Vec<Block> = (2..=4).map(|h| db.get_block(h).unwrap().unwrap()).collect();
+
+        // A pruned target fails without removing anything
+        db.prune_history(1).unwrap();
+        assert!(matches!(
+            db.unwind_to(0).unwrap_err().downcast_ref(),
+            Some(DatabaseError::Pruned(0))
+        ));
+        assert_eq!(db.latest_block_number(), 4);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 4);
+        assert!(db.get_receipt(removed[2].body[0].hash).unwrap().is_some());
+
+        let unwound = db.unwind_to(1).unwrap();
+        assert_eq!(
+            unwound.iter().map(|b| b.header.height).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(db.latest_block_number(), 1);
+        assert_eq!(db.get_state(1).unwrap(), state_at_1);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 1);
+        for block in removed.iter() {
+            let height = block.header.height;
+            assert!(db.get_block(height).is_err());
+            assert!(db.get_transaction_by_hash(block.body[0].hash).unwrap().is_none());
+            assert!(db.get_receipt(block.body[0].hash).unwrap().is_none());
+            assert!(db.get_trie_root(height).unwrap().is_none());
+        }
+        assert!(db.unwind_to(1).unwrap().is_empty());
+
+        // The chain grows again from the unwound height
+        append_blocks(&db, 1);
+        assert_eq!(db.latest_block_number(), 2);
+        assert_eq!(db.get_state(1).unwrap(), state_at_1);
+    }
+
+    #[test]
+    fn test_prune_sweeps_unreachable_trie_nodes() {
+        let db = Arc::new(InMemoryDB::genesis_state());
+        check_prune_sweeps_trie_nodes(&db);
+    }
+
+    #[test]
+    fn test_unwind_drops_unreachable_trie_nodes() {
+        let db = Arc::new(InMemoryDB::genesis_state());
+        check_unwind_drops_trie_nodes(&db);
+    }
+
+    #[test]
+    fn test_account_changes_of_one_block() {
+        check_account_changes(&Arc::new(InMemoryDB::genesis_state()));
+    }
+
+    #[test]
+    fn test_account_transactions_page_and_unwind() {
+        let db = Arc::new(InMemoryDB::genesis_state());
+        append_blocks(&db, 4);
+        let hashes: Vec<TxHash> = (1..=4)
+            .map(|h| db.get_block(h).unwrap().unwrap().body[0].hash)
+            .collect();
+
+        let page = db
+            .account_transactions(&shared_address(), TxPosition::default(), 3)
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|entry| entry.hash).collect::<Vec<_>>(),
+            hashes[..3]
+        );
+        assert_eq!(page[2].position, TxPosition::new(3, 0));
+        let page = db
+            .account_transactions(&shared_address(), TxPosition::new(3, 1), 3)
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!((page[0].position, page[0].hash), (TxPosition::new(4, 0), hashes[3]));
+        assert!(db
+            .account_transactions(&Address::random(), TxPosition::default(), 3)
+            .unwrap()
+            .is_empty());
+
+        db.unwind_to(2).unwrap();
+        let page = db
+            .account_transactions(&shared_address(), TxPosition::default(), 10)
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|entry| entry.hash).collect::<Vec<_>>(),
+            hashes[..2]
+        );
+    }
+}
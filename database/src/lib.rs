@@ -2,21 +2,39 @@ use std::sync::Arc;
 
 use primitives::{
     block::{Block, Header},
+    receipt::TransactionReceipt,
     transaction::SignedTransaction,
+    types::B256,
 };
 
-use crate::{immemorydb::InMemoryDB, mdbx::MDBX, traits::DatabaseTrait};
+use crate::{
+    history::{AccountTransaction, TxPosition},
+    immemorydb::InMemoryDB,
+    mdbx::MDBX,
+    prune::PruneMode,
+    segment::SegmentDB,
+    traits::{DatabaseTrait, TrieUpdate},
+};
 
+pub mod cache;
+pub mod changeset;
 pub mod error;
 pub mod genesis;
+pub mod history;
 pub mod immemorydb;
 pub mod mdbx;
+pub mod prune;
+pub mod schema;
+pub mod segment;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod traits;
 
 #[derive(Clone, Debug)]
 pub enum DBImpl {
     MDBX(MDBX),
     InMemoryDB(Arc<InMemoryDB>),
+    SegmentDB(SegmentDB),
 }
 
 impl DatabaseTrait for DBImpl {
@@ -24,6 +42,7 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.latest_block_number(),
             DBImpl::InMemoryDB(db) => db.latest_block_number(),
+            DBImpl::SegmentDB(db) => db.latest_block_number(),
         }
     }
 
@@ -34,6 +53,7 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.basic(address),
             DBImpl::InMemoryDB(db) => db.basic(address),
+            DBImpl::SegmentDB(db) => db.basic(address),
         }
     }
 
@@ -52,6 +72,29 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.get_state(block_no),
             DBImpl::InMemoryDB(db) => db.get_state(block_no),
+            DBImpl::SegmentDB(db) => db.get_state(block_no),
+        }
+    }
+
+    fn get_account_changes(
+        &self,
+        block_no: u64,
+    ) -> Result<changeset::AccountChangeSet, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.get_account_changes(block_no),
+            DBImpl::InMemoryDB(db) => db.get_account_changes(block_no),
+            DBImpl::SegmentDB(db) => db.get_account_changes(block_no),
+        }
+    }
+
+    fn get_world(
+        &self,
+        block_no: u64,
+    ) -> Result<Option<primitives::world::World>, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.get_world(block_no),
+            DBImpl::InMemoryDB(db) => db.get_world(block_no),
+            DBImpl::SegmentDB(db) => db.get_world(block_no),
         }
     }
 
@@ -62,6 +105,7 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.get_block(block_no),
             DBImpl::InMemoryDB(db) => db.get_block(block_no),
+            DBImpl::SegmentDB(db) => db.get_block(block_no),
         }
     }
 
@@ -72,21 +116,29 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.get_header(block_no),
             DBImpl::InMemoryDB(db) => db.get_header(block_no),
+            DBImpl::SegmentDB(db) => db.get_header(block_no),
         }
     }
 
     fn update(
         &self,
-        new_account_state: std::collections::HashMap<
+        changed_accounts: std::collections::HashMap<
             primitives::types::Address,
             primitives::types::Account,
         >,
         new_field_state: primitives::world::World,
         new_block: primitives::block::Block,
+        trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         match self {
-            DBImpl::MDBX(db) => db.update(new_account_state, new_field_state, new_block),
-            DBImpl::InMemoryDB(db) => db.update(new_account_state, new_field_state, new_block),
+            DBImpl::MDBX(db) => db.update(changed_accounts, new_field_state, new_block, trie, receipts),
+            DBImpl::InMemoryDB(db) => {
+                db.update(changed_accounts, new_field_state, new_block, trie, receipts)
+            }
+            DBImpl::SegmentDB(db) => {
+                db.update(changed_accounts, new_field_state, new_block, trie, receipts)
+            }
         }
     }
 
@@ -94,6 +146,50 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.get_latest_block_header(),
             DBImpl::InMemoryDB(db) => db.get_latest_block_header(),
+            DBImpl::SegmentDB(db) => db.get_latest_block_header(),
+        }
+    }
+
+    fn get_trie_node(
+        &self,
+        hash: B256,
+    ) -> Result<Option<(B256, B256)>, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.get_trie_node(hash),
+            DBImpl::InMemoryDB(db) => db.get_trie_node(hash),
+            DBImpl::SegmentDB(db) => db.get_trie_node(hash),
+        }
+    }
+
+    fn get_trie_root(&self, block_no: u64) -> Result<Option<B256>, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.get_trie_root(block_no),
+            DBImpl::InMemoryDB(db) => db.get_trie_root(block_no),
+            DBImpl::SegmentDB(db) => db.get_trie_root(block_no),
+        }
+    }
+
+    fn update_trie(
+        &self,
+        block_no: u64,
+        root: B256,
+        nodes: Vec<(B256, [u8; 64])>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.update_trie(block_no, root, nodes),
+            DBImpl::InMemoryDB(db) => db.update_trie(block_no, root, nodes),
+            DBImpl::SegmentDB(db) => db.update_trie(block_no, root, nodes),
+        }
+    }
+
+    fn get_receipt(
+        &self,
+        hash: primitives::types::TxHash,
+    ) -> Result<Option<(TransactionReceipt, u64)>, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.get_receipt(hash),
+            DBImpl::InMemoryDB(db) => db.get_receipt(hash),
+            DBImpl::SegmentDB(db) => db.get_receipt(hash),
         }
     }
 
@@ -104,20 +200,83 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.get_block_by_hash(hash),
             DBImpl::InMemoryDB(db) => db.get_block_by_hash(hash),
+            DBImpl::SegmentDB(db) => db.get_block_by_hash(hash),
+        }
+    }
+
+    fn prune_mode(&self) -> PruneMode {
+        match self {
+            DBImpl::MDBX(db) => db.prune_mode(),
+            DBImpl::InMemoryDB(db) => db.prune_mode(),
+            DBImpl::SegmentDB(db) => db.prune_mode(),
         }
     }
 
-    fn remove_datas(&self, height: u64) -> Result<(), Box<dyn std::error::Error>> {
+    fn oldest_state(&self) -> Result<u64, Box<dyn std::error::Error>> {
         match self {
-            DBImpl::MDBX(db) => db.remove_datas(height),
-            DBImpl::InMemoryDB(db) => db.remove_datas(height),
+            DBImpl::MDBX(db) => db.oldest_state(),
+            DBImpl::InMemoryDB(db) => db.oldest_state(),
+            DBImpl::SegmentDB(db) => db.oldest_state(),
         }
     }
 
-    fn remove_data(&self, height: u64) -> Result<(), Box<dyn std::error::Error>> {
+    fn prune_history(&self, before: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.prune_history(before),
+            DBImpl::InMemoryDB(db) => db.prune_history(before),
+            DBImpl::SegmentDB(db) => db.prune_history(before),
+        }
+    }
+
+    fn import_snapshot(
+        &self,
+        accounts: std::collections::HashMap<
+            primitives::types::Address,
+            primitives::types::Account,
+        >,
+        world: primitives::world::World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Header,
+        trie: TrieUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.import_snapshot(accounts, world, block, receipts, parent, trie),
+            DBImpl::InMemoryDB(db) => {
+                db.import_snapshot(accounts, world, block, receipts, parent, trie)
+            }
+            DBImpl::SegmentDB(db) => {
+                db.import_snapshot(accounts, world, block, receipts, parent, trie)
+            }
+        }
+    }
+
+    fn snapshot_height(&self) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.snapshot_height(),
+            DBImpl::InMemoryDB(db) => db.snapshot_height(),
+            DBImpl::SegmentDB(db) => db.snapshot_height(),
+        }
+    }
+
+    fn unwind_to(&self, height: u64) -> Result<Vec<Block>, Box<dyn std::error::Error>> {
+        match self {
+            DBImpl::MDBX(db) => db.unwind_to(height),
+            DBImpl::InMemoryDB(db) => db.unwind_to(height),
+            DBImpl::SegmentDB(db) => db.unwind_to(height),
+        }
+    }
+
+    fn account_transactions(
+        &self,
+        address: &primitives::types::Address,
+        from: TxPosition,
+        limit: usize,
+    ) -> Result<Vec<AccountTransaction>, Box<dyn std::error::Error>> {
         match self {
-            DBImpl::MDBX(db) => db.remove_data(height),
-            DBImpl::InMemoryDB(db) => db.remove_data(height),
+            DBImpl::MDBX(db) => db.account_transactions(address, from, limit),
+            DBImpl::InMemoryDB(db) => db.account_transactions(address, from, limit),
+            DBImpl::SegmentDB(db) => db.account_transactions(address, from, limit),
         }
     }
 
@@ -128,6 +287,7 @@ impl DatabaseTrait for DBImpl {
         match self {
             DBImpl::MDBX(db) => db.get_transaction_by_hash(hash),
             DBImpl::InMemoryDB(db) => db.get_transaction_by_hash(hash),
+            DBImpl::SegmentDB(db) => db.get_transaction_by_hash(hash),
         }
     }
 }
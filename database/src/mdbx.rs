@@ -1,23 +1,41 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use libmdbx::{
-    orm::{Database, DatabaseChart, Decodable, Encodable},
+    RW, TransactionKind,
+    orm::{Database, DatabaseChart, Decodable, Encodable, Transaction},
     table, table_info,
 };
 use once_cell::sync::Lazy;
 use primitives::{
-    block::{Block, Header},
+    block::{Block, BlockBody, Header},
+    receipt::TransactionReceipt,
     transaction::SignedTransaction,
-    types::{Account, Address, BlockHash, TxHash},
+    types::{Account, Address, B256, BlockHash, ChainId, TxHash},
     world::World,
 };
 use tracing::{info, warn};
 
-use crate::{error::DatabaseError, genesis::genesis_accounts_info, traits::DatabaseTrait};
+use crate::{
+    changeset::{AccountChangeSet, changes_of},
+    error::DatabaseError,
+    genesis::genesis_accounts_info,
+    history::{AccountTransaction, TxPosition, block_history},
+    prune::{PruneMode, TrieNodeStore, add_trie_root, release_trie_root},
+    schema::{
+        CHAIN_ID_KEY, ChainMetadata, GENESIS_HASH_KEY, SCHEMA_VERSION, SCHEMA_VERSION_KEY,
+        SNAPSHOT_HEIGHT_KEY, decode_b256, decode_u32, decode_u64, get_metadata, migrate,
+        put_metadata, unversioned_schema,
+    },
+    traits::{DatabaseTrait, TrieUpdate},
+};
 
 pub type BlockNo = u64;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct DBAdress {
     pub block_no: BlockNo,
     pub address: Address,
@@ -58,16 +76,102 @@ impl Decodable for DBAdress {
     }
 }
 
+/// Key of the account history: address, then block, then index in the block
+#[derive(Debug, Clone, Copy)]
+pub struct AccountTxKey {
+    pub address: Address,
+    pub position: TxPosition,
+}
+
+impl Encodable for AccountTxKey {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        let mut raw: Vec<u8> = Vec::new();
+        raw.extend_from_slice(&self.address.encode());
+        raw.extend_from_slice(&self.position.block_no.to_be_bytes());
+        raw.extend_from_slice(&self.position.index.to_be_bytes());
+        raw
+    }
+}
+
+impl Decodable for AccountTxKey {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        if b.len() != 32 {
+            return Err(anyhow::anyhow!("invalid account history key"));
+        }
+        let mut addr_raw = [0u8; 20];
+        addr_raw.copy_from_slice(&b[0..20]);
+        let mut u64_raw = [0u8; 8];
+        u64_raw.copy_from_slice(&b[20..28]);
+        let mut u32_raw = [0u8; 4];
+        u32_raw.copy_from_slice(&b[28..32]);
+        Ok(AccountTxKey {
+            address: Address::from_byte(addr_raw),
+            position: TxPosition::new(u64::from_be_bytes(u64_raw), u32::from_be_bytes(u32_raw)),
+        })
+    }
+}
+
+/// Value of an account before a block changed it. `None` if the block created it.
+#[derive(Debug)]
+pub struct AccountBefore(pub Option<Account>);
+
+impl Encodable for AccountBefore {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        match self.0 {
+            Some(account) => {
+                let mut raw = vec![1u8];
+                raw.extend_from_slice(&account.encode());
+                raw
+            }
+            None => vec![0u8],
+        }
+    }
+}
+
+impl Decodable for AccountBefore {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match b.first() {
+            Some(0) => Ok(AccountBefore(None)),
+            Some(1) => Ok(AccountBefore(Some(<Account as Decodable>::decode(&b[1..])?))),
+            _ => Err(anyhow::anyhow!("invalid account change")),
+        }
+    }
+}
+
 table!(
-    /// State Table
+    /// Legacy state table holding every account for every block. Only read by the migration.
     (Basic) DBAdress => Account
 );
 
 table!(
-    /// Block Table
+    /// Account state at the latest block
+    (PlainAccounts) Address => Account
+);
+
+table!(
+    /// Per-block change sets: (block, address) => account before the block
+    (AccountChangeSets) DBAdress => AccountBefore
+);
+
+table!(
+    /// Legacy table holding whole blocks. Only read by the migration.
     (Blocks) BlockNo => Block
 );
 
+table!(
+    /// Block headers, read without decoding the transactions
+    (Headers) BlockNo => Header
+);
+
+table!(
+    /// Block transactions
+    (Bodies) BlockNo => BlockBody
+);
+
 table!(
     /// BlockHash
     (BlockByHash) BlockHash => BlockNo
@@ -83,14 +187,54 @@ table!(
     (Transactions) TxHash => BlockNo
 );
 
+table!(
+    /// Transactions sent or received by an account: (address, block, index) => hash
+    (AccountTransactions) AccountTxKey => [u8; 32]
+);
+
+table!(
+    /// State trie nodes: hash => left || right
+    (TrieNodes) [u8; 32] => [u8; 64]
+);
+
+table!(
+    /// Number of stored trie nodes and block roots pointing at each trie node, see `TrieNodeStore`
+    (TrieNodeRefs) [u8; 32] => u64
+);
+
+table!(
+    /// State trie root of each block
+    (TrieRoots) BlockNo => [u8; 32]
+);
+
+table!(
+    /// Transaction receipts
+    (Receipts) TxHash => TransactionReceipt
+);
+
+table!(
+    /// Schema version and chain identity, see `schema`
+    (Metadata) String => Vec<u8>
+);
+
 pub static TABLES: Lazy<Arc<DatabaseChart>> = Lazy::new(|| {
     Arc::new(
         [
             table_info!(Basic),
+            table_info!(PlainAccounts),
+            table_info!(AccountChangeSets),
             table_info!(Blocks),
+            table_info!(Headers),
+            table_info!(Bodies),
             table_info!(States),
             table_info!(Transactions),
+            table_info!(AccountTransactions),
             table_info!(BlockByHash),
+            table_info!(TrieNodes),
+            table_info!(TrieNodeRefs),
+            table_info!(TrieRoots),
+            table_info!(Receipts),
+            table_info!(Metadata),
         ]
         .into_iter()
         .collect(),
@@ -100,56 +244,184 @@ pub static TABLES: Lazy<Arc<DatabaseChart>> = Lazy::new(|| {
 #[derive(Clone, Debug)]
 pub struct MDBX {
     inner: Arc<Database>,
-}
-
-pub fn get_db_path() -> PathBuf {
-    let mut pathbuf = dirs::data_dir().expect("no data dir");
-    pathbuf.push("pint");
-    pathbuf.push("data");
-    pathbuf
+    prune_mode: PruneMode,
 }
 
 impl MDBX {
-    pub fn new() -> Self {
-        let pathbuf = get_db_path();
-        let db = Arc::new(libmdbx::orm::Database::create(Some(pathbuf), &TABLES).unwrap());
+    /// Opens the database, upgrading an older schema in place.
+    /// Fails if the schema is unknown or the database belongs to another chain.
+    pub fn new(path: impl Into<PathBuf>, chain_id: ChainId) -> Result<Self, DatabaseError> {
+        let pathbuf = path.into();
+        let db = libmdbx::orm::Database::create(Some(pathbuf), &TABLES)
+            .map_err(|_| DatabaseError::DBError)?;
 
-        let mdbx = MDBX { inner: db };
+        let mdbx = MDBX {
+            inner: Arc::new(db),
+            prune_mode: PruneMode::default(),
+        };
+        mdbx.check_schema()?;
 
-        let tx = mdbx.inner.begin_read().unwrap();
-        let mut cursor = tx.cursor::<Blocks>().unwrap();
-        let is_empty = cursor.first().unwrap().is_none();
+        let tx = mdbx
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let mut cursor = tx.cursor::<Headers>().map_err(|_| DatabaseError::DBError)?;
+        let is_empty = cursor
+            .first()
+            .map_err(|_| DatabaseError::DBError)?
+            .is_none();
         drop(cursor);
         drop(tx);
 
         if is_empty {
             let genesis_block = Block::genesis_block();
+            let genesis_hash = genesis_block.header.calculate_hash();
 
-            let tx = mdbx.inner.begin_readwrite().unwrap();
-            {
-                let mut cursor = tx.cursor::<Blocks>().unwrap();
-                cursor.upsert(0, genesis_block).unwrap();
-            }
+            let tx = mdbx
+                .inner
+                .begin_readwrite()
+                .map_err(|_| DatabaseError::DBError)?;
+            tx.upsert::<BlockByHash>(genesis_hash, 0)
+                .map_err(|_| DatabaseError::DBError)?;
+            put_block(&tx, 0, genesis_block)?;
             {
-                let mut cursor = tx.cursor::<States>().unwrap();
-                cursor.upsert(0, World::new()).unwrap();
+                let mut cursor = tx.cursor::<States>().map_err(|_| DatabaseError::DBError)?;
+                cursor
+                    .upsert(0, World::new())
+                    .map_err(|_| DatabaseError::DBError)?;
             }
-            tx.commit().unwrap();
+            tx.commit().map_err(|_| DatabaseError::DBError)?;
 
             info!("MDBX: Genesis block initialized.");
         } else {
             info!("MDBX: DB already initialized, skipping genesis.");
         }
 
-        mdbx
+        mdbx.check_chain(chain_id)?;
+        Ok(mdbx)
     }
 
-    pub fn genesis_state() -> Self {
-        let mut db = Self::new();
-        for (address, account) in genesis_accounts_info() {
-            db.add_account(address, account).unwrap();
+    // Brings the schema to SCHEMA_VERSION, or refuses a database written by a newer version
+    fn check_schema(&self) -> Result<(), DatabaseError> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let stored = get_metadata(&tx, SCHEMA_VERSION_KEY)?;
+        // Databases before the Headers table hold their blocks in Blocks
+        let mut headers = tx.cursor::<Headers>().map_err(|_| DatabaseError::DBError)?;
+        let mut blocks = tx.cursor::<Blocks>().map_err(|_| DatabaseError::DBError)?;
+        // A block that does not decode still counts, unversioned_schema refuses it
+        let is_empty = headers
+            .first()
+            .map_err(|_| DatabaseError::DBError)?
+            .is_none()
+            && matches!(blocks.first(), Ok(None));
+        let version = match stored.clone() {
+            Some(raw) => decode_u32(SCHEMA_VERSION_KEY, raw)?,
+            None if is_empty => SCHEMA_VERSION,
+            None => unversioned_schema(&tx)?,
+        };
+        drop(headers);
+        drop(blocks);
+        drop(tx);
+
+        if version > SCHEMA_VERSION {
+            return Err(DatabaseError::UnsupportedSchemaVersion {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        if version < SCHEMA_VERSION {
+            return migrate(&self.inner, version);
+        }
+        if stored.is_none() {
+            let tx = self
+                .inner
+                .begin_readwrite()
+                .map_err(|_| DatabaseError::DBError)?;
+            put_metadata(&tx, SCHEMA_VERSION_KEY, version.to_be_bytes().to_vec())?;
+            tx.commit().map_err(|_| DatabaseError::DBError)?;
         }
-        db
+        Ok(())
+    }
+
+    // Records the chain identity on first use and compares it afterwards
+    fn check_chain(&self, chain_id: ChainId) -> Result<(), DatabaseError> {
+        let expected = Block::genesis_block().header.calculate_hash().hash();
+        let tx = self
+            .inner
+            .begin_readwrite()
+            .map_err(|_| DatabaseError::DBError)?;
+
+        let genesis_hash = match get_metadata(&tx, GENESIS_HASH_KEY)? {
+            Some(raw) => decode_b256(GENESIS_HASH_KEY, raw)?,
+            None => {
+                let genesis = tx
+                    .get::<Headers>(0)
+                    .map_err(|_| DatabaseError::DBError)?
+                    .ok_or(DatabaseError::DataNotExists)?;
+                let hash = genesis.calculate_hash().hash();
+                put_metadata(&tx, GENESIS_HASH_KEY, hash.to_vec())?;
+                hash
+            }
+        };
+        if genesis_hash != expected {
+            return Err(DatabaseError::GenesisMismatch {
+                expected,
+                found: genesis_hash,
+            });
+        }
+
+        match get_metadata(&tx, CHAIN_ID_KEY)? {
+            Some(raw) => {
+                let found = decode_u64(CHAIN_ID_KEY, raw)?;
+                if found != chain_id {
+                    return Err(DatabaseError::ChainIdMismatch {
+                        expected: chain_id,
+                        found,
+                    });
+                }
+            }
+            None => put_metadata(&tx, CHAIN_ID_KEY, chain_id.to_be_bytes().to_vec())?,
+        }
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
+        Ok(())
+    }
+
+    pub fn metadata(&self) -> Result<ChainMetadata, DatabaseError> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let get = |key: &str| {
+            get_metadata(&tx, key)?.ok_or(DatabaseError::InvalidMetadata(key.to_string()))
+        };
+        Ok(ChainMetadata {
+            schema_version: decode_u32(SCHEMA_VERSION_KEY, get(SCHEMA_VERSION_KEY)?)?,
+            genesis_hash: decode_b256(GENESIS_HASH_KEY, get(GENESIS_HASH_KEY)?)?,
+            chain_id: decode_u64(CHAIN_ID_KEY, get(CHAIN_ID_KEY)?)?,
+        })
+    }
+
+    pub fn with_prune_mode(mut self, prune_mode: PruneMode) -> Self {
+        self.prune_mode = prune_mode;
+        self
+    }
+
+    pub fn genesis_state(
+        path: impl Into<PathBuf>,
+        chain_id: ChainId,
+    ) -> Result<Self, DatabaseError> {
+        let mut db = Self::new(path, chain_id)?;
+        // Accounts live in the latest state, so they must not be reset once blocks exist
+        if db.latest_block_number() == 0 {
+            for (address, account) in genesis_accounts_info() {
+                db.add_account(address, account)
+                    .map_err(|_| DatabaseError::DBError)?;
+            }
+        }
+        Ok(db)
     }
 
     pub fn add_account(
@@ -161,19 +433,234 @@ impl MDBX {
             .inner
             .begin_readwrite()
             .map_err(|_| DatabaseError::DBError)?;
-        let mut cursor = tx.cursor::<Basic>().map_err(|_| DatabaseError::DBError)?;
+        let mut cursor = tx
+            .cursor::<PlainAccounts>()
+            .map_err(|_| DatabaseError::DBError)?;
         cursor
-            .upsert(DBAdress::new(address, 0), account)
+            .upsert(address, account)
             .map_err(|_| DatabaseError::DBError)?;
         tx.commit().unwrap();
         Ok(())
     }
 }
 
+fn plain_accounts<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+) -> Result<HashMap<Address, Account>, DatabaseError> {
+    let cursor = tx
+        .cursor::<PlainAccounts>()
+        .map_err(|_| DatabaseError::DBError)?;
+    let mut accounts = HashMap::new();
+    for item in cursor.walk(None) {
+        let (address, account) = item.map_err(|_| DatabaseError::DBError)?;
+        accounts.insert(address, account);
+    }
+    Ok(accounts)
+}
+
+fn read_block<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    block_no: u64,
+) -> Result<Option<Block>, DatabaseError> {
+    let header = match tx
+        .get::<Headers>(block_no)
+        .map_err(|_| DatabaseError::DBError)?
+    {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+    let body = tx
+        .get::<Bodies>(block_no)
+        .map_err(|_| DatabaseError::DBError)?
+        .ok_or(DatabaseError::DataNotExists)?;
+    Ok(Some(Block {
+        header,
+        body: body.0,
+    }))
+}
+
+pub(crate) fn put_block(
+    tx: &Transaction<'_, RW>,
+    block_no: u64,
+    block: Block,
+) -> Result<(), DatabaseError> {
+    tx.upsert::<Bodies>(block_no, BlockBody(block.body))
+        .map_err(|_| DatabaseError::DBError)?;
+    tx.upsert::<Headers>(block_no, block.header)
+        .map_err(|_| DatabaseError::DBError)
+}
+
+pub(crate) fn put_trie(
+    mut tx: &Transaction<'_, RW>,
+    block_no: u64,
+    trie: TrieUpdate,
+) -> Result<(), DatabaseError> {
+    add_trie_root(&mut tx, trie.root, trie.nodes)?;
+    let replaced = tx
+        .get::<TrieRoots>(block_no)
+        .map_err(|_| DatabaseError::DBError)?;
+    tx.upsert::<TrieRoots>(block_no, trie.root.0)
+        .map_err(|_| DatabaseError::DBError)?;
+    if let Some(replaced) = replaced {
+        release_trie_root(&mut tx, B256::from(replaced))?;
+    }
+    Ok(())
+}
+
+// Drops the trie root of `block_no` along with the nodes only it reached
+fn delete_trie_root(mut tx: &Transaction<'_, RW>, block_no: u64) -> Result<(), DatabaseError> {
+    let root = tx
+        .get::<TrieRoots>(block_no)
+        .map_err(|_| DatabaseError::DBError)?;
+    if let Some(root) = root {
+        tx.delete::<TrieRoots>(block_no, None)
+            .map_err(|_| DatabaseError::DBError)?;
+        release_trie_root(&mut tx, B256::from(root))?;
+    }
+    Ok(())
+}
+
+impl TrieNodeStore for &Transaction<'_, RW> {
+    type Error = DatabaseError;
+
+    fn trie_node(&mut self, hash: B256) -> Result<Option<(B256, B256)>, Self::Error> {
+        let raw = self
+            .get::<TrieNodes>(hash.0)
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(raw.map(|raw| (B256::from_slice(&raw[..32]), B256::from_slice(&raw[32..]))))
+    }
+
+    fn trie_refs(&mut self, hash: B256) -> Result<Option<u64>, Self::Error> {
+        self.get::<TrieNodeRefs>(hash.0)
+            .map_err(|_| DatabaseError::DBError)
+    }
+
+    fn put_trie_node(
+        &mut self,
+        hash: B256,
+        (left, right): (B256, B256),
+        refs: u64,
+    ) -> Result<(), Self::Error> {
+        let mut raw = [0u8; 64];
+        raw[..32].copy_from_slice(left.as_slice());
+        raw[32..].copy_from_slice(right.as_slice());
+        self.upsert::<TrieNodes>(hash.0, raw)
+            .map_err(|_| DatabaseError::DBError)?;
+        self.set_trie_refs(hash, refs)
+    }
+
+    fn set_trie_refs(&mut self, hash: B256, refs: u64) -> Result<(), Self::Error> {
+        self.upsert::<TrieNodeRefs>(hash.0, refs)
+            .map_err(|_| DatabaseError::DBError)
+    }
+
+    fn delete_trie_node(&mut self, hash: B256) -> Result<(), Self::Error> {
+        self.delete::<TrieNodes>(hash.0, None)
+            .map_err(|_| DatabaseError::DBError)?;
+        self.delete::<TrieNodeRefs>(hash.0, None)
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(())
+    }
+}
+
+// Indexes the transactions of the block stored at `block_no` under their sender and recipient
+pub(crate) fn put_history(
+    tx: &Transaction<'_, RW>,
+    block_no: u64,
+    body: &[SignedTransaction],
+) -> Result<(), DatabaseError> {
+    for (address, entry) in block_history(block_no, body) {
+        let key = AccountTxKey {
+            address,
+            position: entry.position,
+        };
+        tx.upsert::<AccountTransactions>(key, entry.hash.hash().0)
+            .map_err(|_| DatabaseError::DBError)?;
+    }
+    Ok(())
+}
+
+// Everything unwind_to removes, inside the caller's transaction. Nothing is visible until it commits.
+fn unwind(tx: &Transaction<'_, RW>, height: u64) -> Result<Vec<Block>, DatabaseError> {
+    // The chain is left at the state of `height`
+    let oldest_state = {
+        let mut cursor = tx.cursor::<States>().map_err(|_| DatabaseError::DBError)?;
+        cursor
+            .first()
+            .map_err(|_| DatabaseError::DBError)?
+            .map_or(0, |(block_no, _)| block_no)
+    };
+    if height < oldest_state {
+        return Err(DatabaseError::Pruned(height));
+    }
+
+    let mut blocks = Vec::new();
+    let mut changes = Vec::new();
+    {
+        let cursor = tx.cursor::<Headers>().map_err(|_| DatabaseError::DBError)?;
+        for item in cursor.walk(Some(height + 1)) {
+            let (block_no, _) = item.map_err(|_| DatabaseError::DBError)?;
+            blocks.push(read_block(tx, block_no)?.ok_or(DatabaseError::DataNotExists)?);
+        }
+        let cursor = tx
+            .cursor::<AccountChangeSets>()
+            .map_err(|_| DatabaseError::DBError)?;
+        for item in cursor.walk(Some(DBAdress::new(Address::min(), height + 1))) {
+            changes.push(item.map_err(|_| DatabaseError::DBError)?);
+        }
+    }
+
+    // The first change of an address above `height` holds its value at `height`
+    let mut reverted = HashSet::new();
+    for (key, before) in changes {
+        if reverted.insert(key.address) {
+            match before.0 {
+                Some(account) => tx
+                    .upsert::<PlainAccounts>(key.address, account)
+                    .map_err(|_| DatabaseError::DBError)?,
+                None => {
+                    tx.delete::<PlainAccounts>(key.address, None)
+                        .map_err(|_| DatabaseError::DBError)?;
+                }
+            }
+        }
+        tx.delete::<AccountChangeSets>(key, None)
+            .map_err(|_| DatabaseError::DBError)?;
+    }
+
+    for block in blocks.iter() {
+        let block_no = block.header.height;
+        tx.delete::<Headers>(block_no, None)
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.delete::<Bodies>(block_no, None)
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.delete::<BlockByHash>(block.header().calculate_hash(), None)
+            .map_err(|_| DatabaseError::DBError)?;
+        for signed_tx in block.body.iter() {
+            tx.delete::<Transactions>(signed_tx.hash, None)
+                .map_err(|_| DatabaseError::DBError)?;
+            tx.delete::<Receipts>(signed_tx.hash, None)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        for (address, entry) in block_history(block_no, &block.body) {
+            let key = AccountTxKey {
+                address,
+                position: entry.position,
+            };
+            tx.delete::<AccountTransactions>(key, None)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        tx.delete::<States>(block_no, None)
+            .map_err(|_| DatabaseError::DBError)?;
+        delete_trie_root(tx, block_no)?;
+    }
+    Ok(blocks)
+}
+
 impl DatabaseTrait for MDBX {
     fn latest_block_number(&self) -> u64 {
         let tx = self.inner.begin_read().unwrap();
-        let mut cursor = tx.cursor::<Blocks>().unwrap();
+        let mut cursor = tx.cursor::<Headers>().unwrap();
 
         if let Some((block_no, _)) = cursor.last().unwrap() {
             block_no
@@ -187,9 +674,7 @@ impl DatabaseTrait for MDBX {
             .inner
             .begin_read()
             .map_err(|_| DatabaseError::DBError)?;
-        let latest = self.latest_block_number();
-        let db_addr = DBAdress::new(*address, latest);
-        match tx.get::<Basic>(db_addr) {
+        match tx.get::<PlainAccounts>(*address) {
             Ok(res) => Ok(res),
             Err(_e) => return Err(Box::new(DatabaseError::DBError)),
         }
@@ -209,19 +694,29 @@ impl DatabaseTrait for MDBX {
             .inner
             .begin_read()
             .map_err(|_| DatabaseError::DBError)?;
-        let cursor = tx.cursor::<Basic>().map_err(|_| DatabaseError::DBError)?;
-        let mut accounts = HashMap::new();
-
-        let start_key = DBAdress::new(Address::min(), block_no);
-
-        let mut iter = cursor.walk(Some(start_key));
+        if block_no > self.latest_block_number() {
+            return Ok((None, None));
+        }
+        if block_no < self.oldest_state()? {
+            return Err(Box::new(DatabaseError::Pruned(block_no)));
+        }
+        let mut accounts = plain_accounts(&tx)?;
 
-        while let Some(Ok((key, account))) = iter.next() {
-            if key.block_no == block_no {
-                accounts.insert(key.address, account);
-            } else {
-                break;
+        // Undo the blocks after block_no. The first change found for an address,
+        // walking up from block_no + 1, holds its value at block_no.
+        let cursor = tx
+            .cursor::<AccountChangeSets>()
+            .map_err(|_| DatabaseError::DBError)?;
+        let mut reverted = HashSet::new();
+        for item in cursor.walk(Some(DBAdress::new(Address::min(), block_no + 1))) {
+            let (key, before) = item.map_err(|_| DatabaseError::DBError)?;
+            if !reverted.insert(key.address) {
+                continue;
             }
+            match before.0 {
+                Some(account) => accounts.insert(key.address, account),
+                None => accounts.remove(&key.address),
+            };
         }
         let world = tx
             .get::<States>(block_no)
@@ -229,23 +724,53 @@ impl DatabaseTrait for MDBX {
         Ok((Some(accounts), world))
     }
 
-    fn get_block(
+    fn get_account_changes(
         &self,
         block_no: u64,
-    ) -> Result<Option<Block>, Box<dyn std::error::Error + 'static>> {
+    ) -> Result<AccountChangeSet, Box<dyn std::error::Error>> {
+        if block_no <= self.oldest_state()? {
+            return Err(Box::new(DatabaseError::Pruned(block_no.saturating_sub(1))));
+        }
         let tx = self
             .inner
             .begin_read()
             .map_err(|_| DatabaseError::DBError)?;
-        match tx.get::<Blocks>(block_no) {
-            Ok(res) => match res {
-                Some(block) => return Ok(Some(block)),
-                None => return Ok(None),
-            },
-            Err(_e) => {
-                return Err(Box::new(DatabaseError::DBError));
+        let cursor = tx
+            .cursor::<AccountChangeSets>()
+            .map_err(|_| DatabaseError::DBError)?;
+        let mut changes = AccountChangeSet::new();
+        for item in cursor.walk(Some(DBAdress::new(Address::min(), block_no))) {
+            let (key, before) = item.map_err(|_| DatabaseError::DBError)?;
+            if key.block_no != block_no {
+                break;
             }
+            changes.insert(key.address, before.0);
         }
+        Ok(changes)
+    }
+
+    fn get_world(&self, block_no: u64) -> Result<Option<World>, Box<dyn std::error::Error>> {
+        if block_no < self.oldest_state()? {
+            return Err(Box::new(DatabaseError::Pruned(block_no)));
+        }
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(tx
+            .get::<States>(block_no)
+            .map_err(|_| DatabaseError::DBError)?)
+    }
+
+    fn get_block(
+        &self,
+        block_no: u64,
+    ) -> Result<Option<Block>, Box<dyn std::error::Error + 'static>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(read_block(&tx, block_no)?)
     }
 
     fn get_block_by_hash(
@@ -258,7 +783,7 @@ impl DatabaseTrait for MDBX {
             .map_err(|_| DatabaseError::DBError)?;
         match tx.get::<BlockByHash>(hash) {
             Ok(res) => match res {
-                Some(block_no) => self.get_block(block_no),
+                Some(block_no) => Ok(read_block(&tx, block_no)?),
                 None => Err(Box::new(DatabaseError::DataNotExists)),
             },
             Err(_e) => {
@@ -271,20 +796,23 @@ impl DatabaseTrait for MDBX {
         &self,
         block_no: u64,
     ) -> Result<Option<Header>, Box<dyn std::error::Error + 'static>> {
-        match self.get_block(block_no) {
-            Ok(block) => match block {
-                Some(block) => Ok(Some(block.header().clone())),
-                None => Ok(None),
-            },
-            Err(e) => Err(e),
-        }
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let header = tx
+            .get::<Headers>(block_no)
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(header)
     }
 
     fn update(
         &self,
-        new_account_state: std::collections::HashMap<Address, Account>,
+        changed_accounts: std::collections::HashMap<Address, Account>,
         new_field_state: World,
         new_block: Block,
+        trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let latest_bno = self.latest_block_number();
         let new_latest = latest_bno + 1;
@@ -293,10 +821,25 @@ impl DatabaseTrait for MDBX {
             .inner
             .begin_readwrite()
             .map_err(|_| DatabaseError::DBError)?;
-        let mut cursor = tx.cursor::<Basic>().map_err(|_| DatabaseError::DBError)?;
-        for (address, account) in new_account_state.iter() {
+        // Only the accounts changed by the block are read and written
+        let changes = changes_of(&changed_accounts, |address| {
+            tx.get::<PlainAccounts>(*address)
+                .map_err(|_| DatabaseError::DBError)
+        })?;
+        let mut cursor = tx
+            .cursor::<AccountChangeSets>()
+            .map_err(|_| DatabaseError::DBError)?;
+        for (address, before) in changes.iter() {
+            cursor
+                .upsert(DBAdress::new(*address, new_latest), AccountBefore(*before))
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        let mut cursor = tx
+            .cursor::<PlainAccounts>()
+            .map_err(|_| DatabaseError::DBError)?;
+        for address in changes.keys() {
             cursor
-                .upsert(DBAdress::new(*address, new_latest), *account)
+                .upsert(*address, changed_accounts[address])
                 .map_err(|_| DatabaseError::DBError)?;
         }
 
@@ -323,62 +866,278 @@ impl DatabaseTrait for MDBX {
             .upsert(new_latest, new_field_state)
             .map_err(|_| DatabaseError::DBError)?;
 
-        let mut cursor = tx.cursor::<Blocks>().map_err(|_| DatabaseError::DBError)?;
-        cursor
-            .upsert(new_latest, new_block)
+        let mut cursor = tx
+            .cursor::<Receipts>()
             .map_err(|_| DatabaseError::DBError)?;
-        tx.commit().unwrap();
+        for receipt in receipts {
+            cursor
+                .upsert(receipt.tx_hash, receipt)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+
+        put_trie(&tx, new_latest, trie)?;
+        put_history(&tx, new_latest, &new_block.body)?;
+        put_block(&tx, new_latest, new_block)?;
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
         warn!(height = new_latest, "DB updated new block.");
         Ok(())
     }
 
     fn get_latest_block_header(&self) -> primitives::block::Header {
-        let latest_bno = self.latest_block_number();
-        self.get_header(latest_bno).unwrap().unwrap()
+        let tx = self.inner.begin_read().unwrap();
+        let mut cursor = tx.cursor::<Headers>().unwrap();
+        let (_, header) = cursor.last().unwrap().unwrap();
+        header
+    }
+
+    fn prune_mode(&self) -> PruneMode {
+        self.prune_mode
     }
 
-    fn remove_data(&self, height: u64) -> Result<(), Box<dyn std::error::Error>> {
-        let latest = self.latest_block_number();
-        if latest != height {
-            return Err(Box::new(DatabaseError::CannotRemove));
+    fn oldest_state(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let mut cursor = tx.cursor::<States>().map_err(|_| DatabaseError::DBError)?;
+        match cursor.first().map_err(|_| DatabaseError::DBError)? {
+            Some((block_no, _)) => Ok(block_no),
+            None => Ok(0),
         }
+    }
 
+    fn prune_history(&self, before: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        let before = before.min(self.latest_block_number());
         let tx = self
             .inner
             .begin_readwrite()
             .map_err(|_| DatabaseError::DBError)?;
-        let start_key = DBAdress::new(Address::min(), height);
-        let mut cursor = tx.cursor::<Basic>().map_err(|_| DatabaseError::DBError)?;
-        cursor
-            .seek_closest(start_key)
-            .map_err(|_| DatabaseError::DBError)?;
 
-        while let Ok(Some((key, _account))) = cursor.next() {
-            if key.block_no == height {
-                cursor
-                    .delete_current()
-                    .map_err(|_| DatabaseError::DBError)?;
+        let mut states = Vec::new();
+        let mut changes = Vec::new();
+        {
+            let cursor = tx.cursor::<States>().map_err(|_| DatabaseError::DBError)?;
+            for item in cursor.walk(None) {
+                let (block_no, _) = item.map_err(|_| DatabaseError::DBError)?;
+                if block_no >= before {
+                    break;
+                }
+                states.push(block_no);
+            }
+            // The change sets up to `before` are only needed to rebuild the pruned states
+            let cursor = tx
+                .cursor::<AccountChangeSets>()
+                .map_err(|_| DatabaseError::DBError)?;
+            for item in cursor.walk(None) {
+                let (key, _) = item.map_err(|_| DatabaseError::DBError)?;
+                if key.block_no > before {
+                    break;
+                }
+                changes.push(key);
             }
         }
-        let block = match self.get_block(height).map_err(|_| DatabaseError::DBError)? {
-            Some(block) => block,
-            None => return Ok(()),
-        };
-        tx.delete::<Blocks>(height, None)
+        for block_no in states.iter() {
+            tx.delete::<States>(*block_no, None)
+                .map_err(|_| DatabaseError::DBError)?;
+            delete_trie_root(&tx, *block_no)?;
+        }
+        for key in changes {
+            tx.delete::<AccountChangeSets>(key, None)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
+        Ok(states.len() as u64)
+    }
+
+    fn import_snapshot(
+        &self,
+        accounts: HashMap<Address, Account>,
+        world: World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Header,
+        trie: TrieUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.latest_block_number() != 0 {
+            return Err(Box::new(DatabaseError::NotEmpty));
+        }
+        let height = block.header.height;
+
+        let tx = self
+            .inner
+            .begin_readwrite()
             .map_err(|_| DatabaseError::DBError)?;
-        tx.delete::<BlockByHash>(block.header().calculate_hash(), None)
+        // Genesis state is replaced, the genesis block stays as the chain identity
+        tx.clear_table::<PlainAccounts>()
             .map_err(|_| DatabaseError::DBError)?;
-        let txs = block.body;
-        for signed_tx in txs.iter() {
-            tx.delete::<Transactions>(signed_tx.hash, None)
+        tx.clear_table::<AccountChangeSets>()
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.clear_table::<States>()
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.clear_table::<TrieNodes>()
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.clear_table::<TrieNodeRefs>()
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.clear_table::<TrieRoots>()
+            .map_err(|_| DatabaseError::DBError)?;
+        let mut cursor = tx
+            .cursor::<PlainAccounts>()
+            .map_err(|_| DatabaseError::DBError)?;
+        for (address, account) in accounts {
+            cursor
+                .upsert(address, account)
                 .map_err(|_| DatabaseError::DBError)?;
         }
-        tx.delete::<States>(height, None)
+        let mut cursor = tx
+            .cursor::<Transactions>()
+            .map_err(|_| DatabaseError::DBError)?;
+        for signed_tx in block.body.iter() {
+            cursor
+                .upsert(signed_tx.hash, height)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        let mut cursor = tx
+            .cursor::<Receipts>()
+            .map_err(|_| DatabaseError::DBError)?;
+        for receipt in receipts {
+            cursor
+                .upsert(receipt.tx_hash, receipt)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        tx.upsert::<BlockByHash>(block.header().calculate_hash(), height)
+            .map_err(|_| DatabaseError::DBError)?;
+        tx.upsert::<States>(height, world)
+            .map_err(|_| DatabaseError::DBError)?;
+        put_trie(&tx, height, trie)?;
+        put_history(&tx, height, &block.body)?;
+        put_block(&tx, height, block)?;
+        // A header without its body, below every block above genesis
+        if parent.height > 0 {
+            tx.upsert::<Headers>(parent.height, parent)
+                .map_err(|_| DatabaseError::DBError)?;
+        }
+        put_metadata(&tx, SNAPSHOT_HEIGHT_KEY, height.to_be_bytes().to_vec())?;
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
+        info!(height, "MDBX: Snapshot imported.");
+        Ok(())
+    }
+
+    fn snapshot_height(&self) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        match get_metadata(&tx, SNAPSHOT_HEIGHT_KEY)? {
+            Some(raw) => Ok(Some(decode_u64(SNAPSHOT_HEIGHT_KEY, raw)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn unwind_to(&self, height: u64) -> Result<Vec<Block>, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_readwrite()
+            .map_err(|_| DatabaseError::DBError)?;
+        let blocks = unwind(&tx, height)?;
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
+        if !blocks.is_empty() {
+            warn!(height, removed = blocks.len(), "DB unwound blocks.");
+        }
+        Ok(blocks)
+    }
+
+    fn account_transactions(
+        &self,
+        address: &Address,
+        from: TxPosition,
+        limit: usize,
+    ) -> Result<Vec<AccountTransaction>, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let cursor = tx
+            .cursor::<AccountTransactions>()
+            .map_err(|_| DatabaseError::DBError)?;
+        let start = AccountTxKey {
+            address: *address,
+            position: from,
+        };
+        let mut entries = Vec::new();
+        for item in cursor.walk(Some(start)) {
+            let (key, hash) = item.map_err(|_| DatabaseError::DBError)?;
+            if key.address != *address || entries.len() == limit {
+                break;
+            }
+            entries.push(AccountTransaction {
+                position: key.position,
+                hash: TxHash::from(B256::from(hash)),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn get_trie_node(
+        &self,
+        hash: B256,
+    ) -> Result<Option<(B256, B256)>, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let raw = tx
+            .get::<TrieNodes>(hash.0)
             .map_err(|_| DatabaseError::DBError)?;
+        Ok(raw.map(|raw| (B256::from_slice(&raw[..32]), B256::from_slice(&raw[32..]))))
+    }
 
+    fn get_trie_root(&self, block_no: u64) -> Result<Option<B256>, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let root = tx
+            .get::<TrieRoots>(block_no)
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(root.map(B256::from))
+    }
+
+    fn update_trie(
+        &self,
+        block_no: u64,
+        root: B256,
+        nodes: Vec<(B256, [u8; 64])>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_readwrite()
+            .map_err(|_| DatabaseError::DBError)?;
+        put_trie(&tx, block_no, TrieUpdate { root, nodes })?;
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
         Ok(())
     }
 
+    fn get_receipt(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(TransactionReceipt, u64)>, Box<dyn std::error::Error>> {
+        let tx = self
+            .inner
+            .begin_read()
+            .map_err(|_| DatabaseError::DBError)?;
+        let block_no = match tx
+            .get::<Transactions>(hash)
+            .map_err(|_| DatabaseError::DBError)?
+        {
+            Some(bno) => bno,
+            None => return Ok(None),
+        };
+        let receipt = tx
+            .get::<Receipts>(hash)
+            .map_err(|_| DatabaseError::DBError)?;
+        Ok(receipt.map(|receipt| (receipt, block_no)))
+    }
+
     fn get_transaction_by_hash(
         &self,
         hash: TxHash,
@@ -395,12 +1154,15 @@ impl DatabaseTrait for MDBX {
             None => return Ok(None),
         };
 
-        let block = match self.get_block(block_no)? {
-            Some(block) => block,
+        let body = match tx
+            .get::<Bodies>(block_no)
+            .map_err(|_| DatabaseError::DBError)?
+        {
+            Some(body) => body,
             None => return Ok(None),
         };
 
-        let txs = block.body;
+        let txs = body.0;
         for tx in txs.iter() {
             if tx.hash == hash {
                 return Ok(Some((tx.clone(), block_no)));
@@ -409,3 +1171,265 @@ impl DatabaseTrait for MDBX {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        schema::SCHEMA_VERSION,
+        test_utils::{
+            append_blocks, check_account_changes, check_prune_sweeps_trie_nodes,
+        check_unwind_drops_trie_nodes, shared_address,
+        },
+    };
+
+    use super::*;
+
+    fn open(path: &std::path::Path) -> MDBX {
+        MDBX::genesis_state(path, 0).unwrap()
+    }
+
+    #[test]
+    fn test_unwind_is_atomic_across_reopen() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-unwind-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let db = open(&path);
+        append_blocks(&db, 4);
+        let state_at_2 = db.get_state(2).unwrap();
+        let state_at_4 = db.get_state(4).unwrap();
+        let removed: Vec<Block> = (3..=4).map(|h| db.get_block(h).unwrap().unwrap()).collect();
+
+        // Interrupted before the commit: nothing was removed
+        let tx = db.inner.begin_readwrite().unwrap();
+        assert_eq!(unwind(&tx, 2).unwrap().len(), 2);
+        drop(tx);
+        drop(db);
+        let db = open(&path);
+        assert_eq!(db.latest_block_number(), 4);
+        assert_eq!(db.get_state(4).unwrap(), state_at_4);
+        assert!(db.get_transaction_by_hash(removed[1].body[0].hash).unwrap().is_some());
+
+        let unwound = db.unwind_to(2).unwrap();
+        assert_eq!(
+            unwound.iter().map(|b| b.header.height).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        drop(db);
+        let db = open(&path);
+        assert_eq!(db.latest_block_number(), 2);
+        assert_eq!(db.get_state(2).unwrap(), state_at_2);
+        for block in removed.iter() {
+            let height = block.header.height;
+            assert!(db.get_block(height).unwrap().is_none());
+            assert!(db.get_block_by_hash(block.header.calculate_hash()).is_err());
+            assert!(db.get_transaction_by_hash(block.body[0].hash).unwrap().is_none());
+            assert!(db.get_receipt(block.body[0].hash).unwrap().is_none());
+            assert!(db.get_trie_root(height).unwrap().is_none());
+        }
+
+        let history = db
+            .account_transactions(&shared_address(), TxPosition::default(), 10)
+            .unwrap();
+        assert_eq!(
+            history.iter().map(|entry| entry.position).collect::<Vec<_>>(),
+            vec![TxPosition::new(1, 0), TxPosition::new(2, 0)]
+        );
+
+        // The chain grows again from the unwound height
+        append_blocks(&db, 1);
+        assert_eq!(db.latest_block_number(), 3);
+        assert_eq!(db.get_state(2).unwrap(), state_at_2);
+
+        // A pruned target fails without removing anything
+        db.prune_history(2).unwrap();
+        assert!(matches!(
+            db.unwind_to(1).unwrap_err().downcast_ref(),
+            Some(DatabaseError::Pruned(1))
+        ));
+        assert_eq!(db.latest_block_number(), 3);
+        assert!(db.unwind_to(3).unwrap().is_empty());
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_prune_sweeps_unreachable_trie_nodes() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-sweep-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        check_prune_sweeps_trie_nodes(&open(&path));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unwind_drops_unreachable_trie_nodes() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-trie-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        check_unwind_drops_trie_nodes(&open(&path));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_account_changes_of_one_block() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-changes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        check_account_changes(&open(&path));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_blocks_migrate_to_headers_and_bodies() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-headers-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let db = open(&path);
+        append_blocks(&db, 3);
+        let blocks: Vec<Block> = (0..=3).map(|h| db.get_block(h).unwrap().unwrap()).collect();
+
+        // Back to the version 3 layout, whole blocks in Blocks
+        let tx = db.inner.begin_readwrite().unwrap();
+        for block in blocks.iter() {
+            tx.upsert::<Blocks>(block.header.height, block.clone()).unwrap();
+        }
+        tx.clear_table::<Headers>().unwrap();
+        tx.clear_table::<Bodies>().unwrap();
+        tx.clear_table::<AccountTransactions>().unwrap();
+        put_metadata(&tx, SCHEMA_VERSION_KEY, 3u32.to_be_bytes().to_vec()).unwrap();
+        tx.commit().unwrap();
+        drop(db);
+
+        let db = open(&path);
+        assert_eq!(db.metadata().unwrap().schema_version, SCHEMA_VERSION);
+        assert_eq!(db.latest_block_number(), 3);
+        for block in blocks.iter() {
+            let height = block.header.height;
+            let hash = block.header.calculate_hash();
+            assert_eq!(db.get_header(height).unwrap().unwrap().calculate_hash(), hash);
+            let stored = db.get_block_by_hash(hash).unwrap().unwrap();
+            assert_eq!(stored.body.len(), block.body.len());
+        }
+        assert_eq!(
+            db.get_latest_block_header().calculate_hash(),
+            blocks[3].header.calculate_hash()
+        );
+        let (signed, block_no) = db
+            .get_transaction_by_hash(blocks[2].body[0].hash)
+            .unwrap()
+            .unwrap();
+        assert_eq!((signed.hash, block_no), (blocks[2].body[0].hash, 2));
+        let history = db
+            .account_transactions(&shared_address(), TxPosition::new(2, 0), 10)
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].hash, blocks[2].body[0].hash);
+        let tx = db.inner.begin_read().unwrap();
+        assert!(tx.cursor::<Blocks>().unwrap().first().unwrap().is_none());
+        drop(tx);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    // Raw access to a table, as an older release would have written it
+    mod raw {
+        use libmdbx::table;
+
+        table!(
+            /// Blocks before the canonical encoding
+            ( Blocks ) u64 => Vec<u8>
+        );
+    }
+
+    fn put_raw_metadata(path: &std::path::Path, key: &str, value: Vec<u8>) {
+        let db = libmdbx::orm::Database::create(Some(path.to_path_buf()), &TABLES).unwrap();
+        let tx = db.begin_readwrite().unwrap();
+        put_metadata(&tx, key, value).unwrap();
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_open_refuses_other_chains_and_newer_schemas() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-identity-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        drop(open(&path));
+
+        assert!(matches!(
+            MDBX::new(&path, 1),
+            Err(DatabaseError::ChainIdMismatch { expected: 1, found: 0 })
+        ));
+
+        let other_genesis = B256::repeat_byte(7);
+        put_raw_metadata(&path, GENESIS_HASH_KEY, other_genesis.to_vec());
+        assert!(matches!(
+            MDBX::new(&path, 0),
+            Err(DatabaseError::GenesisMismatch { found, .. }) if found == other_genesis
+        ));
+
+        let newer = SCHEMA_VERSION + 1;
+        put_raw_metadata(&path, SCHEMA_VERSION_KEY, newer.to_be_bytes().to_vec());
+        assert!(matches!(
+            MDBX::new(&path, 0),
+            Err(DatabaseError::UnsupportedSchemaVersion { found, supported })
+                if found == newer && supported == SCHEMA_VERSION
+        ));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_refuses_legacy_encoding() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-legacy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        {
+            let db = libmdbx::orm::Database::create(Some(path.clone()), &TABLES).unwrap();
+            let tx = db.begin_readwrite().unwrap();
+            tx.upsert::<raw::Blocks>(0, vec![0xde, 0xad, 0xbe, 0xef]).unwrap();
+            tx.commit().unwrap();
+        }
+        assert!(matches!(MDBX::new(&path, 0), Err(DatabaseError::LegacyEncoding)));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_account_snapshots_migrate_to_change_sets() {
+        let path = std::env::temp_dir().join(format!("pint-mdbx-basic-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let db = open(&path);
+        append_blocks(&db, 3);
+        let states: Vec<_> = (0..=3).map(|h| db.get_state(h).unwrap()).collect();
+        let blocks: Vec<Block> = (0..=3).map(|h| db.get_block(h).unwrap().unwrap()).collect();
+
+        // Back to the version 1 layout, a full account snapshot per block in Basic
+        let tx = db.inner.begin_readwrite().unwrap();
+        for (block_no, (accounts, _)) in states.iter().enumerate() {
+            for (address, account) in accounts.as_ref().unwrap() {
+                tx.upsert::<Basic>(DBAdress::new(*address, block_no as u64), *account)
+                    .unwrap();
+            }
+        }
+        for block in blocks.iter() {
+            tx.upsert::<Blocks>(block.header.height, block.clone()).unwrap();
+        }
+        tx.clear_table::<PlainAccounts>().unwrap();
+        tx.clear_table::<AccountChangeSets>().unwrap();
+        tx.clear_table::<Headers>().unwrap();
+        tx.clear_table::<Bodies>().unwrap();
+        tx.clear_table::<AccountTransactions>().unwrap();
+        // Unversioned, told apart from version 2 by its Basic rows
+        tx.delete::<Metadata>(SCHEMA_VERSION_KEY.to_string(), None).unwrap();
+        tx.commit().unwrap();
+        drop(db);
+
+        let db = open(&path);
+        assert_eq!(db.metadata().unwrap().schema_version, SCHEMA_VERSION);
+        for (block_no, state) in states.iter().enumerate() {
+            assert_eq!(&db.get_state(block_no as u64).unwrap(), state);
+        }
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 3);
+        let tx = db.inner.begin_read().unwrap();
+        assert!(tx.cursor::<Basic>().unwrap().first().unwrap().is_none());
+        drop(tx);
+
+        // Blocks keep building on the migrated state
+        append_blocks(&db, 1);
+        assert_eq!(&db.get_state(3).unwrap(), &states[3]);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 4);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
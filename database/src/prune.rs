@@ -0,0 +1,215 @@
+use std::{collections::HashMap, convert::Infallible, fmt, str::FromStr, time::Duration};
+
+use primitives::types::B256;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::{error::DatabaseError, traits::DatabaseTrait};
+
+pub const DEFAULT_PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How much state history a database keeps.
+/// Blocks, transactions and receipts are never pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PruneMode {
+    /// Every historical state
+    #[default]
+    Archive,
+    /// The latest state and the states of the N blocks before it
+    KeepLast(u64),
+    /// Only the latest state. Blocks can no longer be unwound.
+    Minimal,
+}
+
+impl PruneMode {
+    /// Oldest height whose state is kept while the chain is at `latest`
+    pub fn oldest_kept(&self, latest: u64) -> u64 {
+        match self {
+            PruneMode::Archive => 0,
+            PruneMode::KeepLast(n) => latest.saturating_sub(*n),
+            PruneMode::Minimal => latest,
+        }
+    }
+}
+
+// "archive", "minimal" or the number of blocks to keep
+impl FromStr for PruneMode {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "archive" => Ok(PruneMode::Archive),
+            "minimal" => Ok(PruneMode::Minimal),
+            n => n
+                .parse::<u64>()
+                .map(PruneMode::KeepLast)
+                .map_err(|_| DatabaseError::InvalidPruneMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PruneMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PruneMode::Archive => write!(f, "archive"),
+            PruneMode::KeepLast(n) => write!(f, "{}", n),
+            PruneMode::Minimal => write!(f, "minimal"),
+        }
+    }
+}
+
+/// Stored trie nodes, each with the number of stored nodes and block roots pointing at it.
+/// A node is deleted once nothing points at it, so dropping a root only touches the nodes
+/// it alone reached. Leaves, empty subtrees and nodes stored without a count are never deleted.
+pub(crate) trait TrieNodeStore {
+    type Error;
+
+    fn trie_node(&mut self, hash: B256) -> Result<Option<(B256, B256)>, Self::Error>;
+    fn trie_refs(&mut self, hash: B256) -> Result<Option<u64>, Self::Error>;
+    fn put_trie_node(
+        &mut self,
+        hash: B256,
+        children: (B256, B256),
+        refs: u64,
+    ) -> Result<(), Self::Error>;
+    fn set_trie_refs(&mut self, hash: B256, refs: u64) -> Result<(), Self::Error>;
+    fn delete_trie_node(&mut self, hash: B256) -> Result<(), Self::Error>;
+}
+
+/// Stores the nodes of a new root that are not stored yet and points the root at them.
+/// Nodes of the update the root does not reach, left over from earlier inserts in the same block, are dropped.
+pub(crate) fn add_trie_root<S: TrieNodeStore>(
+    store: &mut S,
+    root: B256,
+    nodes: Vec<(B256, [u8; 64])>,
+) -> Result<(), S::Error> {
+    let mut added = Vec::new();
+    for (hash, raw) in nodes {
+        if store.trie_node(hash)?.is_none() {
+            let children = (B256::from_slice(&raw[..32]), B256::from_slice(&raw[32..]));
+            store.put_trie_node(hash, children, 0)?;
+            added.push((hash, children));
+        }
+    }
+    for (_, (left, right)) in added.iter() {
+        retain_trie_node(store, *left)?;
+        retain_trie_node(store, *right)?;
+    }
+    retain_trie_node(store, root)?;
+    let mut unreached = Vec::new();
+    for (hash, _) in added {
+        if store.trie_refs(hash)? == Some(0) {
+            unreached.push(hash);
+        }
+    }
+    release_trie_nodes(store, unreached)
+}
+
+/// Drops the reference of a root that is no longer kept, with the nodes only it reached
+pub(crate) fn release_trie_root<S: TrieNodeStore>(
+    store: &mut S,
+    root: B256,
+) -> Result<(), S::Error> {
+    release_trie_nodes(store, vec![root])
+}
+
+fn retain_trie_node<S: TrieNodeStore>(store: &mut S, hash: B256) -> Result<(), S::Error> {
+    match store.trie_refs(hash)? {
+        Some(refs) => store.set_trie_refs(hash, refs + 1),
+        None => Ok(()),
+    }
+}
+
+// Drops one reference to each node, deleting the nodes left without any along with their references to their children
+fn release_trie_nodes<S: TrieNodeStore>(
+    store: &mut S,
+    mut pending: Vec<B256>,
+) -> Result<(), S::Error> {
+    while let Some(hash) = pending.pop() {
+        match store.trie_refs(hash)? {
+            None => {}
+            Some(refs) if refs > 1 => store.set_trie_refs(hash, refs - 1)?,
+            Some(_) => {
+                if let Some((left, right)) = store.trie_node(hash)? {
+                    pending.push(left);
+                    pending.push(right);
+                }
+                store.delete_trie_node(hash)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// In-memory trie nodes: hash => (children, reference count)
+pub(crate) type TrieNodeMap = HashMap<B256, ((B256, B256), u64)>;
+
+impl TrieNodeStore for TrieNodeMap {
+    type Error = Infallible;
+
+    fn trie_node(&mut self, hash: B256) -> Result<Option<(B256, B256)>, Self::Error> {
+        Ok(self.get(&hash).map(|(children, _)| *children))
+    }
+
+    fn trie_refs(&mut self, hash: B256) -> Result<Option<u64>, Self::Error> {
+        Ok(self.get(&hash).map(|(_, refs)| *refs))
+    }
+
+    fn put_trie_node(
+        &mut self,
+        hash: B256,
+        children: (B256, B256),
+        refs: u64,
+    ) -> Result<(), Self::Error> {
+        self.insert(hash, (children, refs));
+        Ok(())
+    }
+
+    fn set_trie_refs(&mut self, hash: B256, refs: u64) -> Result<(), Self::Error> {
+        if let Some(entry) = self.get_mut(&hash) {
+            entry.1 = refs;
+        }
+        Ok(())
+    }
+
+    fn delete_trie_node(&mut self, hash: B256) -> Result<(), Self::Error> {
+        self.remove(&hash);
+        Ok(())
+    }
+}
+
+/// Periodically drops the state history that falls out of the database's retention window
+#[derive(Debug)]
+pub struct Pruner<DB: DatabaseTrait> {
+    db: DB,
+    interval: Duration,
+}
+
+impl<DB: DatabaseTrait> Pruner<DB> {
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            interval: DEFAULT_PRUNE_INTERVAL,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn start(self) -> JoinHandle<()> {
+        info!(mode = %self.db.prune_mode(), "Pruner started.");
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                match self.db.prune() {
+                    Ok(0) => {}
+                    Ok(pruned) => info!(pruned, "Pruned old states."),
+                    Err(e) => warn!(error = ?e, "Failed to prune old states."),
+                }
+            }
+        })
+    }
+}
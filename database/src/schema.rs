@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use libmdbx::{
+    RW, TransactionKind,
+    orm::{Database, Table, Transaction},
+};
+use primitives::types::{Account, Address, B256, ChainId};
+use tracing::info;
+
+use crate::{
+    changeset::account_changes,
+    error::DatabaseError,
+    mdbx::{
+        AccountBefore, AccountChangeSets, Basic, BlockByHash, BlockNo, Blocks, Bodies, DBAdress,
+        Metadata, PlainAccounts, put_block, put_history,
+    },
+};
+
+// Bump on every change to the on-disk format and add the migration from the previous version.
+//   1: per-block account snapshots in `Basic`
+//   2: latest accounts in `PlainAccounts` plus `AccountChangeSets`
+//   3: genesis block indexed in `BlockByHash`
+//   4: blocks split into `Headers` and `Bodies`
+//   5: transactions indexed by account in `AccountTransactions`
+pub const SCHEMA_VERSION: u32 = 5;
+
+// Blocks a migration reads at once. Small in tests so they cross batch boundaries.
+#[cfg(not(test))]
+const MIGRATION_BATCH: usize = 1024;
+#[cfg(test)]
+const MIGRATION_BATCH: usize = 3;
+
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+pub const GENESIS_HASH_KEY: &str = "genesis_hash";
+pub const CHAIN_ID_KEY: &str = "chain_id";
+// Height of the snapshot the database was started from, absent for a database synced from genesis
+pub const SNAPSHOT_HEIGHT_KEY: &str = "snapshot_height";
+
+/// On-disk format and chain identity of a database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainMetadata {
+    pub schema_version: u32,
+    pub genesis_hash: B256,
+    pub chain_id: ChainId,
+}
+
+struct Migration {
+    from: u32,
+    description: &'static str,
+    run: fn(&Transaction<'_, RW>) -> Result<(), DatabaseError>,
+}
+
+// Each entry upgrades `from` to `from + 1`
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        description: "account snapshots to change sets",
+        run: account_change_sets,
+    },
+    Migration {
+        from: 2,
+        description: "genesis block hash index",
+        run: genesis_hash_index,
+    },
+    Migration {
+        from: 3,
+        description: "separate header and body tables",
+        run: split_blocks,
+    },
+    Migration {
+        from: 4,
+        description: "account transaction history",
+        run: account_transaction_history,
+    },
+];
+
+/// Upgrades the database in place, one version at a time.
+/// Each step is committed together with its new version, so an interrupted upgrade resumes where it stopped.
+pub(crate) fn migrate(db: &Database, mut version: u32) -> Result<(), DatabaseError> {
+    while version < SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(DatabaseError::NoMigration {
+                from: version,
+                to: SCHEMA_VERSION,
+            })?;
+        let tx = db.begin_readwrite().map_err(|_| DatabaseError::DBError)?;
+        (migration.run)(&tx)?;
+        version += 1;
+        put_metadata(&tx, SCHEMA_VERSION_KEY, version.to_be_bytes().to_vec())?;
+        tx.commit().map_err(|_| DatabaseError::DBError)?;
+        info!(
+            version,
+            migration = migration.description,
+            "MDBX: Migrated schema."
+        );
+    }
+    Ok(())
+}
+
+/// Version of a database written before the schema version was recorded.
+/// Databases older than the canonical encoding have blocks and accounts that no longer
+/// decode, so nothing can be migrated: they are refused with DatabaseError::LegacyEncoding.
+pub(crate) fn unversioned_schema<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+) -> Result<u32, DatabaseError> {
+    // Unversioned databases kept every block in Blocks
+    tx.get::<Blocks>(0)
+        .map_err(|_| DatabaseError::LegacyEncoding)?;
+    let mut cursor = tx.cursor::<Basic>().map_err(|_| DatabaseError::DBError)?;
+    match cursor.first().map_err(|_| DatabaseError::LegacyEncoding)? {
+        Some(_) => Ok(1),
+        None => Ok(2),
+    }
+}
+
+pub(crate) fn get_metadata<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    key: &str,
+) -> Result<Option<Vec<u8>>, DatabaseError> {
+    tx.get::<Metadata>(key.to_string())
+        .map_err(|_| DatabaseError::DBError)
+}
+
+pub(crate) fn put_metadata(
+    tx: &Transaction<'_, RW>,
+    key: &str,
+    value: Vec<u8>,
+) -> Result<(), DatabaseError> {
+    tx.upsert::<Metadata>(key.to_string(), value)
+        .map_err(|_| DatabaseError::DBError)
+}
+
+pub(crate) fn decode_u32(key: &str, raw: Vec<u8>) -> Result<u32, DatabaseError> {
+    let raw: [u8; 4] = raw
+        .try_into()
+        .map_err(|_| DatabaseError::InvalidMetadata(key.to_string()))?;
+    Ok(u32::from_be_bytes(raw))
+}
+
+pub(crate) fn decode_u64(key: &str, raw: Vec<u8>) -> Result<u64, DatabaseError> {
+    let raw: [u8; 8] = raw
+        .try_into()
+        .map_err(|_| DatabaseError::InvalidMetadata(key.to_string()))?;
+    Ok(u64::from_be_bytes(raw))
+}
+
+pub(crate) fn decode_b256(key: &str, raw: Vec<u8>) -> Result<B256, DatabaseError> {
+    if raw.len() != 32 {
+        return Err(DatabaseError::InvalidMetadata(key.to_string()));
+    }
+    Ok(B256::from_slice(&raw))
+}
+
+// 1 -> 2: rebuilds the latest accounts and per-block change sets from the snapshots.
+// Walks them one block at a time, only the previous snapshot is kept.
+fn account_change_sets(tx: &Transaction<'_, RW>) -> Result<(), DatabaseError> {
+    let mut accounts = HashMap::new();
+    let mut snapshot = HashMap::new();
+    let mut from = 0;
+    while let Some(block_no) = read_snapshot(tx, from, &mut snapshot)? {
+        // Block 0 is the base state
+        if block_no > 0 {
+            for (address, before) in account_changes(&accounts, &snapshot) {
+                tx.upsert::<AccountChangeSets>(
+                    DBAdress::new(address, block_no),
+                    AccountBefore(before),
+                )
+                .map_err(|_| DatabaseError::DBError)?;
+            }
+        }
+        accounts = std::mem::take(&mut snapshot);
+        from = block_no + 1;
+    }
+    tx.clear_table::<PlainAccounts>()
+        .map_err(|_| DatabaseError::DBError)?;
+    for (address, account) in accounts {
+        tx.upsert::<PlainAccounts>(address, account)
+            .map_err(|_| DatabaseError::DBError)?;
+    }
+    tx.clear_table::<Basic>()
+        .map_err(|_| DatabaseError::DBError)
+}
+
+// Reads the first snapshot in `Basic` at or after block `from` into `snapshot`.
+// Returns its block, None once every snapshot was read.
+fn read_snapshot(
+    tx: &Transaction<'_, RW>,
+    from: BlockNo,
+    snapshot: &mut HashMap<Address, Account>,
+) -> Result<Option<BlockNo>, DatabaseError> {
+    let cursor = tx.cursor::<Basic>().map_err(|_| DatabaseError::DBError)?;
+    let mut block_no = None;
+    for item in cursor.walk(Some(DBAdress::new(Address::min(), from))) {
+        let (key, account) = item.map_err(|_| DatabaseError::DBError)?;
+        if *block_no.get_or_insert(key.block_no) != key.block_no {
+            break;
+        }
+        snapshot.insert(key.address, account);
+    }
+    Ok(block_no)
+}
+
+// 2 -> 3: genesis was stored without its hash index
+fn genesis_hash_index(tx: &Transaction<'_, RW>) -> Result<(), DatabaseError> {
+    let genesis = tx
+        .get::<Blocks>(0)
+        .map_err(|_| DatabaseError::DBError)?
+        .ok_or(DatabaseError::DataNotExists)?;
+    tx.upsert::<BlockByHash>(genesis.header.calculate_hash(), 0)
+        .map_err(|_| DatabaseError::DBError)
+}
+
+// 3 -> 4: moves every block out of `Blocks` into a header and a body
+fn split_blocks(tx: &Transaction<'_, RW>) -> Result<(), DatabaseError> {
+    let mut next = 0;
+    loop {
+        let blocks = read_batch::<Blocks>(tx, next)?;
+        let Some((last, _)) = blocks.last() else {
+            break;
+        };
+        next = last + 1;
+        for (block_no, block) in blocks {
+            put_block(tx, block_no, block)?;
+        }
+    }
+    tx.clear_table::<Blocks>()
+        .map_err(|_| DatabaseError::DBError)
+}
+
+// 4 -> 5: indexes the transactions of every stored block by account
+fn account_transaction_history(tx: &Transaction<'_, RW>) -> Result<(), DatabaseError> {
+    let mut next = 0;
+    loop {
+        let bodies = read_batch::<Bodies>(tx, next)?;
+        let Some((last, _)) = bodies.last() else {
+            break;
+        };
+        next = last + 1;
+        for (block_no, body) in bodies {
+            put_history(tx, block_no, &body.0)?;
+        }
+    }
+    Ok(())
+}
+
+// At most MIGRATION_BATCH rows from `from` on, so a migration never holds a whole table.
+// The cursor is dropped before the batch is written.
+fn read_batch<T>(
+    tx: &Transaction<'_, RW>,
+    from: BlockNo,
+) -> Result<Vec<(BlockNo, T::Value)>, DatabaseError>
+where
+    T: Table<Key = BlockNo, SeekKey = BlockNo>,
+{
+    let cursor = tx.cursor::<T>().map_err(|_| DatabaseError::DBError)?;
+    cursor
+        .walk(Some(from))
+        .take(MIGRATION_BATCH)
+        .map(|item| item.map_err(|_| DatabaseError::DBError))
+        .collect()
+}
@@ -0,0 +1,1365 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use parking_lot::{Mutex, RwLock};
+use primitives::{
+    block::{Block, Header},
+    encoding::{Decoder, Encoder},
+    error::DecodeError,
+    receipt::TransactionReceipt,
+    transaction::SignedTransaction,
+    types::{Account, Address, B256, BlockHash, ChainId, TxHash},
+    world::World,
+};
+use tracing::{info, warn};
+
+use crate::{
+    changeset::{AccountChangeSet, changes_of, revert_changes},
+    error::DatabaseError,
+    genesis::genesis_accounts_info,
+    history::{AccountTransaction, TxPosition, block_history},
+    prune::{PruneMode, TrieNodeMap, add_trie_root, release_trie_root},
+    traits::{DatabaseTrait, TrieUpdate},
+};
+
+// Append-only log of every write, split into numbered segment files.
+//
+// Every record is framed as
+//   [payload length: u32 BE][crc32 of the payload: u32 BE][payload]
+// and the payload starts with the record kind. The first record of segment 0 names
+// the format version and the chain. Nothing is ever rewritten: an unwind or a prune
+// is a record of its own. On open the log is replayed into a SegmentIndex, and blocks,
+// receipts, change sets and worlds are read back from the record they were written in.
+//
+// Bump the version on every change to the record layout and add the upgrade from the previous one.
+//   1: block, trie, prune, unwind and snapshot records
+pub const SEGMENT_FORMAT_VERSION: u32 = 1;
+pub const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+const RECORD_HEADER_LEN: usize = 4 + 4;
+const SEGMENT_EXTENSION: &str = "seg";
+
+const CHAIN_RECORD: u8 = 0x00;
+const BLOCK_RECORD: u8 = 0x01;
+const TRIE_RECORD: u8 = 0x02;
+const PRUNE_RECORD: u8 = 0x04;
+const UNWIND_RECORD: u8 = 0x05;
+const SNAPSHOT_RECORD: u8 = 0x06;
+
+struct Upgrade {
+    from: u32,
+    description: &'static str,
+    run: fn(&Path) -> Result<(), DatabaseError>,
+}
+
+// Each entry rewrites the segments of version `from` into `from + 1`, chain record included.
+// An upgrade writes the new segments next to the old ones and swaps them in once complete,
+// so an interrupted upgrade starts over from the old log.
+const UPGRADES: &[Upgrade] = &[];
+
+/// Upgrades the segments in `dir` one version at a time
+fn upgrade_segments(dir: &Path, mut version: u32) -> Result<(), DatabaseError> {
+    while version < SEGMENT_FORMAT_VERSION {
+        let upgrade = UPGRADES
+            .iter()
+            .find(|upgrade| upgrade.from == version)
+            .ok_or(DatabaseError::NoMigration {
+                from: version,
+                to: SEGMENT_FORMAT_VERSION,
+            })?;
+        info!(
+            from = version,
+            to = version + 1,
+            upgrade = upgrade.description,
+            "SegmentDB: Upgrading segments."
+        );
+        (upgrade.run)(dir)?;
+        version += 1;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+enum Record {
+    Chain {
+        version: u32,
+        chain_id: ChainId,
+        genesis_hash: B256,
+    },
+    // The accounts the block changed with their new values, and with their values before it
+    Block {
+        block_no: u64,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        accounts: Vec<(Address, Account)>,
+        changes: Vec<(Address, Option<Account>)>,
+        world: World,
+        trie: TrieUpdate,
+    },
+    Trie {
+        block_no: u64,
+        root: B256,
+        nodes: Vec<(B256, [u8; 64])>,
+    },
+    Prune {
+        before: u64,
+    },
+    Unwind {
+        height: u64,
+    },
+    Snapshot {
+        accounts: HashMap<Address, Account>,
+        world: World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Box<Header>,
+        trie: TrieUpdate,
+    },
+}
+
+impl Record {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        match self {
+            Record::Chain {
+                version,
+                chain_id,
+                genesis_hash,
+            } => {
+                encoder
+                    .put_u8(CHAIN_RECORD)
+                    .put_u32(*version)
+                    .put_u64(*chain_id)
+                    .put_b256(*genesis_hash);
+            }
+            Record::Block {
+                block_no,
+                block,
+                receipts,
+                accounts,
+                changes,
+                world,
+                trie,
+            } => {
+                encoder.put_u8(BLOCK_RECORD).put_u64(*block_no);
+                encoder.put_bytes(&block.encode_ref());
+                put_receipts(&mut encoder, receipts);
+                put_accounts(
+                    &mut encoder,
+                    accounts.iter().map(|(address, account)| (address, account)),
+                );
+                encoder.put_u32(changes.len() as u32);
+                for (address, before) in changes.iter() {
+                    encoder.put_address(*address);
+                    match before {
+                        Some(account) => {
+                            encoder
+                                .put_u8(1)
+                                .put_u64(account.nonce())
+                                .put_u256(account.balance());
+                        }
+                        None => {
+                            encoder.put_u8(0);
+                        }
+                    }
+                }
+                encoder.put_bytes(&world.encode());
+                put_trie(&mut encoder, trie.root, &trie.nodes);
+            }
+            Record::Trie {
+                block_no,
+                root,
+                nodes,
+            } => {
+                encoder.put_u8(TRIE_RECORD).put_u64(*block_no);
+                put_trie(&mut encoder, *root, nodes);
+            }
+            Record::Prune { before } => {
+                encoder.put_u8(PRUNE_RECORD).put_u64(*before);
+            }
+            Record::Unwind { height } => {
+                encoder.put_u8(UNWIND_RECORD).put_u64(*height);
+            }
+            Record::Snapshot {
+                accounts,
+                world,
+                block,
+                receipts,
+                parent,
+                trie,
+            } => {
+                encoder.put_u8(SNAPSHOT_RECORD);
+                put_accounts(&mut encoder, accounts.iter());
+                encoder.put_bytes(&world.encode());
+                encoder.put_bytes(&block.encode_ref());
+                encoder.put_bytes(&parent.encode());
+                put_trie(&mut encoder, trie.root, &trie.nodes);
+                put_receipts(&mut encoder, receipts);
+            }
+        }
+        encoder.into_payload()
+    }
+
+    fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let record = match decoder.read_u8()? {
+            CHAIN_RECORD => Record::Chain {
+                version: decoder.read_u32()?,
+                chain_id: decoder.read_u64()?,
+                genesis_hash: decoder.read_b256()?,
+            },
+            BLOCK_RECORD => {
+                let block_no = decoder.read_u64()?;
+                let block = Block::decode(decoder.read_bytes()?)?.0;
+                let receipts = read_receipts(&mut decoder)?;
+                let accounts = read_accounts(&mut decoder)?;
+                let mut changes = Vec::new();
+                for _ in 0..decoder.read_u32()? {
+                    let address = decoder.read_address()?;
+                    let before = match decoder.read_u8()? {
+                        0 => None,
+                        _ => Some(Account::new(decoder.read_u64()?, decoder.read_u256()?)),
+                    };
+                    changes.push((address, before));
+                }
+                Record::Block {
+                    block_no,
+                    block,
+                    receipts,
+                    accounts,
+                    changes,
+                    world: World::decode(decoder.read_bytes()?)?,
+                    trie: read_trie(&mut decoder)?,
+                }
+            }
+            TRIE_RECORD => {
+                let block_no = decoder.read_u64()?;
+                let TrieUpdate { root, nodes } = read_trie(&mut decoder)?;
+                Record::Trie {
+                    block_no,
+                    root,
+                    nodes,
+                }
+            }
+            PRUNE_RECORD => Record::Prune {
+                before: decoder.read_u64()?,
+            },
+            UNWIND_RECORD => Record::Unwind {
+                height: decoder.read_u64()?,
+            },
+            SNAPSHOT_RECORD => Record::Snapshot {
+                accounts: read_accounts(&mut decoder)?.into_iter().collect(),
+                world: World::decode(decoder.read_bytes()?)?,
+                block: Block::decode(decoder.read_bytes()?)?.0,
+                parent: Box::new(Header::decode(decoder.read_bytes()?)?),
+                trie: read_trie(&mut decoder)?,
+                receipts: read_receipts(&mut decoder)?,
+            },
+            kind => return Err(DecodeError::UnexpectedItemType(kind)),
+        };
+        decoder.finish()?;
+        Ok(record)
+    }
+}
+
+fn put_accounts<'a>(
+    encoder: &mut Encoder,
+    accounts: impl ExactSizeIterator<Item = (&'a Address, &'a Account)>,
+) {
+    encoder.put_u32(accounts.len() as u32);
+    for (address, account) in accounts {
+        encoder
+            .put_address(*address)
+            .put_u64(account.nonce())
+            .put_u256(account.balance());
+    }
+}
+
+fn read_accounts(decoder: &mut Decoder) -> Result<Vec<(Address, Account)>, DecodeError> {
+    let mut accounts = Vec::new();
+    for _ in 0..decoder.read_u32()? {
+        let address = decoder.read_address()?;
+        accounts.push((
+            address,
+            Account::new(decoder.read_u64()?, decoder.read_u256()?),
+        ));
+    }
+    Ok(accounts)
+}
+
+fn put_receipts(encoder: &mut Encoder, receipts: &[TransactionReceipt]) {
+    encoder.put_u32(receipts.len() as u32);
+    for receipt in receipts.iter() {
+        encoder.put_bytes(&receipt.encode());
+    }
+}
+
+fn read_receipts(decoder: &mut Decoder) -> Result<Vec<TransactionReceipt>, DecodeError> {
+    let mut receipts = Vec::new();
+    for _ in 0..decoder.read_u32()? {
+        receipts.push(TransactionReceipt::decode(decoder.read_bytes()?)?);
+    }
+    Ok(receipts)
+}
+
+fn put_trie(encoder: &mut Encoder, root: B256, nodes: &[(B256, [u8; 64])]) {
+    encoder.put_b256(root).put_u32(nodes.len() as u32);
+    for (hash, raw) in nodes.iter() {
+        encoder.put_b256(*hash).put_fixed(raw);
+    }
+}
+
+fn read_trie(decoder: &mut Decoder) -> Result<TrieUpdate, DecodeError> {
+    let root = decoder.read_b256()?;
+    let mut nodes = Vec::new();
+    for _ in 0..decoder.read_u32()? {
+        let hash = decoder.read_b256()?;
+        nodes.push((hash, decoder.read_fixed(64)?.try_into()?));
+    }
+    Ok(TrieUpdate { root, nodes })
+}
+
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+    raw.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    raw.extend_from_slice(&crc32fast::hash(payload).to_be_bytes());
+    raw.extend_from_slice(payload);
+    raw
+}
+
+enum Framed<'a> {
+    Record(&'a [u8]),
+    // Runs past the end of the file
+    Incomplete,
+    Corrupted,
+}
+
+// The record at the start of `raw`. A bad checksum on the last record of the file counts as
+// incomplete, since a torn write can leave any of its bytes unwritten.
+fn unframe(raw: &[u8]) -> Framed<'_> {
+    let Some(header) = raw.get(..RECORD_HEADER_LEN) else {
+        return Framed::Incomplete;
+    };
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let crc = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let end = RECORD_HEADER_LEN + len;
+    match raw.get(RECORD_HEADER_LEN..end) {
+        Some(payload) if crc32fast::hash(payload) == crc => Framed::Record(payload),
+        Some(_) if end == raw.len() => Framed::Incomplete,
+        Some(_) => Framed::Corrupted,
+        None => Framed::Incomplete,
+    }
+}
+
+fn segment_path(dir: &Path, index: u32) -> PathBuf {
+    dir.join(format!("{index:08}.{SEGMENT_EXTENSION}"))
+}
+
+// Indexes of the segment files in `dir`, in order
+fn segment_indexes(dir: &Path) -> Result<Vec<u32>, DatabaseError> {
+    let mut indexes = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(index) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            indexes.push(index);
+        }
+    }
+    indexes.sort_unstable();
+    Ok(indexes)
+}
+
+// Where a record starts: its segment and the offset of its frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Location {
+    segment: u32,
+    offset: u64,
+}
+
+// Reads back the record at `at`
+fn read_record(dir: &Path, at: Location) -> Result<Record, DatabaseError> {
+    let corrupted = || DatabaseError::CorruptedSegment {
+        segment: at.segment,
+        offset: at.offset,
+    };
+    let mut file = File::open(segment_path(dir, at.segment))?;
+    file.seek(SeekFrom::Start(at.offset))?;
+    let mut raw = vec![0u8; RECORD_HEADER_LEN];
+    file.read_exact(&mut raw).map_err(|_| corrupted())?;
+    let len = u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as usize;
+    raw.resize(RECORD_HEADER_LEN + len, 0);
+    file.read_exact(&mut raw[RECORD_HEADER_LEN..])
+        .map_err(|_| corrupted())?;
+    match unframe(&raw) {
+        Framed::Record(payload) => Record::decode(payload).map_err(|_| corrupted()),
+        Framed::Incomplete | Framed::Corrupted => Err(corrupted()),
+    }
+}
+
+// The segment written to. Older segments are never opened for writing again.
+#[derive(Debug)]
+struct SegmentLog {
+    dir: PathBuf,
+    segment_size: u64,
+    index: u32,
+    file: File,
+    len: u64,
+}
+
+impl SegmentLog {
+    fn open_segment(dir: &Path, index: u32) -> Result<File, DatabaseError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(dir, index))?;
+        // A new file is only durable once its directory entry is
+        File::open(dir)?.sync_all()?;
+        Ok(file)
+    }
+
+    // A record is durable once this returns. Returns where it starts.
+    fn append(&mut self, record: &Record) -> Result<Location, DatabaseError> {
+        let raw = frame(&record.encode());
+        // A record larger than a segment gets one of its own
+        if self.len > 0 && self.len + raw.len() as u64 > self.segment_size {
+            self.file.sync_all()?;
+            self.index += 1;
+            self.file = Self::open_segment(&self.dir, self.index)?;
+            self.len = 0;
+        }
+        if let Err(err) = self
+            .file
+            .write_all(&raw)
+            .and_then(|_| self.file.sync_data())
+        {
+            // Drop a partial record so the next append does not land behind it
+            let _ = self.file.set_len(self.len);
+            return Err(err.into());
+        }
+        let start = Location {
+            segment: self.index,
+            offset: self.len,
+        };
+        self.len += raw.len() as u64;
+        Ok(start)
+    }
+
+    // Appends the record and hands it to `apply` with where it starts. A record the index
+    // refuses is cut from the segment again, otherwise the next replay would stop at it.
+    fn append_then<T>(
+        &mut self,
+        record: Record,
+        apply: impl FnOnce(Record, Location) -> Result<T, DatabaseError>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let start = self.append(&record)?;
+        let applied = apply(record, start);
+        if applied.is_err() {
+            self.file.set_len(start.offset)?;
+            self.file.sync_data()?;
+            self.len = start.offset;
+        }
+        Ok(applied?)
+    }
+}
+
+// What SegmentDB keeps in memory, rebuilt from the log on open. Everything else is read back
+// from the segments at the location of the record that wrote it.
+#[derive(Debug)]
+struct SegmentIndex {
+    dir: PathBuf,
+    latest: u64,
+    latest_header: Header,
+    oldest_state: u64,
+    // Accounts at the latest block. Older states are rebuilt from the change sets in the block records.
+    accounts: HashMap<Address, Account>,
+    // Block or snapshot record of every stored block above genesis
+    blocks: BTreeMap<u64, Location>,
+    block_hashes: HashMap<B256, u64>,
+    transactions: HashMap<TxHash, TxPosition>,
+    account_transactions: BTreeMap<(Address, TxPosition), TxHash>,
+    trie_nodes: TrieNodeMap,
+    trie_roots: BTreeMap<u64, B256>,
+    // Height of the imported snapshot and its record, which also holds the parent header
+    snapshot: Option<(u64, Location)>,
+}
+
+impl SegmentIndex {
+    fn genesis(dir: PathBuf) -> Self {
+        let genesis = Block::genesis_block().header;
+        Self {
+            dir,
+            latest: 0,
+            block_hashes: HashMap::from([(genesis.calculate_hash().hash(), 0)]),
+            latest_header: genesis,
+            oldest_state: 0,
+            accounts: genesis_accounts_info().into_iter().collect(),
+            blocks: BTreeMap::new(),
+            transactions: HashMap::new(),
+            account_transactions: BTreeMap::new(),
+            trie_nodes: TrieNodeMap::new(),
+            trie_roots: BTreeMap::new(),
+            snapshot: None,
+        }
+    }
+
+    // The block or snapshot record of `block_no`, None for genesis and blocks not stored
+    fn record(&self, block_no: u64) -> Result<Option<Record>, DatabaseError> {
+        match self.blocks.get(&block_no) {
+            Some(at) => read_record(&self.dir, *at).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn block(&self, block_no: u64) -> Result<Option<Block>, DatabaseError> {
+        if block_no == 0 {
+            return Ok(Some(Block::genesis_block()));
+        }
+        match self.record(block_no)? {
+            Some(Record::Block { block, .. } | Record::Snapshot { block, .. }) => Ok(Some(block)),
+            _ => Ok(None),
+        }
+    }
+
+    fn world(&self, block_no: u64) -> Result<Option<World>, DatabaseError> {
+        if block_no == 0 {
+            return Ok(Some(World::new()));
+        }
+        match self.record(block_no)? {
+            Some(Record::Block { world, .. } | Record::Snapshot { world, .. }) => Ok(Some(world)),
+            _ => Ok(None),
+        }
+    }
+
+    fn changes(&self, block_no: u64) -> Result<AccountChangeSet, DatabaseError> {
+        match self.record(block_no)? {
+            Some(Record::Block { changes, .. }) => Ok(changes.into_iter().collect()),
+            _ => Ok(AccountChangeSet::new()),
+        }
+    }
+
+    fn add_transactions(&mut self, block_no: u64, block: &Block) {
+        self.block_hashes
+            .insert(block.header.calculate_hash().hash(), block_no);
+        for (index, signed) in block.body.iter().enumerate() {
+            self.transactions
+                .insert(signed.hash, TxPosition::new(block_no, index as u32));
+        }
+        for (address, entry) in block_history(block_no, &block.body) {
+            self.account_transactions
+                .insert((address, entry.position), entry.hash);
+        }
+    }
+
+    fn set_trie(&mut self, block_no: u64, trie: TrieUpdate) {
+        let Ok(()) = add_trie_root(&mut self.trie_nodes, trie.root, trie.nodes);
+        if let Some(replaced) = self.trie_roots.insert(block_no, trie.root) {
+            let Ok(()) = release_trie_root(&mut self.trie_nodes, replaced);
+        }
+    }
+
+    // Brings the index to right after the record
+    fn apply(&mut self, record: Record, at: Location) -> Result<(), DatabaseError> {
+        match record {
+            // Checked when the log is opened
+            Record::Chain { .. } => {}
+            Record::Block {
+                block_no,
+                block,
+                accounts,
+                trie,
+                ..
+            } => {
+                if block_no != self.latest + 1 {
+                    return Err(DatabaseError::DataNotExists);
+                }
+                self.accounts.extend(accounts);
+                self.add_transactions(block_no, &block);
+                self.blocks.insert(block_no, at);
+                self.set_trie(block_no, trie);
+                self.latest = block_no;
+                self.latest_header = block.header;
+            }
+            Record::Trie {
+                block_no,
+                root,
+                nodes,
+            } => self.set_trie(block_no, TrieUpdate { root, nodes }),
+            Record::Prune { before } => {
+                self.prune(before);
+            }
+            Record::Unwind { height } => {
+                self.unwind(height)?;
+            }
+            Record::Snapshot {
+                accounts,
+                block,
+                trie,
+                ..
+            } => {
+                if self.latest != 0 {
+                    return Err(DatabaseError::NotEmpty);
+                }
+                let height = block.header.height;
+                // Genesis state is replaced, the genesis block stays as the chain identity
+                self.accounts = accounts;
+                self.trie_nodes.clear();
+                self.trie_roots.clear();
+                self.set_trie(height, trie);
+                self.add_transactions(height, &block);
+                self.blocks.insert(height, at);
+                self.snapshot = Some((height, at));
+                self.oldest_state = height;
+                self.latest = height;
+                self.latest_header = block.header;
+            }
+        }
+        Ok(())
+    }
+
+    // Returns how many states were dropped. Their records stay in the segments.
+    fn prune(&mut self, before: u64) -> u64 {
+        let before = before.min(self.latest);
+        if before <= self.oldest_state {
+            return 0;
+        }
+        let kept = self.trie_roots.split_off(&before);
+        for root in std::mem::replace(&mut self.trie_roots, kept).into_values() {
+            let Ok(()) = release_trie_root(&mut self.trie_nodes, root);
+        }
+        let pruned = before - self.oldest_state;
+        self.oldest_state = before;
+        pruned
+    }
+
+    // Every record is read before anything changes, so a failed read leaves the index untouched
+    fn unwind(&mut self, height: u64) -> Result<Vec<Block>, DatabaseError> {
+        if height < self.oldest_state {
+            return Err(DatabaseError::Pruned(height));
+        }
+        if height >= self.latest {
+            return Ok(Vec::new());
+        }
+        let header = self
+            .block(height)?
+            .ok_or(DatabaseError::DataNotExists)?
+            .header;
+        let mut removed = Vec::new();
+        for block_no in (height + 1..=self.latest).rev() {
+            match self.record(block_no)? {
+                Some(Record::Block { block, changes, .. }) => removed.push((block, changes)),
+                _ => return Err(DatabaseError::DataNotExists),
+            }
+        }
+
+        let mut blocks = Vec::with_capacity(removed.len());
+        for (block, changes) in removed {
+            let block_no = block.header.height;
+            revert_changes(&mut self.accounts, &changes.into_iter().collect());
+            self.blocks.remove(&block_no);
+            self.block_hashes
+                .remove(&block.header.calculate_hash().hash());
+            for signed in block.body.iter() {
+                self.transactions.remove(&signed.hash);
+            }
+            for (address, entry) in block_history(block_no, &block.body) {
+                self.account_transactions.remove(&(address, entry.position));
+            }
+            if let Some(root) = self.trie_roots.remove(&block_no) {
+                let Ok(()) = release_trie_root(&mut self.trie_nodes, root);
+            }
+            blocks.push(block);
+        }
+        blocks.reverse();
+        self.latest = height;
+        self.latest_header = header;
+        Ok(blocks)
+    }
+}
+
+/// Pure Rust database that appends every write to segment files.
+/// Memory holds an index rebuilt from the segments on open: the latest accounts, the trie nodes
+/// of the kept states and where every block and transaction was written. Blocks, receipts,
+/// change sets and worlds are read back from the segments. The files keep growing, a prune
+/// only drops states from the index.
+/// Clones share the log and the index.
+#[derive(Debug, Clone)]
+pub struct SegmentDB {
+    // Held across appending a record and applying it, so the log order is the applied order
+    log: Arc<Mutex<SegmentLog>>,
+    index: Arc<RwLock<SegmentIndex>>,
+    prune_mode: PruneMode,
+}
+
+impl SegmentDB {
+    /// Opens the segments in `path`, upgrades them to the current format and replays them.
+    /// A record cut short at the end of the last segment is dropped, any other damage fails.
+    pub fn new(path: impl Into<PathBuf>, chain_id: ChainId) -> Result<Self, DatabaseError> {
+        Self::with_segment_size(path, chain_id, DEFAULT_SEGMENT_SIZE)
+    }
+
+    pub fn with_segment_size(
+        path: impl Into<PathBuf>,
+        chain_id: ChainId,
+        segment_size: u64,
+    ) -> Result<Self, DatabaseError> {
+        let dir = path.into();
+        std::fs::create_dir_all(&dir)?;
+        let genesis_hash = Block::genesis_block().header.calculate_hash().hash();
+
+        // The chain record names the format, so the log is upgraded before anything is replayed
+        if segment_path(&dir, 0).exists() {
+            let first = Location {
+                segment: 0,
+                offset: 0,
+            };
+            match read_record(&dir, first) {
+                Ok(record) => check_chain(&dir, &record, chain_id, genesis_hash)?,
+                // A torn chain record is dropped by the replay, other damage is reported by it
+                Err(DatabaseError::CorruptedSegment { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let indexes = segment_indexes(&dir)?;
+        for (expected, index) in indexes.iter().enumerate() {
+            if *index != expected as u32 {
+                return Err(DatabaseError::CorruptedSegment {
+                    segment: expected as u32,
+                    offset: 0,
+                });
+            }
+        }
+        let last = indexes.last().copied().unwrap_or(0);
+
+        let mut index = SegmentIndex::genesis(dir.clone());
+        let mut records = 0;
+        let mut len = 0;
+        for segment in indexes {
+            let raw = std::fs::read(segment_path(&dir, segment))?;
+            let mut offset = 0;
+            while offset < raw.len() {
+                let at = Location {
+                    segment,
+                    offset: offset as u64,
+                };
+                let corrupted = DatabaseError::CorruptedSegment {
+                    segment,
+                    offset: at.offset,
+                };
+                let payload = match unframe(&raw[offset..]) {
+                    Framed::Record(payload) => payload,
+                    // Torn by a crash in the middle of an append
+                    Framed::Incomplete if segment == last => {
+                        warn!(
+                            segment,
+                            offset,
+                            dropped = raw.len() - offset,
+                            "SegmentDB: Dropping incomplete record."
+                        );
+                        OpenOptions::new()
+                            .write(true)
+                            .open(segment_path(&dir, segment))?
+                            .set_len(at.offset)?;
+                        break;
+                    }
+                    Framed::Incomplete | Framed::Corrupted => return Err(corrupted),
+                };
+                let record = Record::decode(payload).map_err(|_| corrupted)?;
+                index
+                    .apply(record, at)
+                    .map_err(|_| DatabaseError::CorruptedSegment {
+                        segment,
+                        offset: at.offset,
+                    })?;
+                records += 1;
+                offset += RECORD_HEADER_LEN + payload.len();
+            }
+            len = offset as u64;
+        }
+
+        let mut log = SegmentLog {
+            file: SegmentLog::open_segment(&dir, last)?,
+            dir,
+            segment_size,
+            index: last,
+            len,
+        };
+        if records == 0 {
+            log.append(&Record::Chain {
+                version: SEGMENT_FORMAT_VERSION,
+                chain_id,
+                genesis_hash,
+            })?;
+            info!("SegmentDB: Genesis block initialized.");
+        } else {
+            info!(
+                records,
+                latest = index.latest,
+                "SegmentDB: Replayed segments."
+            );
+        }
+        Ok(Self {
+            log: Arc::new(Mutex::new(log)),
+            index: Arc::new(RwLock::new(index)),
+            prune_mode: PruneMode::default(),
+        })
+    }
+
+    pub fn with_prune_mode(mut self, prune_mode: PruneMode) -> Self {
+        self.prune_mode = prune_mode;
+        self
+    }
+
+    // Genesis accounts come with the replayed index, this only mirrors MDBX::genesis_state
+    pub fn genesis_state(
+        path: impl Into<PathBuf>,
+        chain_id: ChainId,
+    ) -> Result<Self, DatabaseError> {
+        Self::new(path, chain_id)
+    }
+}
+
+// Compares the identity in the first record with the node's, and upgrades an older log
+fn check_chain(
+    dir: &Path,
+    record: &Record,
+    chain_id: ChainId,
+    expected: B256,
+) -> Result<(), DatabaseError> {
+    let Record::Chain {
+        version,
+        chain_id: found_chain_id,
+        genesis_hash,
+    } = record
+    else {
+        return Err(DatabaseError::InvalidMetadata(
+            "segment chain record".to_string(),
+        ));
+    };
+    if *version > SEGMENT_FORMAT_VERSION {
+        return Err(DatabaseError::UnsupportedSchemaVersion {
+            found: *version,
+            supported: SEGMENT_FORMAT_VERSION,
+        });
+    }
+    if *genesis_hash != expected {
+        return Err(DatabaseError::GenesisMismatch {
+            expected,
+            found: *genesis_hash,
+        });
+    }
+    if *found_chain_id != chain_id {
+        return Err(DatabaseError::ChainIdMismatch {
+            expected: chain_id,
+            found: *found_chain_id,
+        });
+    }
+    upgrade_segments(dir, *version)
+}
+
+impl DatabaseTrait for SegmentDB {
+    fn latest_block_number(&self) -> u64 {
+        self.index.read().latest
+    }
+
+    fn basic(&self, address: &Address) -> Result<Option<Account>, Box<dyn std::error::Error>> {
+        Ok(self.index.read().accounts.get(address).copied())
+    }
+
+    fn get_state(
+        &self,
+        block_no: u64,
+    ) -> Result<(Option<HashMap<Address, Account>>, Option<World>), Box<dyn std::error::Error>>
+    {
+        let index = self.index.read();
+        if block_no < index.oldest_state {
+            return Err(Box::new(DatabaseError::Pruned(block_no)));
+        }
+        if block_no > index.latest {
+            return Ok((None, None));
+        }
+        let mut accounts = index.accounts.clone();
+        for later in (block_no + 1..=index.latest).rev() {
+            revert_changes(&mut accounts, &index.changes(later)?);
+        }
+        Ok((Some(accounts), index.world(block_no)?))
+    }
+
+    fn get_account_changes(
+        &self,
+        block_no: u64,
+    ) -> Result<AccountChangeSet, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        if block_no <= index.oldest_state {
+            return Err(Box::new(DatabaseError::Pruned(block_no.saturating_sub(1))));
+        }
+        Ok(index.changes(block_no)?)
+    }
+
+    fn get_world(&self, block_no: u64) -> Result<Option<World>, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        if block_no < index.oldest_state {
+            return Err(Box::new(DatabaseError::Pruned(block_no)));
+        }
+        Ok(index.world(block_no)?)
+    }
+
+    fn get_block(&self, block_no: u64) -> Result<Option<Block>, Box<dyn std::error::Error>> {
+        match self.index.read().block(block_no)? {
+            Some(block) => Ok(Some(block)),
+            None => Err(Box::new(DatabaseError::DataNotExists)),
+        }
+    }
+
+    fn get_block_by_hash(
+        &self,
+        hash: BlockHash,
+    ) -> Result<Option<Block>, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        match index.block_hashes.get(&hash.hash()) {
+            Some(block_no) => Ok(index.block(*block_no)?),
+            None => Err(Box::new(DatabaseError::DataNotExists)),
+        }
+    }
+
+    fn get_transaction_by_hash(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(SignedTransaction, u64)>, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        let Some(position) = index.transactions.get(&hash).copied() else {
+            return Ok(None);
+        };
+        let block = index
+            .block(position.block_no)?
+            .ok_or(DatabaseError::DataNotExists)?;
+        Ok(block
+            .body
+            .into_iter()
+            .nth(position.index as usize)
+            .map(|signed| (signed, position.block_no)))
+    }
+
+    fn get_header(&self, block_no: u64) -> Result<Option<Header>, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        if let Some(block) = index.block(block_no)? {
+            return Ok(Some(block.header));
+        }
+        match index.snapshot {
+            Some((height, at)) if height == block_no + 1 => match read_record(&index.dir, at)? {
+                Record::Snapshot { parent, .. } => Ok(Some(*parent)),
+                _ => Err(Box::new(DatabaseError::DataNotExists)),
+            },
+            _ => Err(Box::new(DatabaseError::DataNotExists)),
+        }
+    }
+
+    fn update(
+        &self,
+        changed_accounts: HashMap<Address, Account>,
+        new_field_state: World,
+        new_block: Block,
+        trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut log = self.log.lock();
+        let mut index = self.index.write();
+        let block_no = index.latest + 1;
+        let Ok(changes) = changes_of(&changed_accounts, |address| {
+            Ok::<_, Infallible>(index.accounts.get(address).copied())
+        });
+        let record = Record::Block {
+            block_no,
+            block: new_block,
+            receipts,
+            accounts: changed_accounts.into_iter().collect(),
+            changes: changes.into_iter().collect(),
+            world: new_field_state,
+            trie,
+        };
+        log.append_then(record, |record, at| index.apply(record, at))?;
+        warn!(block_height = block_no, "DB updated new block.");
+        Ok(())
+    }
+
+    fn get_latest_block_header(&self) -> Header {
+        self.index.read().latest_header.clone()
+    }
+
+    fn get_trie_node(
+        &self,
+        hash: B256,
+    ) -> Result<Option<(B256, B256)>, Box<dyn std::error::Error>> {
+        Ok(self
+            .index
+            .read()
+            .trie_nodes
+            .get(&hash)
+            .map(|(children, _)| *children))
+    }
+
+    fn get_trie_root(&self, block_no: u64) -> Result<Option<B256>, Box<dyn std::error::Error>> {
+        Ok(self.index.read().trie_roots.get(&block_no).copied())
+    }
+
+    fn update_trie(
+        &self,
+        block_no: u64,
+        root: B256,
+        nodes: Vec<(B256, [u8; 64])>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut log = self.log.lock();
+        let mut index = self.index.write();
+        let record = Record::Trie {
+            block_no,
+            root,
+            nodes,
+        };
+        log.append_then(record, |record, at| index.apply(record, at))
+    }
+
+    fn get_receipt(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(TransactionReceipt, u64)>, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        let Some(position) = index.transactions.get(&hash).copied() else {
+            return Ok(None);
+        };
+        let receipts = match index.record(position.block_no)? {
+            Some(Record::Block { receipts, .. } | Record::Snapshot { receipts, .. }) => receipts,
+            _ => return Ok(None),
+        };
+        Ok(receipts
+            .into_iter()
+            .find(|receipt| receipt.tx_hash == hash)
+            .map(|receipt| (receipt, position.block_no)))
+    }
+
+    fn prune_mode(&self) -> PruneMode {
+        self.prune_mode
+    }
+
+    fn oldest_state(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(self.index.read().oldest_state)
+    }
+
+    fn prune_history(&self, before: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        let mut log = self.log.lock();
+        let mut index = self.index.write();
+        // Nothing to record if the states are already gone
+        if before.min(index.latest) <= index.oldest_state {
+            return Ok(0);
+        }
+        log.append_then(Record::Prune { before }, |_, _| Ok(index.prune(before)))
+    }
+
+    fn import_snapshot(
+        &self,
+        accounts: HashMap<Address, Account>,
+        world: World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Header,
+        trie: TrieUpdate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut log = self.log.lock();
+        let mut index = self.index.write();
+        if index.latest != 0 {
+            return Err(Box::new(DatabaseError::NotEmpty));
+        }
+        let record = Record::Snapshot {
+            accounts,
+            world,
+            block,
+            receipts,
+            parent: Box::new(parent),
+            trie,
+        };
+        log.append_then(record, |record, at| index.apply(record, at))
+    }
+
+    fn snapshot_height(&self) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        Ok(self.index.read().snapshot.map(|(height, _)| height))
+    }
+
+    fn unwind_to(&self, height: u64) -> Result<Vec<Block>, Box<dyn std::error::Error>> {
+        let mut log = self.log.lock();
+        let mut index = self.index.write();
+        if height < index.oldest_state {
+            return Err(Box::new(DatabaseError::Pruned(height)));
+        }
+        if height >= index.latest {
+            return Ok(Vec::new());
+        }
+        let blocks = log.append_then(Record::Unwind { height }, |_, _| index.unwind(height))?;
+        warn!(height, removed = blocks.len(), "DB unwound blocks.");
+        Ok(blocks)
+    }
+
+    fn account_transactions(
+        &self,
+        address: &Address,
+        from: TxPosition,
+        limit: usize,
+    ) -> Result<Vec<AccountTransaction>, Box<dyn std::error::Error>> {
+        let index = self.index.read();
+        let entries = index
+            .account_transactions
+            .range((*address, from)..)
+            .take_while(|((entry_address, _), _)| entry_address == address)
+            .take(limit)
+            .map(|((_, position), hash)| AccountTransaction {
+                position: *position,
+                hash: *hash,
+            })
+            .collect();
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{
+        append_blocks, check_account_changes, check_prune_sweeps_trie_nodes,
+        check_unwind_drops_trie_nodes, shared_address,
+    };
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pint-segment-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // Replaces the first segment with a chain record of `version`
+    fn write_chain_record(dir: &Path, version: u32, chain_id: ChainId) {
+        std::fs::create_dir_all(dir).unwrap();
+        let record = Record::Chain {
+            version,
+            chain_id,
+            genesis_hash: Block::genesis_block().header.calculate_hash().hash(),
+        };
+        std::fs::write(segment_path(dir, 0), frame(&record.encode())).unwrap();
+    }
+
+    #[test]
+    fn test_segments_replay_after_reopen() {
+        let dir = temp_dir("replay");
+        let db = SegmentDB::with_segment_size(&dir, 7, 1024).unwrap();
+        append_blocks(&db, 5);
+        db.unwind_to(3).unwrap();
+        append_blocks(&db, 2);
+        db.prune_history(2).unwrap();
+        assert!(segment_indexes(&dir).unwrap().len() > 1);
+
+        let latest = db.get_latest_block_header().calculate_hash();
+        let state = db.get_state(5).unwrap();
+        let history = db
+            .account_transactions(&shared_address(), TxPosition::default(), 10)
+            .unwrap();
+        let tx_hash = db.get_block(5).unwrap().unwrap().body[0].hash;
+        drop(db);
+
+        let db = SegmentDB::with_segment_size(&dir, 7, 1024).unwrap();
+        assert_eq!(db.latest_block_number(), 5);
+        assert_eq!(db.get_latest_block_header().calculate_hash(), latest);
+        assert_eq!(db.get_state(5).unwrap(), state);
+        assert_eq!(db.oldest_state().unwrap(), 2);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 5);
+        assert_eq!(
+            db.account_transactions(&shared_address(), TxPosition::default(), 10)
+                .unwrap(),
+            history
+        );
+        assert_eq!(db.get_receipt(tx_hash).unwrap().unwrap().1, 5);
+        assert_eq!(db.get_trie_root(5).unwrap(), Some(B256::repeat_byte(5)));
+
+        // The chain keeps growing after a replay
+        append_blocks(&db, 1);
+        assert_eq!(db.latest_block_number(), 6);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_blocks_are_read_from_the_segments() {
+        let dir = temp_dir("read");
+        let db = SegmentDB::new(&dir, 0).unwrap();
+        append_blocks(&db, 2);
+        let tx_hash = db.get_block(2).unwrap().unwrap().body[0].hash;
+        assert_eq!(db.get_receipt(tx_hash).unwrap().unwrap().1, 2);
+
+        // Damage to the record of block 2 shows up on the next read of it
+        let at = db.index.read().blocks[&2];
+        let path = segment_path(&dir, at.segment);
+        let mut raw = std::fs::read(&path).unwrap();
+        raw[at.offset as usize + RECORD_HEADER_LEN + 1] ^= 0xff;
+        std::fs::write(&path, raw).unwrap();
+        let err = db.get_block(2).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DatabaseError::CorruptedSegment { offset, .. }) if *offset == at.offset
+        ));
+        assert!(db.get_receipt(tx_hash).is_err());
+        assert!(db.get_block(1).unwrap().is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_torn_tail_is_dropped() {
+        let dir = temp_dir("torn");
+        let db = SegmentDB::new(&dir, 0).unwrap();
+        append_blocks(&db, 2);
+        drop(db);
+
+        // A crash in the middle of an append leaves part of a record behind
+        let path = segment_path(&dir, 0);
+        let len = std::fs::metadata(&path).unwrap().len();
+        let record = frame(&Record::Unwind { height: 1 }.encode());
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&record[..record.len() - 1]).unwrap();
+        drop(file);
+
+        let db = SegmentDB::new(&dir, 0).unwrap();
+        assert_eq!(db.latest_block_number(), 2);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+        append_blocks(&db, 1);
+        drop(db);
+        assert_eq!(SegmentDB::new(&dir, 0).unwrap().latest_block_number(), 3);
+
+        // Damage before the end is not a torn append
+        let mut raw = std::fs::read(&path).unwrap();
+        raw[RECORD_HEADER_LEN + 1] ^= 0xff;
+        std::fs::write(&path, raw).unwrap();
+        assert!(matches!(
+            SegmentDB::new(&dir, 0),
+            Err(DatabaseError::CorruptedSegment {
+                segment: 0,
+                offset: 0
+            })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_crash_during_rollover() {
+        let dir = temp_dir("rollover");
+        let db = SegmentDB::with_segment_size(&dir, 0, 1024).unwrap();
+        append_blocks(&db, 3);
+        drop(db);
+        let next = segment_indexes(&dir).unwrap().len() as u32;
+        assert!(next > 1);
+
+        // The crash came after the next segment was created, before its first record was complete
+        let path = segment_path(&dir, next);
+        let record = frame(&Record::Unwind { height: 1 }.encode());
+        std::fs::write(&path, &record[..RECORD_HEADER_LEN + 1]).unwrap();
+
+        let db = SegmentDB::with_segment_size(&dir, 0, 1024).unwrap();
+        assert_eq!(db.latest_block_number(), 3);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        // Writing goes on in the emptied segment
+        append_blocks(&db, 1);
+        drop(db);
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        let db = SegmentDB::with_segment_size(&dir, 0, 1024).unwrap();
+        assert_eq!(db.latest_block_number(), 4);
+        assert_eq!(db.basic(&shared_address()).unwrap().unwrap().nonce(), 4);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_refused_record_is_cut_from_the_log() {
+        let dir = temp_dir("refused");
+        let db = SegmentDB::new(&dir, 0).unwrap();
+        append_blocks(&db, 3);
+        db.prune_history(2).unwrap();
+        let path = segment_path(&dir, 0);
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // The state before block 2 is gone, so the unwind fails after its record was written
+        let unwind = Record::Unwind { height: 0 };
+        let result = db
+            .log
+            .lock()
+            .append_then(unwind, |record, at| db.index.write().apply(record, at));
+        assert!(result.is_err());
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+        append_blocks(&db, 1);
+        drop(db);
+
+        let db = SegmentDB::new(&dir, 0).unwrap();
+        assert_eq!(db.latest_block_number(), 4);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_segments_belong_to_one_chain() {
+        let dir = temp_dir("chain");
+        drop(SegmentDB::new(&dir, 1).unwrap());
+        assert!(matches!(
+            SegmentDB::new(&dir, 2),
+            Err(DatabaseError::ChainIdMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_segment_format_version() {
+        let dir = temp_dir("version");
+        write_chain_record(&dir, SEGMENT_FORMAT_VERSION + 1, 0);
+        assert!(matches!(
+            SegmentDB::new(&dir, 0),
+            Err(DatabaseError::UnsupportedSchemaVersion { found, supported })
+                if found == SEGMENT_FORMAT_VERSION + 1 && supported == SEGMENT_FORMAT_VERSION
+        ));
+
+        // No upgrade leads from a version before the first
+        write_chain_record(&dir, 0, 0);
+        assert!(matches!(
+            SegmentDB::new(&dir, 0),
+            Err(DatabaseError::NoMigration { from: 0, to })
+                if to == SEGMENT_FORMAT_VERSION
+        ));
+
+        write_chain_record(&dir, SEGMENT_FORMAT_VERSION, 0);
+        assert_eq!(SegmentDB::new(&dir, 0).unwrap().latest_block_number(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prune_sweeps_unreachable_trie_nodes() {
+        let dir = temp_dir("prune-trie");
+        check_prune_sweeps_trie_nodes(&SegmentDB::new(&dir, 0).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_unwind_drops_unreachable_trie_nodes() {
+        let dir = temp_dir("unwind-trie");
+        check_unwind_drops_trie_nodes(&SegmentDB::new(&dir, 0).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_account_changes_of_one_block() {
+        let dir = temp_dir("changes");
+        check_account_changes(&SegmentDB::new(&dir, 0).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use primitives::{
+    block::Block,
+    receipt::TransactionReceipt,
+    signature::Signature,
+    transaction::{SignedTransaction, Transaction, TxTransfer},
+    types::{Account, Address, B256, U256},
+};
+
+use crate::{
+    error::DatabaseError,
+    traits::{DatabaseTrait, TrieUpdate},
+};
+
+// Changed by every block appended by append_blocks, and the recipient of its transfers
+pub fn shared_address() -> Address {
+    Address::from_byte([1u8; 20])
+}
+
+// Appends `count` blocks, each with one unsigned transfer and its receipt and trie root,
+// a change to shared_address(), a new account and a World entry
+pub fn append_blocks<DB: DatabaseTrait>(db: &DB, count: u64) {
+    append_blocks_with_txs(db, count, 1);
+}
+
+// append_blocks with `txs` transfers per block, so a block read decodes as much as on a busy chain
+pub fn append_blocks_with_txs<DB: DatabaseTrait>(db: &DB, count: u64, txs: u64) {
+    for _ in 0..count {
+        let latest = db.get_latest_block_header();
+        let height = latest.height + 1;
+        let mut block = Block::genesis_block();
+        block.header.height = height;
+        block.header.previous_hash = latest.calculate_hash();
+        let mut receipts = Vec::new();
+        for index in 0..txs {
+            let tx = Transaction::Transfer(TxTransfer {
+                chain_id: 0,
+                nonce: height * txs + index,
+                to: shared_address(),
+                fee: 1,
+                value: U256::from(1),
+                data: Vec::new(),
+            });
+            let hash = tx.encode_for_signing();
+            block
+                .body
+                .push(SignedTransaction::new(tx, Signature::dummy(), hash));
+            receipts.push(TransactionReceipt {
+                tx_hash: hash,
+                index: index as u32,
+                success: true,
+                fee: 1,
+                error: None,
+            });
+        }
+
+        let mut world = db.get_state(height - 1).unwrap().1.unwrap();
+        let accounts = HashMap::from([
+            (shared_address(), Account::new(height, U256::from(height))),
+            (Address::random(), Account::new(0, U256::from(height))),
+        ]);
+        world.set(
+            shared_address(),
+            B256::repeat_byte(height as u8),
+            vec![height as u8],
+        );
+
+        let trie = TrieUpdate {
+            root: B256::repeat_byte(height as u8),
+            nodes: Vec::new(),
+        };
+        db.update(accounts, world, block, trie, receipts).unwrap();
+    }
+}
+
+fn trie_node(left: B256, right: B256) -> [u8; 64] {
+    let mut raw = [0u8; 64];
+    raw[..32].copy_from_slice(left.as_slice());
+    raw[32..].copy_from_slice(right.as_slice());
+    raw
+}
+
+// Gives blocks 1 and 2 tries sharing one inner node, prunes block 1 and checks
+// that only the nodes block 2 no longer reaches are dropped
+pub fn check_prune_sweeps_trie_nodes<DB: DatabaseTrait>(db: &DB) {
+    append_blocks(db, 3);
+    let (shared, old_root, new_root) = (
+        B256::repeat_byte(0x33),
+        B256::repeat_byte(0x11),
+        B256::repeat_byte(0x22),
+    );
+    let shared_node = trie_node(B256::repeat_byte(0xaa), B256::repeat_byte(0xbb));
+    db.update_trie(
+        1,
+        old_root,
+        vec![
+            (old_root, trie_node(shared, B256::repeat_byte(0x01))),
+            (shared, shared_node),
+        ],
+    )
+    .unwrap();
+    db.update_trie(
+        2,
+        new_root,
+        vec![(new_root, trie_node(shared, B256::repeat_byte(0x02)))],
+    )
+    .unwrap();
+
+    assert_eq!(db.prune_history(2).unwrap(), 2);
+    assert!(db.get_trie_node(old_root).unwrap().is_none());
+    assert!(db.get_trie_node(new_root).unwrap().is_some());
+    assert!(db.get_trie_node(shared).unwrap().is_some());
+    assert_eq!(db.get_trie_root(2).unwrap(), Some(new_root));
+}
+
+// Gives blocks 2 and 3 tries sharing one inner node, unwinds them one at a time and checks
+// that each drops the nodes only its root reached. A node no root reaches is not kept at all.
+pub fn check_unwind_drops_trie_nodes<DB: DatabaseTrait>(db: &DB) {
+    append_blocks(db, 3);
+    let (shared, stale, root_2, root_3) = (
+        B256::repeat_byte(0x33),
+        B256::repeat_byte(0x44),
+        B256::repeat_byte(0x22),
+        B256::repeat_byte(0x11),
+    );
+    let shared_node = trie_node(B256::repeat_byte(0xaa), B256::repeat_byte(0xbb));
+    db.update_trie(
+        2,
+        root_2,
+        vec![
+            (root_2, trie_node(shared, B256::repeat_byte(0x02))),
+            (shared, shared_node),
+            (stale, trie_node(shared, B256::repeat_byte(0xcc))),
+        ],
+    )
+    .unwrap();
+    assert!(db.get_trie_node(stale).unwrap().is_none());
+    db.update_trie(
+        3,
+        root_3,
+        vec![(root_3, trie_node(shared, B256::repeat_byte(0x03)))],
+    )
+    .unwrap();
+
+    db.unwind_to(2).unwrap();
+    assert!(db.get_trie_node(root_3).unwrap().is_none());
+    assert!(db.get_trie_node(root_2).unwrap().is_some());
+    assert!(db.get_trie_node(shared).unwrap().is_some());
+
+    db.unwind_to(1).unwrap();
+    assert!(db.get_trie_node(root_2).unwrap().is_none());
+    assert!(db.get_trie_node(shared).unwrap().is_none());
+}
+
+// Reads the change sets and worlds of appended blocks and checks the pruned ones are refused
+pub fn check_account_changes<DB: DatabaseTrait>(db: &DB) {
+    append_blocks(db, 3);
+    let changes = db.get_account_changes(2).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        changes[&shared_address()],
+        Some(Account::new(1, U256::from(1)))
+    );
+    assert_eq!(
+        changes.values().filter(|before| before.is_none()).count(),
+        1
+    );
+    assert_eq!(db.get_world(2).unwrap(), db.get_state(2).unwrap().1);
+
+    db.prune_history(2).unwrap();
+    let pruned = db.get_account_changes(2).unwrap_err();
+    assert!(matches!(
+        pruned.downcast_ref(),
+        Some(DatabaseError::Pruned(1))
+    ));
+    assert!(db.get_world(1).is_err());
+    assert_eq!(db.get_account_changes(3).unwrap().len(), 2);
+}
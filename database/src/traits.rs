@@ -1,30 +1,67 @@
 use std::{collections::HashMap};
 
-use primitives::{block::{Block, Header}, transaction::SignedTransaction, types::{Account, Address, BlockHash, TxHash}, world::World};
+use primitives::{block::{Block, Header}, receipt::TransactionReceipt, transaction::SignedTransaction, types::{Account, Address, B256, BlockHash, TxHash}, world::World};
 
-use crate::error::DatabaseError;
+use crate::{changeset::AccountChangeSet, history::{AccountTransaction, TxPosition}, prune::PruneMode};
+
+/// State trie of one block: its root and the nodes the block added, as (hash, left || right)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrieUpdate {
+    pub root: B256,
+    pub nodes: Vec<(B256, [u8; 64])>,
+}
 
 pub trait DatabaseTrait: Send + Sync + Clone + 'static + Sized {
     fn latest_block_number(&self) -> u64;
     fn basic(&self, address: &Address) -> Result<Option<Account>, Box<dyn std::error::Error>>;
     fn get_state(&self, block_no: u64) -> Result<(Option<HashMap<Address, Account>>, Option<World>), Box<dyn std::error::Error>>;
+    // Accounts changed by the block with their values before it, so the states can be walked
+    // forward without rebuilding each one. Fails with DatabaseError::Pruned if the state before is gone.
+    fn get_account_changes(&self, block_no: u64) -> Result<AccountChangeSet, Box<dyn std::error::Error>>;
+    fn get_world(&self, block_no: u64) -> Result<Option<World>, Box<dyn std::error::Error>>;
     fn get_block(&self, block_no: u64) -> Result<Option<Block>, Box<dyn std::error::Error>>;
     fn get_block_by_hash(&self, hash: BlockHash) -> Result<Option<Block>, Box<dyn std::error::Error>>;
     fn get_transaction_by_hash(&self, hash: TxHash) -> Result<Option<(SignedTransaction, u64)>, Box<dyn std::error::Error>>;
     fn get_header(&self, block_no: u64) -> Result<Option<Header>, Box<dyn std::error::Error>>;
-    fn update(&self, new_account_state: HashMap<Address, Account>, new_field_state: World, new_block: Block)
-        -> Result<(), Box<dyn std::error::Error>>;
+    // Writes the block with its state, state trie and receipts in one transaction.
+    // `changed_accounts` holds only the accounts the block changed, with their new values.
+    fn update(&self, changed_accounts: HashMap<Address, Account>, new_field_state: World, new_block: Block, trie: TrieUpdate,
+        receipts: Vec<TransactionReceipt>) -> Result<(), Box<dyn std::error::Error>>;
     fn get_latest_block_header(&self) -> Header;
-    // remove all datas in front of height
-    fn remove_datas(&self, height: u64) -> Result<(), Box<dyn std::error::Error>> {
-        let latest = self.latest_block_number();
-        for cur in (height + 1)..(latest + 1) {
-            if let Err(_e) = self.remove_data(cur) {
-                return Err(Box::new(DatabaseError::DBError));
-            }
+    // State trie nodes are stored by hash as (left, right) and shared between blocks
+    fn get_trie_node(&self, hash: B256) -> Result<Option<(B256, B256)>, Box<dyn std::error::Error>>;
+    fn get_trie_root(&self, block_no: u64) -> Result<Option<B256>, Box<dyn std::error::Error>>;
+    fn update_trie(&self, block_no: u64, root: B256, nodes: Vec<(B256, [u8; 64])>)
+        -> Result<(), Box<dyn std::error::Error>>;
+    // Receipts are stored by transaction hash. The block number comes from the transaction index.
+    fn get_receipt(&self, hash: TxHash) -> Result<Option<(TransactionReceipt, u64)>, Box<dyn std::error::Error>>;
+    // State history older than the prune mode's window is dropped by prune.
+    // get_state fails with DatabaseError::Pruned below oldest_state.
+    fn prune_mode(&self) -> PruneMode;
+    fn oldest_state(&self) -> Result<u64, Box<dyn std::error::Error>>;
+    // Drops the states of the blocks below `before` and returns how many were dropped.
+    // Trie nodes no longer reachable from a kept root are dropped with them.
+    fn prune_history(&self, before: u64) -> Result<u64, Box<dyn std::error::Error>>;
+    // Starts a database holding only genesis from the full state at `block` and its state trie, in one transaction.
+    // The blocks between genesis and the snapshot are never stored, and its parent states count as pruned.
+    // Only the header of the parent is kept, for the difficulty of the next block.
+    // The block's receipts are stored with it, so its transactions are indexed like those of any other block.
+    fn import_snapshot(&self, accounts: HashMap<Address, Account>, world: World, block: Block,
+        receipts: Vec<TransactionReceipt>, parent: Header, trie: TrieUpdate) -> Result<(), Box<dyn std::error::Error>>;
+    fn snapshot_height(&self) -> Result<Option<u64>, Box<dyn std::error::Error>>;
+    fn prune(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        let before = self.prune_mode().oldest_kept(self.latest_block_number());
+        if before <= self.oldest_state()? {
+            return Ok(0);
         }
-
-        Ok(())
+        self.prune_history(before)
     }
-    fn remove_data(&self, height: u64) -> Result<(), Box<dyn std::error::Error>>;
+    // Removes the blocks above `height` with their indexes, receipts and state in one transaction,
+    // so a failure leaves the chain untouched. Returns the removed blocks in ascending order.
+    // Fails with DatabaseError::Pruned if the state at `height` is gone.
+    fn unwind_to(&self, height: u64) -> Result<Vec<Block>, Box<dyn std::error::Error>>;
+    // Transactions sent or received by `address` in chain order, at most `limit` starting at `from`.
+    // Kept for every stored block and removed with the block by unwind_to.
+    fn account_transactions(&self, address: &Address, from: TxPosition, limit: usize)
+        -> Result<Vec<AccountTransaction>, Box<dyn std::error::Error>>;
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 use primitives::{
+    merkle::MerkleProof,
     transaction::{SignedTransaction, Transaction},
-    types::{Address, U256},
+    types::{Address, B256, U256},
 };
 use serde_json::json;
 
@@ -47,6 +48,77 @@ pub async fn get_tx_from_rpc(tx_hash: String, url: &str) -> anyhow::Result<Trans
     Ok(signed.tx)
 }
 
+pub async fn get_tx_proof_from_rpc(
+    tx_hash: String,
+    url: &str,
+) -> anyhow::Result<(MerkleProof, B256)> {
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "method": "transaction_proof",
+        "params": [tx_hash],
+        "id": 0
+    });
+
+    let res = reqwest::Client::new()
+        .post(url)
+        .json(&payload)
+        .send()
+        .await?;
+
+    let body = res.text().await?;
+    let resp: serde_json::Value = serde_json::from_str(&body)?;
+
+    let root = resp["result"]["transaction_root"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing result.transaction_root"))?;
+    let root = B256::from_slice(&hex::decode(root)?);
+
+    let mut proof = MerkleProof::default();
+    for sibling in resp["result"]["siblings"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("missing result.siblings"))?
+    {
+        let sibling = sibling.as_str().ok_or_else(|| anyhow::anyhow!("wrong sibling"))?;
+        proof.siblings.push(B256::from_slice(&hex::decode(sibling)?));
+    }
+    for is_right in resp["result"]["path"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("missing result.path"))?
+    {
+        proof.path.push(is_right.as_bool().ok_or_else(|| anyhow::anyhow!("wrong path"))?);
+    }
+
+    Ok((proof, root))
+}
+
+pub async fn get_receipt_from_rpc(tx_hash: String, url: &str) -> anyhow::Result<(bool, u128)> {
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "method": "transaction_receipt",
+        "params": [tx_hash],
+        "id": 0
+    });
+
+    let res = reqwest::Client::new()
+        .post(url)
+        .json(&payload)
+        .send()
+        .await?;
+
+    let body = res.text().await?;
+    let resp: serde_json::Value = serde_json::from_str(&body)?;
+
+    let success = resp["result"]["success"]
+        .as_bool()
+        .ok_or_else(|| anyhow::anyhow!("missing result.success"))?;
+    let fee = resp["result"]["fee"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("missing result.fee"))?
+        .parse()?;
+
+    Ok((success, fee))
+}
+
 pub async fn get_account_from_rpc(address: Address, url: &str) -> anyhow::Result<(u64, U256)> {
     let payload = json!({
         "jsonrpc": "2.0",
@@ -100,3 +172,60 @@ pub async fn get_chain_height_from_rpc(url: &str) -> anyhow::Result<u64> {
 
     Ok(block_height)
 }
+
+// One page of the account's history: (tx hash hex, block number) per entry, and where the next page starts
+pub async fn get_account_transactions_from_rpc(
+    address: Address,
+    from: Option<(u64, u32)>,
+    limit: usize,
+    url: &str,
+) -> anyhow::Result<(Vec<(String, u64)>, Option<(u64, u32)>)> {
+    let (from_block, from_index) = from.unwrap_or_default();
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "method": "account_transactions",
+        "params": [
+            address.get_addr_hex(),
+            from_block.to_string(),
+            from_index.to_string(),
+            limit.to_string()
+        ],
+        "id": 0
+    });
+
+    let res = reqwest::Client::new()
+        .post(url)
+        .json(&payload)
+        .send()
+        .await?;
+
+    let body = res.text().await?;
+    let resp: serde_json::Value = serde_json::from_str(&body)?;
+
+    let mut transactions = Vec::new();
+    for entry in resp["result"]["transactions"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("missing result.transactions"))?
+    {
+        let hash = entry["tx_hash"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("missing tx_hash"))?;
+        let block_number = entry["block_number"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("missing block_number"))?;
+        transactions.push((hash.to_string(), block_number));
+    }
+    let next = match &resp["result"]["next"] {
+        serde_json::Value::Null => None,
+        next => Some((
+            next["block_number"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("missing next.block_number"))?,
+            next["index"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("missing next.index"))? as u32,
+        )),
+    };
+
+    Ok((transactions, next))
+}
@@ -7,11 +7,13 @@ use e2e_test::{
     common::{create_key_pairs, create_signed},
     process::{NodeConfig, launch_test_node},
     rpc_client::{
-        get_account_from_rpc, get_chain_height_from_rpc, get_tx_from_rpc, send_tx_to_rpc,
+        get_account_from_rpc, get_account_transactions_from_rpc, get_chain_height_from_rpc,
+        get_receipt_from_rpc, get_tx_from_rpc, get_tx_proof_from_rpc, send_tx_to_rpc,
     },
 };
 use primitives::{
-    transaction::Transaction,
+    merkle::verify_merkle_proof,
+    transaction::{Transaction, TxTransfer},
     types::{Address, U256},
 };
 use tracing_subscriber::EnvFilter;
@@ -47,13 +49,14 @@ async fn e2e_single_node_basic() {
         .await
         .expect("Account must exists");
 
-    let tx = Transaction {
+    let tx = Transaction::Transfer(TxTransfer {
         chain_id: 0,
         nonce: 0,
         to: addr_apple,
         fee: 5,
         value: U256::from(1000),
-    };
+        data: Vec::new(),
+    });
 
     let signed = create_signed(&key_pint, tx);
 
@@ -65,17 +68,37 @@ async fn e2e_single_node_basic() {
 
     // Transaction Request
     let encoded_hash = hex::encode(signed.hash.0.as_slice());
-    let tx = get_tx_from_rpc(encoded_hash, boot_node_url)
+    let tx = get_tx_from_rpc(encoded_hash.clone(), boot_node_url)
         .await
         .expect("tx must exist");
     let tx_hash = tx.encode_for_signing();
     assert_eq!(tx_hash, signed.hash);
+    // Transaction Proof Request
+    let (proof, transaction_root) = get_tx_proof_from_rpc(encoded_hash.clone(), boot_node_url)
+        .await
+        .expect("proof must exist");
+    assert!(verify_merkle_proof(signed.hash.hash(), &proof, transaction_root));
+    // Receipt Request
+    let (success, fee) = get_receipt_from_rpc(encoded_hash.clone(), boot_node_url)
+        .await
+        .expect("receipt must exist");
+    assert!(success);
+    assert_eq!(fee, 5);
     // Account Request
     let (nonce, balance) = get_account_from_rpc(addr_pint, boot_node_url)
         .await
         .expect("Account must exists");
     assert_eq!(nonce - nonce_before, 1);
     assert_eq!(balance_before - balance, 1005);
+    // History of the sender and the recipient
+    for address in [addr_pint, addr_apple] {
+        let (transactions, next) =
+            get_account_transactions_from_rpc(address, None, 10, boot_node_url)
+                .await
+                .expect("history must exist");
+        assert_eq!(transactions, vec![(encoded_hash.clone(), 1)]);
+        assert_eq!(next, None);
+    }
     // Blcok Height Request
     let block_height = get_chain_height_from_rpc(boot_node_url)
         .await
@@ -89,13 +112,14 @@ async fn e2e_single_node_basic() {
     assert_eq!(balance, 5);
 
     for i in 1..5 {
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce: 5 - i,
             to: addr_apple,
             fee: 5 * i as u128,
             value: U256::from(1000 * i),
-        };
+            data: Vec::new(),
+        });
 
         let signed = create_signed(&key_pint, tx);
         let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
@@ -146,13 +170,14 @@ async fn e2e_multi_node_basic() {
         .await
         .expect("Account must exists");
 
-    let tx = Transaction {
+    let tx = Transaction::Transfer(TxTransfer {
         chain_id: 0,
         nonce: 0,
         to: addr_apple,
         fee: 5,
         value: U256::from(1000),
-    };
+        data: Vec::new(),
+    });
 
     let signed = create_signed(&key_pint, tx);
     let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
@@ -163,11 +188,15 @@ async fn e2e_multi_node_basic() {
     // Boot node!
     // Transaction Request
     let encoded_hash = hex::encode(signed.hash.0.as_slice());
-    let tx = get_tx_from_rpc(encoded_hash, boot_node_url)
+    let tx = get_tx_from_rpc(encoded_hash.clone(), boot_node_url)
         .await
         .expect("tx must exist");
     let tx_hash = tx.encode_for_signing();
     assert_eq!(tx_hash, signed.hash);
+    let (success, _fee) = get_receipt_from_rpc(encoded_hash, boot_node_url)
+        .await
+        .expect("receipt must exist");
+    assert!(success);
     // Account Request
     let (nonce, balance) = get_account_from_rpc(addr_pint, boot_node_url)
         .await
@@ -208,24 +237,26 @@ async fn e2e_multi_node_basic() {
     assert_eq!(block_height, 1);
 
     for i in 1..5 {
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce: 5 - i,
             to: addr_apple,
             fee: 5 * i as u128,
             value: U256::from(1000 * i),
-        };
+            data: Vec::new(),
+        });
 
         let signed = create_signed(&key_pint, tx);
         let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
 
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce: 4 - i,
             to: addr_banana,
             fee: 5 * i as u128,
             value: U256::from(1000 * i),
-        };
+            data: Vec::new(),
+        });
 
         let signed = create_signed(&_key_apple, tx);
         let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error("Invalid secret key")]
+    InvalidSecretKey,
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+    #[error("Invalid derivation path: {0}")]
+    InvalidDerivationPath(String),
+    #[error("Wrong password or corrupted keystore file")]
+    DecryptionFailed,
+    #[error("Unsupported keystore: {0}")]
+    Unsupported(String),
+    #[error("Invalid keystore file")]
+    InvalidFile(#[from] serde_json::Error),
+    #[error("Invalid hex in keystore file")]
+    InvalidHex(#[from] hex::FromHexError),
+    #[error("Key does not exist in keystore")]
+    KeyNotFound,
+    #[error("Key already exists in keystore")]
+    KeyAlreadyExists,
+    #[error("Keystore IO Error")]
+    IoError(#[from] std::io::Error),
+}
@@ -0,0 +1,94 @@
+use k256::ecdsa::{RecoveryId, Signature as ECDSASig, SigningKey};
+use primitives::{
+    signature::Signature,
+    transaction::{SignedTransaction, Transaction},
+    types::Address,
+};
+use sha2::{Digest, Sha256};
+
+use crate::error::KeystoreError;
+
+/// secp256k1 key and its Pint address
+#[derive(Clone)]
+pub struct KeyPair {
+    signing_key: SigningKey,
+    address: Address,
+}
+
+impl std::fmt::Debug for KeyPair {
+    // Never print the secret
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+impl KeyPair {
+    pub fn generate() -> Self {
+        loop {
+            // Out of range secrets are astronomically rare, just draw again
+            if let Ok(key) = Self::from_secret(&rand::random::<[u8; 32]>()) {
+                return key;
+            }
+        }
+    }
+
+    pub fn from_secret(secret: &[u8]) -> Result<Self, KeystoreError> {
+        let signing_key =
+            SigningKey::from_slice(secret).map_err(|_| KeystoreError::InvalidSecretKey)?;
+        let address = Address::from_public_key(signing_key.verifying_key());
+        Ok(Self {
+            signing_key,
+            address,
+        })
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.signing_key.to_bytes().into()
+    }
+
+    pub fn sign(&self, tx: Transaction) -> SignedTransaction {
+        let tx_hash = tx.encode_for_signing();
+        let digest = Sha256::new_with_prefix(tx_hash.hash());
+        let (sig, recid): (ECDSASig, RecoveryId) = self
+            .signing_key
+            .sign_digest_recoverable(digest)
+            .expect("signing with a valid key can't fail");
+        SignedTransaction::new(tx, Signature::from_sig(sig, recid), tx_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{transaction::TxTransfer, types::U256};
+
+    use super::*;
+
+    #[test]
+    fn test_key_address_matches_recovered_signer() {
+        let key = KeyPair::generate();
+        let tx = Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 1,
+            value: U256::from(1),
+            data: Vec::new(),
+        });
+        let signed = key.sign(tx);
+        assert_eq!(signed.recover_signer().unwrap(), key.address());
+
+        let restored = KeyPair::from_secret(&key.secret_bytes()).unwrap();
+        assert_eq!(restored.address(), key.address());
+        assert!(KeyPair::from_secret(&[0u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    fs::{DirBuilder, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use primitives::types::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::error::KeystoreError;
+pub use crate::{key::KeyPair, mnemonic::Mnemonic};
+
+pub mod error;
+pub mod key;
+pub mod mnemonic;
+
+pub const KEYSTORE_VERSION: u32 = 1;
+// scrypt cost: N = 2^15, r = 8, p = 1
+pub const DEFAULT_SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+// Upper bounds on the cost read from a file, so a crafted file cannot make decryption
+// allocate or spin without limit. 128 * r * 2^log_n bytes is at most 256 MiB.
+pub const MAX_SCRYPT_LOG_N: u8 = 18;
+const MAX_SCRYPT_R: u32 = 8;
+const MAX_SCRYPT_P: u32 = 4;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// One encrypted key, stored as `<address>.json`.
+/// The address is kept in plain text so keys can be listed without a password.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeystoreFile {
+    pub version: u32,
+    pub address: String,
+    pub crypto: CryptoParams,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CryptoParams {
+    pub kdf: String,
+    pub kdf_log_n: u8,
+    pub kdf_r: u32,
+    pub kdf_p: u32,
+    pub salt: String,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl KeystoreFile {
+    /// scrypt(password) -> AES-256-GCM key. The address is bound as associated data.
+    pub fn encrypt(key: &KeyPair, password: &str, log_n: u8) -> Result<Self, KeystoreError> {
+        let salt: [u8; SALT_LEN] = rand::random();
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let derived = derive_key(password, &salt, log_n, SCRYPT_R, SCRYPT_P)?;
+
+        let cipher = Aes256Gcm::new_from_slice(&derived).expect("derived key is 32 bytes");
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &key.secret_bytes(),
+                    aad: key.address().get_addr(),
+                },
+            )
+            .map_err(|_| KeystoreError::DecryptionFailed)?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            address: key.address().get_addr_hex(),
+            crypto: CryptoParams {
+                kdf: "scrypt".to_string(),
+                kdf_log_n: log_n,
+                kdf_r: SCRYPT_R,
+                kdf_p: SCRYPT_P,
+                salt: hex::encode(salt),
+                cipher: "aes-256-gcm".to_string(),
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+            },
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<KeyPair, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::Unsupported(format!(
+                "version {}",
+                self.version
+            )));
+        }
+        let crypto = &self.crypto;
+        if crypto.kdf != "scrypt" || crypto.cipher != "aes-256-gcm" {
+            return Err(KeystoreError::Unsupported(format!(
+                "{}/{}",
+                crypto.kdf, crypto.cipher
+            )));
+        }
+
+        let address = hex::decode(&self.address)?;
+        let salt = hex::decode(&crypto.salt)?;
+        let nonce = hex::decode(&crypto.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(KeystoreError::DecryptionFailed);
+        }
+        let ciphertext = hex::decode(&crypto.ciphertext)?;
+        let derived = derive_key(
+            password,
+            &salt,
+            crypto.kdf_log_n,
+            crypto.kdf_r,
+            crypto.kdf_p,
+        )?;
+
+        let cipher = Aes256Gcm::new_from_slice(&derived).expect("derived key is 32 bytes");
+        let secret = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &address,
+                },
+            )
+            .map_err(|_| KeystoreError::DecryptionFailed)?;
+
+        let key = KeyPair::from_secret(&secret)?;
+        if key.address().get_addr() != address.as_slice() {
+            return Err(KeystoreError::DecryptionFailed);
+        }
+        Ok(key)
+    }
+}
+
+fn derive_key(
+    password: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<[u8; 32], KeystoreError> {
+    if log_n > MAX_SCRYPT_LOG_N || r > MAX_SCRYPT_R || p > MAX_SCRYPT_P {
+        return Err(KeystoreError::Unsupported(format!(
+            "scrypt cost log_n={log_n} r={r} p={p}"
+        )));
+    }
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|_| KeystoreError::Unsupported("scrypt params".to_string()))?;
+    let mut derived = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut derived)
+        .map_err(|_| KeystoreError::Unsupported("scrypt output".to_string()))?;
+    Ok(derived)
+}
+
+/// Directory of encrypted key files
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    dir: PathBuf,
+    kdf_log_n: u8,
+}
+
+impl Keystore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            kdf_log_n: DEFAULT_SCRYPT_LOG_N,
+        }
+    }
+
+    // Lower cost is only meant for tests
+    pub fn with_kdf_log_n(mut self, log_n: u8) -> Self {
+        self.kdf_log_n = log_n;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path_of(&self, address: &Address) -> PathBuf {
+        self.dir.join(format!("{}.json", address.get_addr_hex()))
+    }
+
+    /// Generates a new key and stores it
+    pub fn create(&self, password: &str) -> Result<Address, KeystoreError> {
+        self.store(&KeyPair::generate(), password)
+    }
+
+    /// Stores an existing secret key
+    pub fn import(&self, secret: &[u8], password: &str) -> Result<Address, KeystoreError> {
+        self.store(&KeyPair::from_secret(secret)?, password)
+    }
+
+    pub fn store(&self, key: &KeyPair, password: &str) -> Result<Address, KeystoreError> {
+        let path = self.path_of(&key.address());
+        if path.exists() {
+            return Err(KeystoreError::KeyAlreadyExists);
+        }
+        let mut dir = DirBuilder::new();
+        dir.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut dir, 0o700);
+        dir.create(&self.dir)?;
+
+        let file = KeystoreFile::encrypt(key, password, self.kdf_log_n)?;
+        // Readable by the owner only, and never over an existing key
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut out = options.open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => KeystoreError::KeyAlreadyExists,
+            _ => KeystoreError::IoError(e),
+        })?;
+        out.write_all(&serde_json::to_vec_pretty(&file)?)?;
+        out.sync_all()?;
+        Ok(key.address())
+    }
+
+    pub fn load(&self, address: &Address, password: &str) -> Result<KeyPair, KeystoreError> {
+        self.read_file(&self.path_of(address))?.decrypt(password)
+    }
+
+    /// Addresses of every key in the keystore, sorted
+    pub fn list(&self) -> Result<Vec<Address>, KeystoreError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut addresses = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let file = self.read_file(&path)?;
+            let address = Address::from_hex(file.address)
+                .map_err(|_| KeystoreError::Unsupported("address".to_string()))?;
+            addresses.push(address);
+        }
+        addresses.sort();
+        Ok(addresses)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<KeystoreFile, KeystoreError> {
+        if !path.exists() {
+            return Err(KeystoreError::KeyNotFound);
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_keystore(name: &str) -> Keystore {
+        let dir =
+            std::env::temp_dir().join(format!("pint-keystore-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Keystore::new(dir).with_kdf_log_n(4)
+    }
+
+    #[test]
+    fn test_keystore_create_list_load() {
+        let keystore = temp_keystore("create");
+        let first = keystore.create("pint").unwrap();
+        let second = keystore
+            .import(&KeyPair::generate().secret_bytes(), "chain")
+            .unwrap();
+
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(keystore.list().unwrap(), expected);
+
+        assert_eq!(keystore.load(&first, "pint").unwrap().address(), first);
+        assert!(matches!(
+            keystore.load(&first, "wrong"),
+            Err(KeystoreError::DecryptionFailed)
+        ));
+        assert!(matches!(
+            keystore.load(&Address::random(), "pint"),
+            Err(KeystoreError::KeyNotFound)
+        ));
+
+        std::fs::remove_dir_all(keystore.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_keystore_import_is_stable() {
+        let keystore = temp_keystore("import");
+        let key = KeyPair::generate();
+        let address = keystore.import(&key.secret_bytes(), "pint").unwrap();
+        assert_eq!(address, key.address());
+        assert!(matches!(
+            keystore.import(&key.secret_bytes(), "pint"),
+            Err(KeystoreError::KeyAlreadyExists)
+        ));
+
+        // Tampering with the stored address breaks the authentication tag
+        let path = keystore.path_of(&address);
+        let mut file: KeystoreFile =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        file.address = Address::random().get_addr_hex();
+        assert!(file.decrypt("pint").is_err());
+
+        std::fs::remove_dir_all(keystore.dir()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let keystore = temp_keystore("private");
+        let address = keystore.create("pint").unwrap();
+        let mode = std::fs::metadata(keystore.path_of(&address))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        std::fs::remove_dir_all(keystore.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_kdf_cost_is_bounded() {
+        let keystore = temp_keystore("kdf");
+        let address = keystore.create("pint").unwrap();
+        let read = || -> KeystoreFile {
+            serde_json::from_slice(&std::fs::read(keystore.path_of(&address)).unwrap()).unwrap()
+        };
+
+        for (log_n, r, p) in [
+            (MAX_SCRYPT_LOG_N + 1, SCRYPT_R, SCRYPT_P),
+            (4, u32::MAX, SCRYPT_P),
+            (4, SCRYPT_R, 64),
+        ] {
+            let mut file = read();
+            (file.crypto.kdf_log_n, file.crypto.kdf_r, file.crypto.kdf_p) = (log_n, r, p);
+            assert!(matches!(
+                file.decrypt("pint"),
+                Err(KeystoreError::Unsupported(_))
+            ));
+        }
+        assert_eq!(read().decrypt("pint").unwrap().address(), address);
+
+        std::fs::remove_dir_all(keystore.dir()).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use bip32::{DerivationPath, XPrv};
+
+use crate::{error::KeystoreError, key::KeyPair};
+
+// Pint has no registered SLIP-44 coin type, so accounts live under the Ethereum
+// one (60'). Keys are plain secp256k1, only the address format differs.
+pub const DEFAULT_DERIVATION_PATH_PREFIX: &str = "m/44'/60'/0'/0";
+
+/// BIP-39 mnemonic (English wordlist)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mnemonic(bip39::Mnemonic);
+
+impl Mnemonic {
+    /// New random phrase of 12, 15, 18, 21 or 24 words
+    pub fn generate(word_count: usize) -> Result<Self, KeystoreError> {
+        if !(12..=24).contains(&word_count) || !word_count.is_multiple_of(3) {
+            return Err(KeystoreError::InvalidMnemonic(format!(
+                "word count {}",
+                word_count
+            )));
+        }
+        // 32 bits of entropy per 3 words
+        let entropy: [u8; 32] = rand::random();
+        Self::from_entropy(&entropy[..word_count / 3 * 4])
+    }
+
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, KeystoreError> {
+        bip39::Mnemonic::from_entropy(entropy)
+            .map(Self)
+            .map_err(|e| KeystoreError::InvalidMnemonic(e.to_string()))
+    }
+
+    /// Parses and validates a phrase, including its checksum
+    pub fn from_phrase(phrase: &str) -> Result<Self, KeystoreError> {
+        bip39::Mnemonic::parse(phrase)
+            .map(Self)
+            .map_err(|e| KeystoreError::InvalidMnemonic(e.to_string()))
+    }
+
+    pub fn phrase(&self) -> String {
+        self.0.to_string()
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.0.word_count()
+    }
+
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        self.0.to_seed(passphrase)
+    }
+
+    /// Key at a BIP-32 path such as `m/44'/60'/0'/0/0`
+    pub fn derive_key(&self, passphrase: &str, path: &str) -> Result<KeyPair, KeystoreError> {
+        derive_key_from_seed(&self.to_seed(passphrase), path)
+    }
+
+    /// index-th account under DEFAULT_DERIVATION_PATH_PREFIX
+    pub fn derive_account(&self, passphrase: &str, index: u32) -> Result<KeyPair, KeystoreError> {
+        self.derive_key(
+            passphrase,
+            &format!("{}/{}", DEFAULT_DERIVATION_PATH_PREFIX, index),
+        )
+    }
+}
+
+pub fn derive_key_from_seed(seed: &[u8], path: &str) -> Result<KeyPair, KeystoreError> {
+    let path: DerivationPath = path
+        .parse()
+        .map_err(|_| KeystoreError::InvalidDerivationPath(path.to_string()))?;
+    let xprv = XPrv::derive_from_path(seed, &path)
+        .map_err(|e| KeystoreError::InvalidDerivationPath(e.to_string()))?;
+    KeyPair::from_secret(&xprv.private_key().to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-39 reference vectors (passphrase "TREZOR")
+    const BIP39_VECTORS: [(&str, &str, &str); 3] = [
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+            "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+        ),
+    ];
+
+    #[test]
+    fn test_bip39_vectors() {
+        for (entropy, phrase, seed) in BIP39_VECTORS {
+            let mnemonic = Mnemonic::from_entropy(&hex::decode(entropy).unwrap()).unwrap();
+            assert_eq!(mnemonic.phrase(), phrase);
+            assert_eq!(Mnemonic::from_phrase(phrase).unwrap(), mnemonic);
+            assert_eq!(hex::encode(mnemonic.to_seed("TREZOR")), seed);
+        }
+
+        // Bad checksum and unknown word
+        assert!(Mnemonic::from_phrase(&"abandon ".repeat(12)).is_err());
+        assert!(Mnemonic::from_phrase("pint abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about").is_err());
+
+        let generated = Mnemonic::generate(24).unwrap();
+        assert_eq!(generated.word_count(), 24);
+        assert_eq!(
+            Mnemonic::from_phrase(&generated.phrase()).unwrap(),
+            generated
+        );
+        assert!(Mnemonic::generate(13).is_err());
+    }
+
+    // BIP-32 test vector 1
+    #[test]
+    fn test_bip32_vectors() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let vectors = [
+            (
+                "m",
+                "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35",
+            ),
+            (
+                "m/0'",
+                "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+            ),
+            (
+                "m/0'/1",
+                "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+            ),
+        ];
+        for (path, secret) in vectors {
+            let key = derive_key_from_seed(&seed, path).unwrap();
+            assert_eq!(hex::encode(key.secret_bytes()), secret);
+        }
+        assert!(derive_key_from_seed(&seed, "0/1").is_err());
+    }
+
+    // Pint accounts of the "abandon ... about" phrase with an empty passphrase
+    #[test]
+    fn test_pint_account_vectors() {
+        let mnemonic = Mnemonic::from_phrase(BIP39_VECTORS[0].1).unwrap();
+        let vectors = [
+            (
+                0,
+                "1ab42cc412b618bdea3a599e3c9bae199ebf030895b039e9db1e30dafb12b727",
+                "eca7ce6b2e58330672795e847c4d6af44e024230",
+            ),
+            (
+                1,
+                "9a983cb3d832fbde5ab49d692b7a8bf5b5d232479c99333d0fc8e1d21f1b55b6",
+                "4a8cea6c7b6a6057d0eb90a9a875f446c131031d",
+            ),
+        ];
+        for (index, secret, address) in vectors {
+            let key = mnemonic.derive_account("", index).unwrap();
+            assert_eq!(hex::encode(key.secret_bytes()), secret);
+            assert_eq!(key.address().get_addr_hex(), address);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::net::{IpAddr, SocketAddr};
 
+use database::error::DatabaseError;
 use primitives::{
     handle::{ConsensusHandleMessage, Handle, NetworkHandleMessage},
     types::BlockHash,
@@ -229,7 +230,9 @@ impl<DB: DatabaseTrait + Sync + Send + 'static> NetworkManager<DB> {
 
                                 if !is_boot_node {
                                     info!("Try to synchronize db and mem-pool.");
-                                    this.network_handle.send(NetworkHandleMessage::RequestData(1));
+                                    // Blocks up to the latest are already stored, e.g. from a snapshot
+                                    let from = this.provider.db().latest_block_number() + 1;
+                                    this.network_handle.send(NetworkHandleMessage::RequestData(from));
                                 }
                             }
 
@@ -307,39 +310,20 @@ impl<DB: DatabaseTrait + Sync + Send + 'static> NetworkManager<DB> {
                             }
 
                             NetworkHandleMessage::RespondChainDataResult(_len, hash_vec) => {
-                                let mut found = false;
-                                for hash in hash_vec.iter().rev() {
-                                    match this.provider.db().get_block_by_hash(hash.clone()) {
-                                        Ok(Some(block)) => {
-                                            found = true;
-                                            let height = block.header().height;
-                                            // delete datas
-                                            if let Err(e) = this.provider.db().remove_datas(height) {
-                                                error!(error = ?e, "RequestChainData: Failed to clean db datas.");
-                                                break;
-                                            }
-                                            // then request new data
-                                            this.network_handle.send(NetworkHandleMessage::RequestData(height+1));
-                                            break;
-                                        }
-                                        Ok(None) => {
-                                            continue;
-                                        }
-                                        Err(_e) => {
-                                            continue;
-                                        }
-                                    }
-                                }
-
-                                // reorg chain from scratch
-                                if !found {
-                                    if let Err(e) = this.provider.db().remove_datas(0) {
-                                        error!(error = ?e, "RequestChainData: Failed to clean db datas.");
-                                        break;
+                                let height = match reorg_base(this.provider.db(), &hash_vec) {
+                                    Ok(height) => height,
+                                    Err(e) => {
+                                        error!(error = ?e, "RequestChainData: Can't reorg to the peer's chain.");
+                                        continue;
                                     }
-                                    // then request new data
-                                    this.network_handle.send(NetworkHandleMessage::RequestData(1));
+                                };
+                                // delete datas
+                                if let Err(e) = this.unwind_to(height) {
+                                    error!(error = ?e, "RequestChainData: Failed to clean db datas.");
+                                    continue;
                                 }
+                                // then request new data
+                                this.network_handle.send(NetworkHandleMessage::RequestData(height+1));
                             }
                             NetworkHandleMessage::Ping (ip_addr, port) => {
                                 let socket_addr = SocketAddr::from((ip_addr, port));
@@ -364,6 +348,25 @@ impl<DB: DatabaseTrait + Sync + Send + 'static> NetworkManager<DB> {
         });
     }
 
+    // Drops the blocks above `height` and returns their transactions to the pool
+    fn unwind_to(&self, height: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let removed = self.provider.db().unwind_to(height)?;
+        for signed in removed.into_iter().flat_map(|block| block.body) {
+            let recovered = match signed.into_recovered() {
+                Ok(recovered) => recovered,
+                Err(e) => {
+                    warn!(error = ?e, "Unwound transaction Recover Error.");
+                    continue;
+                }
+            };
+            if let Err(e) = self.pool.add_transaction(TransactionOrigin::External, recovered) {
+                debug!(error = ?e, "Unwound transaction is not pooled again.");
+            }
+        }
+        self.pool.reorganize_pool();
+        Ok(())
+    }
+
     pub async fn connect_with_boot_node(
         &mut self,
         _ip_addr: IpAddr,
@@ -449,6 +452,26 @@ impl<DB: DatabaseTrait + std::fmt::Debug> std::fmt::Debug for NetworkManager<DB>
     }
 }
 
+// Height to unwind to before syncing from a peer whose latest block hashes are `hashes`: the newest block
+// both chains hold, or the oldest stored state when they share none. States below it are pruned or came
+// before a snapshot, so a peer that forked off below it is refused with DatabaseError::Pruned.
+fn reorg_base<DB: DatabaseTrait>(
+    db: &DB,
+    hashes: &[BlockHash],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let oldest = db.oldest_state()?;
+    for hash in hashes.iter().rev() {
+        if let Ok(Some(block)) = db.get_block_by_hash(hash.clone()) {
+            let height = block.header().height;
+            if height < oldest {
+                return Err(Box::new(DatabaseError::Pruned(height)));
+            }
+            return Ok(height);
+        }
+    }
+    Ok(oldest)
+}
+
 #[derive(Debug)]
 pub struct NoopConsensusHandle;
 
@@ -458,3 +481,68 @@ impl Handle for NoopConsensusHandle {
         // Do nothing
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use database::{immemorydb::InMemoryDB, prune::PruneMode};
+    use primitives::types::Address;
+    use provider::test_utils::import_empty_block;
+
+    use super::*;
+
+    fn chain(len: u64, prune_mode: PruneMode) -> ProviderFactory<Arc<InMemoryDB>> {
+        let db = InMemoryDB::genesis_state().with_prune_mode(prune_mode);
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        for _ in 0..len {
+            import_empty_block(&provider, Address::random());
+        }
+        provider
+    }
+
+    fn hashes<DB: DatabaseTrait>(
+        provider: &ProviderFactory<DB>,
+        heights: &[u64],
+    ) -> Vec<BlockHash> {
+        heights
+            .iter()
+            .map(|height| {
+                provider
+                    .db()
+                    .get_header(*height)
+                    .unwrap()
+                    .unwrap()
+                    .calculate_hash()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reorg_stays_above_pruned_states() {
+        let local = chain(4, PruneMode::KeepLast(1));
+        local.db().prune().unwrap();
+        let oldest = local.db().oldest_state().unwrap();
+        assert_eq!(oldest, 3);
+
+        // The newest shared block
+        assert_eq!(
+            reorg_base(local.db(), &hashes(&local, &[2, 3, 4])).unwrap(),
+            4
+        );
+
+        // Nothing shared: back to the oldest state rather than genesis, which is pruned
+        let other = chain(4, PruneMode::Archive);
+        let base = reorg_base(local.db(), &hashes(&other, &[1, 2, 3])).unwrap();
+        assert_eq!(base, oldest);
+        assert!(local.db().unwind_to(base).is_ok());
+        assert_eq!(local.db().latest_block_number(), oldest);
+
+        // A peer that forked off below the oldest state is refused
+        let err = reorg_base(local.db(), &hashes(&local, &[1, 2])).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DatabaseError>(),
+            Some(DatabaseError::Pruned(2))
+        ));
+    }
+}
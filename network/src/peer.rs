@@ -1,7 +1,7 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use parking_lot::RwLock;
-use primitives::handle::Handle;
+use primitives::handle::{FRAME_HEADER_LEN, Handle, MAX_FRAME_PAYLOAD};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
@@ -128,6 +128,44 @@ impl PeerList {
     }
 }
 
+// Decodes the complete frames at the start of `pending` and keeps the rest for the next read.
+// A frame that fails to decode is skipped on its own. Fails with the announced payload length
+// if a frame is over MAX_FRAME_PAYLOAD, so the buffer never grows past one frame.
+fn decode_frames(
+    pending: &mut Vec<u8>,
+    addr: SocketAddr,
+) -> Result<Vec<NetworkHandleMessage>, u64> {
+    let mut messages = Vec::new();
+    let mut off: usize = 0;
+    while let Some(payload_len) = NetworkHandleMessage::frame_payload_len(&pending[off..]) {
+        if payload_len > MAX_FRAME_PAYLOAD {
+            return Err(payload_len);
+        }
+        let frame_len = FRAME_HEADER_LEN + payload_len as usize;
+        match NetworkHandleMessage::decode(&pending[off..], addr) {
+            Ok((_, 0)) => {
+                // Wait for the rest of the frame
+                break;
+            }
+            Ok((res, used)) => {
+                if let Some(decoded) = res {
+                    messages.push(decoded);
+                }
+                off += used;
+            }
+            Err(e) => {
+                error!(
+                    error = ?e,
+                    "Failed to decode Network handle message from {:?}", addr
+                );
+                off += frame_len;
+            }
+        }
+    }
+    pending.drain(..off);
+    Ok(messages)
+}
+
 impl PeerList {
     pub fn insert_new_peer(
         &mut self,
@@ -149,6 +187,8 @@ impl PeerList {
         let incoming = async move {
             info!("Peer {:?} incoming task has spawned.", addr);
             let mut buf = [0u8; 1024];
+            // Bytes of a frame which is not complete yet
+            let mut pending: Vec<u8> = Vec::new();
             loop {
                 match read_socket.read(&mut buf).await {
                     Ok(0) => {
@@ -158,30 +198,21 @@ impl PeerList {
                     }
                     Ok(n) => {
                         debug!("encoded {} data incomed", n);
-                        let mut off: usize = 0;
-
-                        while off < n {
-                            match NetworkHandleMessage::decode(&buf[off..n], addr) {
-                                Ok((res, used)) => match res {
-                                    Some(decoded) => {
-                                        let _ = network_handle.send(decoded);
-                                        off += used;
-                                    }
-                                    None => {
-                                        // It often occurs.. so it is not warning maybe..
-                                        // warn!( addr = ?addr, "Invalid Request from Peer");
-                                        off += used;
-                                    }
-                                },
-
-                                Err(e) => {
-                                    error!(
-                                        error = ?e,
-                                        "Failed to decode Network handle message from {:?}", addr
-                                    );
-                                    continue;
+                        pending.extend_from_slice(&buf[..n]);
+                        match decode_frames(&mut pending, addr) {
+                            Ok(messages) => {
+                                for message in messages {
+                                    network_handle.send(message);
                                 }
                             }
+                            Err(payload_len) => {
+                                error!(
+                                    payload_len,
+                                    "Peer {:?} announced a frame over the size limit.", addr
+                                );
+                                network_handle.send(NetworkHandleMessage::RemovePeer(pid));
+                                break;
+                            }
                         }
                     }
                     Err(e) => {
@@ -225,3 +256,47 @@ impl PeerList {
         (new_peer, pid as u64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(msg_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut raw = vec![msg_type, 0x00];
+        raw.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        raw.extend_from_slice(payload);
+        raw
+    }
+
+    #[test]
+    fn test_bad_frame_is_skipped_alone() {
+        let addr = "127.0.0.1:33333".parse().unwrap();
+        // A NewTransaction whose payload is not a transaction, between two good frames
+        let mut pending = frame(0x01, &[]);
+        pending.extend(frame(0x02, &[0xff; 12]));
+        pending.extend(frame(0x01, &[]));
+        // The first half of a frame still on its way
+        let tail = frame(0x01, &[]);
+        pending.extend_from_slice(&tail[..4]);
+
+        let messages = decode_frames(&mut pending, addr).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(
+            messages
+                .iter()
+                .all(|msg| matches!(msg, NetworkHandleMessage::PeerConnectionTest))
+        );
+        assert_eq!(pending, tail[..4]);
+    }
+
+    #[test]
+    fn test_oversized_frame_is_refused() {
+        let addr = "127.0.0.1:33333".parse().unwrap();
+        let mut pending = vec![0x02, 0x00];
+        pending.extend_from_slice(&(MAX_FRAME_PAYLOAD + 1).to_be_bytes());
+        assert_eq!(
+            decode_frames(&mut pending, addr).unwrap_err(),
+            MAX_FRAME_PAYLOAD + 1
+        );
+    }
+}
@@ -1,7 +1,14 @@
 use std::sync::Arc;
 
 use consensus::{ConsensusEngine, handle::ConsensusHandle, miner::Miner};
-use database::{DBImpl, immemorydb::InMemoryDB, mdbx::MDBX};
+use database::{
+    DBImpl,
+    cache::CachedDB,
+    immemorydb::InMemoryDB,
+    mdbx::MDBX,
+    prune::{PruneMode, Pruner},
+    segment::SegmentDB,
+};
 use network::{
     builder::{NetworkBuilder, NetworkConfig},
     handle::NetworkHandle,
@@ -16,17 +23,23 @@ use transaction_pool::Pool;
 
 use crate::{
     Node,
-    configs::{BlockConfig, ExecConfig, PoolConfig, RpcConfig},
+    configs::{BlockConfig, ChainConfig, ExecConfig, PoolConfig, PruneConfig, RpcConfig},
+    datadir::DataDir,
     error::NodeLaunchError,
 };
 
 pub struct LaunchContext {
+    pub chain_config: ChainConfig,
     pub block_config: BlockConfig,
     pub pool_config: PoolConfig,
     pub network_config: NetworkConfig,
     pub rpc_config: RpcConfig,
     pub exec_config: ExecConfig,
+    pub prune_config: PruneConfig,
+    pub datadir: DataDir,
     pub in_memory_db: bool,
+    // Append-only segment files instead of MDBX. Ignored with in_memory_db.
+    pub segment_db: bool,
 }
 
 impl LaunchContext {
@@ -36,34 +49,58 @@ impl LaunchContext {
         in_memory_db: bool,
     ) -> Self {
         Self {
+            chain_config: ChainConfig::default(),
             block_config,
             pool_config: PoolConfig::default(),
             network_config,
             rpc_config: RpcConfig::default(),
             exec_config: ExecConfig::default(),
+            prune_config: PruneConfig::default(),
+            datadir: DataDir::default(),
             in_memory_db: in_memory_db,
+            segment_db: false,
         }
     }
 }
 
 impl LaunchContext {
-    pub async fn launch(self) -> Result<Node<DBImpl>, NodeLaunchError> {
+    pub async fn launch(self) -> Result<Node<CachedDB<DBImpl>>, NodeLaunchError> {
         let Self {
+            chain_config,
             network_config,
             block_config,
+            prune_config,
+            datadir,
             in_memory_db,
+            segment_db,
             ..
         } = self;
         // Build Provider
 
-        let db = if !in_memory_db {
-            info!("DB Launched with MDBX.");
-            DBImpl::MDBX(MDBX::genesis_state())
-        } else {
+        let mut datadir_lock = None;
+        let db = if in_memory_db {
             info!("DB Launched with InMemoryDB.");
-            DBImpl::InMemoryDB(Arc::new(InMemoryDB::genesis_state()))
+            DBImpl::InMemoryDB(Arc::new(
+                InMemoryDB::genesis_state().with_prune_mode(prune_config.mode),
+            ))
+        } else if segment_db {
+            datadir_lock = Some(datadir.lock()?);
+            datadir.create_dirs()?;
+            info!(datadir = ?datadir.root(), "DB Launched with SegmentDB.");
+            let db = SegmentDB::genesis_state(datadir.segments_path(), chain_config.chain_id)?;
+            DBImpl::SegmentDB(db.with_prune_mode(prune_config.mode))
+        } else {
+            datadir_lock = Some(datadir.lock()?);
+            datadir.create_dirs()?;
+            info!(datadir = ?datadir.root(), "DB Launched with MDBX.");
+            let mdbx = MDBX::genesis_state(datadir.db_path(), chain_config.chain_id)?;
+            DBImpl::MDBX(mdbx.with_prune_mode(prune_config.mode))
         };
-        let provider = ProviderFactory::new(db);
+        let db = CachedDB::new(db);
+        if prune_config.mode != PruneMode::Archive {
+            Pruner::new(db.clone()).start();
+        }
+        let provider = ProviderFactory::new(db, chain_config.chain_id);
         // Build Pool
         let pool = Pool::new(provider.clone());
         // Build PayloadBuilder
@@ -108,6 +145,7 @@ impl LaunchContext {
             pool,
             consensus: Box::new(consensus_handle),
             network: Box::new(network_handle),
+            datadir_lock,
         })
     }
 }
@@ -0,0 +1,320 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use consensus::importer::BlockImporter;
+use primitives::{block::Block, encoding::ITEM_HEADER_LEN, handle::MAX_FRAME_PAYLOAD};
+use provider::{DatabaseTrait, ProviderFactory};
+use tracing::info;
+
+use crate::error::ChainFileError;
+
+// Blocks between two progress reports
+const PROGRESS_INTERVAL: u64 = 1000;
+// A block has to fit in one network frame to be propagated
+const MAX_BLOCK_LEN: u64 = MAX_FRAME_PAYLOAD;
+
+/// Reads blocks back to back in the canonical encoding
+#[derive(Debug)]
+pub struct BlockFileReader {
+    reader: BufReader<File>,
+    position: u64,
+    len: u64,
+}
+
+impl BlockFileReader {
+    pub fn open(path: &Path) -> Result<Self, ChainFileError> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            position: 0,
+            len,
+        })
+    }
+
+    /// Offset of the next block
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `None` at the end of the file. A block cut off by an interrupted export is `Truncated`,
+    /// a block announcing more than MAX_BLOCK_LEN bytes is `Oversized`.
+    pub fn next_block(&mut self) -> Result<Option<Block>, ChainFileError> {
+        let mut raw = vec![0u8; ITEM_HEADER_LEN];
+        match self.read_exact_or_eof(&mut raw)? {
+            0 => return Ok(None),
+            n if n < ITEM_HEADER_LEN => return Err(ChainFileError::Truncated(self.position)),
+            _ => {}
+        }
+        // The announced length is checked before anything is allocated for it
+        let payload_len = u32::from_be_bytes([raw[2], raw[3], raw[4], raw[5]]) as u64;
+        if ITEM_HEADER_LEN as u64 + payload_len > MAX_BLOCK_LEN {
+            return Err(ChainFileError::Oversized {
+                position: self.position,
+                len: payload_len,
+            });
+        }
+        if self.position + ITEM_HEADER_LEN as u64 + payload_len > self.len {
+            return Err(ChainFileError::Truncated(self.position));
+        }
+        let payload_len = payload_len as usize;
+        raw.resize(ITEM_HEADER_LEN + payload_len, 0);
+        if self.read_exact_or_eof(&mut raw[ITEM_HEADER_LEN..])? < payload_len {
+            return Err(ChainFileError::Truncated(self.position));
+        }
+        let (block, _) = Block::decode(&raw).map_err(|e| ChainFileError::Decode {
+            position: self.position,
+            error: e,
+        })?;
+        self.position += raw.len() as u64;
+        Ok(Some(block))
+    }
+
+    // Number of bytes read, short only at the end of the file
+    fn read_exact_or_eof(&mut self, buf: &mut [u8]) -> Result<usize, ChainFileError> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.reader.read(&mut buf[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// Writes blocks `from..=to` to `path`.
+/// An existing file from an interrupted export of the same range is continued after its last complete block.
+/// Blocks of an existing file past `to` are dropped.
+pub fn export_blocks<DB: DatabaseTrait>(
+    db: &DB,
+    from: u64,
+    to: u64,
+    path: &Path,
+) -> Result<u64, ChainFileError> {
+    let latest = db.latest_block_number();
+    if from > to || to > latest {
+        return Err(ChainFileError::InvalidRange { from, to, latest });
+    }
+
+    let (next, valid_len) = match path.exists() {
+        true => resume_point(db, from, to, path)?,
+        false => (from, 0),
+    };
+    if next > from {
+        info!(height = next, "Resuming export.");
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    // Drops a block cut off by the interruption, and any block past `to`
+    file.set_len(valid_len)?;
+    file.seek(SeekFrom::End(0))?;
+    let mut writer = BufWriter::new(file);
+
+    let mut exported = 0;
+    for height in next..=to {
+        let block = db
+            .get_block(height)
+            .map_err(|e| ChainFileError::Database(e.to_string()))?
+            .ok_or(ChainFileError::MissingBlock(height))?;
+        writer.write_all(&block.encode_ref())?;
+        exported += 1;
+        if exported % PROGRESS_INTERVAL == 0 {
+            writer.flush()?;
+            info!(height, to, "Exported blocks.");
+        }
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(exported)
+}
+
+// Height to continue from and the length of the complete blocks already written
+fn resume_point<DB: DatabaseTrait>(
+    db: &DB,
+    from: u64,
+    to: u64,
+    path: &Path,
+) -> Result<(u64, u64), ChainFileError> {
+    let mut reader = BlockFileReader::open(path)?;
+    let mut next = from;
+    while next <= to {
+        let block = match reader.next_block() {
+            Ok(Some(block)) => block,
+            Ok(None) | Err(ChainFileError::Truncated(_)) => break,
+            Err(e) => return Err(e),
+        };
+        let height = block.header.height;
+        if height != next {
+            return Err(ChainFileError::NotContiguous {
+                expected: next,
+                found: height,
+            });
+        }
+        let stored = db
+            .get_block(height)
+            .map_err(|e| ChainFileError::Database(e.to_string()))?
+            .ok_or(ChainFileError::MissingBlock(height))?;
+        if stored.header.calculate_hash() != block.header.calculate_hash() {
+            return Err(ChainFileError::ChainMismatch(height));
+        }
+        next += 1;
+    }
+    Ok((next, reader.position()))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: u64,
+    // Blocks of the file the database already had
+    pub skipped: u64,
+}
+
+/// Runs every block of `path` through the block importer.
+/// Blocks the database already has are compared and skipped, so an interrupted import is continued by running it again.
+pub fn import_blocks<DB: DatabaseTrait>(
+    provider: ProviderFactory<DB>,
+    path: &Path,
+) -> Result<ImportReport, ChainFileError> {
+    let mut reader = BlockFileReader::open(path)?;
+    let importer = BlockImporter::new(provider.clone());
+    let mut report = ImportReport::default();
+
+    while let Some(block) = reader.next_block()? {
+        let height = block.header.height;
+        let latest = provider.block_number();
+        if height <= latest {
+            let stored = provider
+                .db()
+                .get_block(height)
+                .map_err(|e| ChainFileError::Database(e.to_string()))?
+                .ok_or(ChainFileError::MissingBlock(height))?;
+            if stored.header.calculate_hash() != block.header.calculate_hash() {
+                return Err(ChainFileError::ChainMismatch(height));
+            }
+            report.skipped += 1;
+            continue;
+        }
+
+        importer
+            .import_new_block(block)
+            .map_err(|error| ChainFileError::Import { height, error })?;
+        report.imported += 1;
+        if height % PROGRESS_INTERVAL == 0 {
+            let percent = reader.position() * 100 / reader.len().max(1);
+            info!(height, percent, "Imported blocks.");
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use database::immemorydb::InMemoryDB;
+    use primitives::types::Address;
+    use provider::test_utils::import_empty_block;
+
+    use super::*;
+
+    #[test]
+    fn test_export_and_resumed_import() {
+        let dir = std::env::temp_dir().join(format!("pint-chain-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chain.bin");
+        let _ = std::fs::remove_file(&path);
+
+        let source = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 0);
+        for _ in 0..4 {
+            import_empty_block(&source, Address::random());
+        }
+        assert_eq!(export_blocks(source.db(), 1, 2, &path).unwrap(), 2);
+        // Interrupted in the middle of block 3
+        let complete = std::fs::metadata(&path).unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&source.db().get_block(3).unwrap().unwrap().encode_ref()[..10])
+            .unwrap();
+        drop(file);
+
+        let target = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 0);
+        assert!(matches!(
+            import_blocks(target.clone(), &path),
+            Err(ChainFileError::Truncated(position)) if position == complete
+        ));
+        assert_eq!(target.block_number(), 2);
+
+        // Both sides continue where they stopped
+        assert_eq!(export_blocks(source.db(), 1, 4, &path).unwrap(), 2);
+        let report = import_blocks(target.clone(), &path).unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                imported: 2,
+                skipped: 2
+            }
+        );
+        assert_eq!(
+            target.db().get_latest_block_header().calculate_hash(),
+            source.db().get_latest_block_header().calculate_hash()
+        );
+
+        // A shorter range over a longer file keeps only its own blocks
+        assert_eq!(export_blocks(source.db(), 1, 2, &path).unwrap(), 0);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), complete);
+        let shorter = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 0);
+        assert_eq!(import_blocks(shorter.clone(), &path).unwrap().imported, 2);
+        assert_eq!(shorter.block_number(), 2);
+
+        // A file of another chain is refused
+        let other = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 0);
+        import_empty_block(&other, Address::random());
+        assert!(matches!(
+            import_blocks(other, &path),
+            Err(ChainFileError::ChainMismatch(1))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_block_length_is_checked_before_reading() {
+        let path = std::env::temp_dir().join(format!("pint-chain-file-len-{}", std::process::id()));
+        let header = |len: u32| {
+            let mut raw = vec![0u8; 2];
+            raw.extend_from_slice(&len.to_be_bytes());
+            raw.extend_from_slice(&[0u8; 16]);
+            raw
+        };
+
+        std::fs::write(&path, header(u32::MAX)).unwrap();
+        assert!(matches!(
+            BlockFileReader::open(&path).unwrap().next_block(),
+            Err(ChainFileError::Oversized { position: 0, len }) if len == u32::MAX as u64
+        ));
+
+        // Past the end of the file
+        std::fs::write(&path, header(1024)).unwrap();
+        assert!(matches!(
+            BlockFileReader::open(&path).unwrap().next_block(),
+            Err(ChainFileError::Truncated(0))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
-use primitives::types::Address;
+use database::prune::PruneMode;
+use primitives::types::{Address, ChainId};
 
 #[derive(Default)]
 pub struct PoolConfig {}
@@ -16,3 +17,23 @@ impl BlockConfig {
 }
 #[derive(Default)]
 pub struct ExecConfig {}
+#[derive(Default)]
+pub struct ChainConfig {
+    pub chain_id: ChainId,
+}
+
+impl ChainConfig {
+    pub fn new(chain_id: ChainId) -> Self {
+        Self { chain_id }
+    }
+}
+#[derive(Default)]
+pub struct PruneConfig {
+    pub mode: PruneMode,
+}
+
+impl PruneConfig {
+    pub fn new(mode: PruneMode) -> Self {
+        Self { mode }
+    }
+}
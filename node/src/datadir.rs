@@ -0,0 +1,120 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+};
+
+use crate::error::NodeLaunchError;
+
+const LOCK_FILE: &str = "LOCK";
+
+/// Root directory of one node
+///
+/// ```text
+/// <root>/LOCK      held while a node runs on this directory
+/// <root>/data      MDBX database
+/// <root>/segments  SegmentDB database, used instead of data with --segment-db
+/// <root>/keystore  encrypted keys
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl Default for DataDir {
+    // <data_dir>/pint, the layout used before the datadir existed
+    fn default() -> Self {
+        let mut root = dirs::data_dir().expect("no data dir");
+        root.push("pint");
+        Self { root }
+    }
+}
+
+impl DataDir {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn db_path(&self) -> PathBuf {
+        self.root.join("data")
+    }
+
+    pub fn segments_path(&self) -> PathBuf {
+        self.root.join("segments")
+    }
+
+    pub fn keystore_path(&self) -> PathBuf {
+        self.root.join("keystore")
+    }
+
+    pub fn create_dirs(&self) -> Result<(), NodeLaunchError> {
+        for dir in [self.db_path(), self.keystore_path()] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the directory for this process.
+    /// Fails right away if another process holds it. The lock is released on drop, or when the process dies.
+    pub fn lock(&self) -> Result<DataDirLock, NodeLaunchError> {
+        std::fs::create_dir_all(&self.root)?;
+        let path = self.root.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(NodeLaunchError::DataDirLocked(self.root.clone()));
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        // Only informative. The OS lock is what excludes other processes.
+        file.set_len(0)?;
+        std::io::Write::write_all(&mut &file, std::process::id().to_string().as_bytes())?;
+        Ok(DataDirLock { _file: file, path })
+    }
+}
+
+#[derive(Debug)]
+pub struct DataDirLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl DataDirLock {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datadir_lock_is_exclusive() {
+        let root = std::env::temp_dir().join(format!("pint-datadir-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let datadir = DataDir::new(&root);
+
+        let lock = datadir.lock().unwrap();
+        assert!(matches!(
+            datadir.lock(),
+            Err(NodeLaunchError::DataDirLocked(_))
+        ));
+        drop(lock);
+        let _lock = datadir.lock().unwrap();
+
+        datadir.create_dirs().unwrap();
+        assert!(datadir.db_path().is_dir() && datadir.keystore_path().is_dir());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,10 +1,97 @@
+use std::path::PathBuf;
+
+use database::error::DatabaseError;
 use network::error::NetworkStartError;
+use primitives::error::{BlockImportError, DecodeError};
+use provider::error::ProviderError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum NodeLaunchError {
     #[error("Network Start Error")]
     NetworkStartError(NetworkStartError),
+    #[error("Data directory {} is used by another process", .0.display())]
+    DataDirLocked(PathBuf),
+    #[error("Data directory Error")]
+    DataDirError(std::io::Error),
+    #[error("Database Error: {0}")]
+    DatabaseError(DatabaseError),
+}
+
+#[derive(Debug, Error)]
+pub enum ChainFileError {
+    #[error("Chain file Error: {0}")]
+    Io(std::io::Error),
+    #[error("Chain file ends with an incomplete block at byte {0}")]
+    Truncated(u64),
+    #[error("Invalid block at byte {position}: {error}")]
+    Decode { position: u64, error: DecodeError },
+    #[error("Block at byte {position} announces {len} bytes, more than a block can hold")]
+    Oversized { position: u64, len: u64 },
+    #[error("Expected block {expected} in the chain file, found {found}")]
+    NotContiguous { expected: u64, found: u64 },
+    #[error("Block {0} in the chain file differs from the stored block")]
+    ChainMismatch(u64),
+    #[error("Invalid block range {from}..={to}, latest block is {latest}")]
+    InvalidRange { from: u64, to: u64, latest: u64 },
+    #[error("Block {0} is missing from the database")]
+    MissingBlock(u64),
+    #[error("Database Error: {0}")]
+    Database(String),
+    #[error("Failed to import block {height}: {error}")]
+    Import { height: u64, error: BlockImportError },
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Snapshot file Error: {0}")]
+    Io(std::io::Error),
+    #[error("Invalid snapshot file: {0}")]
+    Decode(DecodeError),
+    #[error("Block {height} is above the latest block {latest}")]
+    InvalidHeight { height: u64, latest: u64 },
+    #[error("The genesis block has no snapshot, a node starts from it without one")]
+    Genesis,
+    #[error("State at block {0} is not stored")]
+    MissingState(u64),
+    #[error("Receipts of block {0} are not stored")]
+    MissingReceipts(u64),
+    #[error("Database already holds blocks up to {0}")]
+    NotEmpty(u64),
+    #[error("Database Error: {0}")]
+    Database(String),
+    #[error("Failed to import the snapshot: {0}")]
+    Import(ProviderError),
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<DecodeError> for SnapshotError {
+    fn from(value: DecodeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl From<std::io::Error> for ChainFileError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<DatabaseError> for NodeLaunchError {
+    fn from(value: DatabaseError) -> Self {
+        Self::DatabaseError(value)
+    }
+}
+
+impl From<std::io::Error> for NodeLaunchError {
+    fn from(value: std::io::Error) -> Self {
+        Self::DataDirError(value)
+    }
 }
 
 impl From<NetworkStartError> for NodeLaunchError {
@@ -1,4 +1,4 @@
-use crate::rpc::rpc_handle;
+use crate::{datadir::DataDirLock, rpc::rpc_handle};
 use axum::{Router, routing::post};
 use network::builder::NetworkConfig;
 use primitives::{
@@ -12,9 +12,12 @@ use tracing::{error, info};
 use transaction_pool::{Pool, identifier::TransactionOrigin};
 
 pub mod builder;
+pub mod chain_file;
 pub mod configs;
+pub mod datadir;
 pub mod error;
 pub mod rpc;
+pub mod snapshot;
 
 #[derive(Debug)]
 pub struct Node<DB: DatabaseTrait> {
@@ -22,6 +25,8 @@ pub struct Node<DB: DatabaseTrait> {
     pub pool: Pool<DB>,
     pub consensus: Box<dyn Handle<Msg = ConsensusHandleMessage>>,
     pub network: Box<dyn Handle<Msg = NetworkHandleMessage>>,
+    // Held for as long as the node runs on its data directory
+    pub datadir_lock: Option<DataDirLock>,
 }
 
 impl<DB: DatabaseTrait> Node<DB> {
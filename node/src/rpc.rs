@@ -3,10 +3,12 @@ use std::sync::Arc;
 use axum::{Json, extract::State};
 use primitives::{
     handle::{ConsensusHandleMessage, NetworkHandleMessage},
-    transaction::SignedTransaction,
+    merkle::calculate_merkle_proof,
+    transaction::{SignedTransaction, Tx},
     types::{Address, B256, TxHash},
 };
-use provider::DatabaseTrait;
+use database::history::TxPosition;
+use provider::{DatabaseTrait, error::ProviderError};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tracing::error;
@@ -14,6 +16,10 @@ use transaction_pool::{error::PoolErrorKind, identifier::TransactionOrigin};
 
 use crate::Node;
 
+// Page size of account_transactions when none is requested, and the largest one served
+const DEFAULT_HISTORY_PAGE: usize = 100;
+const MAX_HISTORY_PAGE: usize = 1000;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct RpcRequest {
     pub jsonrpc: String,
@@ -56,6 +62,16 @@ pub async fn rpc_handle<DB: DatabaseTrait>(
                 id: req.id,
             })
         }
+        "chain_id" => {
+            let result = json!(node.provider.chain_id());
+            success = true;
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                success,
+                result,
+                id: req.id,
+            })
+        }
         "local_transaction" => {
             let result;
             if let Some(raw) = req.params[0].as_str() {
@@ -180,7 +196,26 @@ pub async fn rpc_handle<DB: DatabaseTrait>(
                     }
                 };
 
-                match node.provider.db().basic(&address) {
+                // An optional block number reads the account from that block's state
+                let account = match req.params.get(1).and_then(|raw| raw.as_str()) {
+                    Some(raw) => match raw.parse::<u64>() {
+                        Ok(bno) => node.provider.state_by_block_number(bno).account(address),
+                        Err(_e) => {
+                            return Json(RpcResponse {
+                                jsonrpc: "2.0".to_string(),
+                                success,
+                                result: json!("U64 parse Failed"),
+                                id: req.id,
+                            });
+                        }
+                    },
+                    None => node
+                        .provider
+                        .db()
+                        .basic(&address)
+                        .map_err(ProviderError::DatabaseError),
+                };
+                match account {
                     Ok(account) => {
                         result = match account {
                             Some(account) => {
@@ -194,6 +229,9 @@ pub async fn rpc_handle<DB: DatabaseTrait>(
                             None => json!("No account info"),
                         };
                     }
+                    Err(ProviderError::StatePruned(bno)) => {
+                        result = json!(format!("State at block {} has been pruned", bno));
+                    }
                     Err(_e) => {}
                 };
             }
@@ -204,6 +242,182 @@ pub async fn rpc_handle<DB: DatabaseTrait>(
                 id: req.id,
             })
         }
+        // Params: address, then optionally the block number and index to start from and the page size.
+        // `next` is where the following page starts, null on the last page.
+        "account_transactions" => {
+            let mut result = json!("Failed");
+            if let Some(raw) = req.params[0].as_str() {
+                let address = match Address::from_hex(raw.to_string()) {
+                    Ok(addr) => addr,
+                    Err(_e) => {
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Wrong address"),
+                            id: req.id,
+                        });
+                    }
+                };
+                let param = |i: usize| req.params.get(i).and_then(|raw| raw.as_str());
+                let from_block = param(1).map(str::parse::<u64>).transpose();
+                let from_index = param(2).map(str::parse::<u32>).transpose();
+                let limit = param(3).map(str::parse::<usize>).transpose();
+                let (from, limit) = match (from_block, from_index, limit) {
+                    (Ok(block_no), Ok(index), Ok(limit)) => (
+                        TxPosition::new(block_no.unwrap_or(0), index.unwrap_or(0)),
+                        limit.unwrap_or(DEFAULT_HISTORY_PAGE).clamp(1, MAX_HISTORY_PAGE),
+                    ),
+                    _ => {
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Number parse Failed"),
+                            id: req.id,
+                        });
+                    }
+                };
+
+                // One more than the page tells where the next page starts
+                match node
+                    .provider
+                    .db()
+                    .account_transactions(&address, from, limit + 1)
+                {
+                    Ok(mut entries) => {
+                        let next = match entries.len() > limit {
+                            true => entries.pop().map(|entry| {
+                                json!({
+                                    "block_number": entry.position.block_no,
+                                    "index": entry.position.index,
+                                })
+                            }),
+                            false => None,
+                        };
+                        let transactions: Vec<Value> = entries
+                            .iter()
+                            .map(|entry| {
+                                json!({
+                                    "tx_hash": hex::encode(entry.hash.hash()),
+                                    "block_number": entry.position.block_no,
+                                    "index": entry.position.index,
+                                })
+                            })
+                            .collect();
+                        success = true;
+                        result = json!({
+                            "transactions": transactions,
+                            "next": next,
+                        });
+                    }
+                    Err(_e) => {
+                        result = json!("Database Error. Try again");
+                    }
+                }
+            }
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                success,
+                result,
+                id: req.id,
+            })
+        }
+        "account_proof" => {
+            let mut result = json!("Failed");
+            if let Some(raw) = req.params[0].as_str() {
+                let address = match Address::from_hex(raw.to_string()) {
+                    Ok(addr) => addr,
+                    Err(e) => {
+                        error!(error = ?e, "Failed to get account proof.");
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Wrong address"),
+                            id: req.id,
+                        });
+                    }
+                };
+
+                let state = node.provider.latest();
+                match (state.account_proof(address), node.provider.db().get_header(state.block_number())) {
+                    (Ok(proof), Ok(Some(header))) => {
+                        success = true;
+                        let account = proof.account.map(|account| {
+                            json!({
+                                "nonce": format!("{}", account.nonce()),
+                                "balance": format!("{}", account.balance()),
+                            })
+                        });
+                        result = json!({
+                            "block_number": header.height,
+                            "state_root": hex::encode(header.state_root),
+                            "account": account,
+                            "account_root": hex::encode(proof.account_root),
+                            "world_hash": hex::encode(proof.world_hash),
+                            "siblings": proof.siblings.iter().map(hex::encode).collect::<Vec<_>>(),
+                        });
+                    }
+                    (Err(e), _) => {
+                        error!(error = ?e, "Failed to get account proof.");
+                        result = json!("Database Error. Try again");
+                    }
+                    _ => {
+                        result = json!("Database Error. Try again");
+                    }
+                }
+            }
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                success,
+                result,
+                id: req.id,
+            })
+        }
+        "storage" => {
+            let mut result = json!("Failed");
+            if let (Some(raw_addr), Some(raw_key)) = (
+                req.params.first().and_then(|p| p.as_str()),
+                req.params.get(1).and_then(|p| p.as_str()),
+            ) {
+                let address = match Address::from_hex(raw_addr.to_string()) {
+                    Ok(addr) => addr,
+                    Err(e) => {
+                        error!(error = ?e, "Failed to get storage.");
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Wrong address"),
+                            id: req.id,
+                        });
+                    }
+                };
+                let key = match hex::decode(raw_key) {
+                    Ok(key) if key.len() == 32 => B256::from_slice(&key),
+                    _ => {
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Wrong key"),
+                            id: req.id,
+                        });
+                    }
+                };
+
+                match node.provider.latest().storage(address, key) {
+                    Ok(Some(value)) => {
+                        success = true;
+                        result = json!({ "value": hex::encode(value) });
+                    }
+                    Ok(None) => result = json!("No storage value"),
+                    Err(_e) => result = json!("Database Error. Try again"),
+                }
+            }
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                success,
+                result,
+                id: req.id,
+            })
+        }
         "blockchain_height" => {
             let result = json!(node.provider.block_number());
             Json(RpcResponse {
@@ -231,9 +445,9 @@ pub async fn rpc_handle<DB: DatabaseTrait>(
                 let tx_hash: TxHash = TxHash::from(B256::from_slice(&data));
                 match node.provider.db().get_transaction_by_hash(tx_hash) {
                     Ok(Some((tx, bno))) => {
-                        let data = tx.encode();
                         result = json!({
-                            "tx": hex::encode(data),
+                            "tx": hex::encode(tx.encode()),
+                            "data": hex::encode(tx.data()),
                             "block_number": bno
                         });
                     }
@@ -252,6 +466,100 @@ pub async fn rpc_handle<DB: DatabaseTrait>(
                 id: req.id,
             })
         }
+        "transaction_proof" => {
+            let mut result: Value = json!("There is no transaction you want to find.");
+            if let Some(raw) = req.params[0].as_str() {
+                let data = match hex::decode(raw) {
+                    Ok(data) if data.len() == 32 => data,
+                    _ => {
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Transaction Hex Decode Error"),
+                            id: req.id,
+                        });
+                    }
+                };
+
+                let tx_hash: TxHash = TxHash::from(B256::from_slice(&data));
+                let block = match node.provider.db().get_transaction_by_hash(tx_hash) {
+                    Ok(Some((_, bno))) => node.provider.db().get_block(bno),
+                    Ok(None) => Ok(None),
+                    Err(e) => Err(e),
+                };
+                match block {
+                    Ok(Some(block)) => {
+                        let leaves: Vec<B256> = block.body.iter().map(|tx| tx.hash.hash()).collect();
+                        let proof = leaves
+                            .iter()
+                            .position(|leaf| *leaf == tx_hash.hash())
+                            .and_then(|index| calculate_merkle_proof(leaves, index));
+                        if let Some(proof) = proof {
+                            success = true;
+                            result = json!({
+                                "block_number": block.header.height,
+                                "transaction_root": hex::encode(block.header.transaction_root),
+                                "index": proof.index(),
+                                "siblings": proof.siblings.iter().map(hex::encode).collect::<Vec<_>>(),
+                                "path": proof.path,
+                            });
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(_e) => {
+                        result = json!("Database Error. Try again");
+                    }
+                }
+            }
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                success,
+                result,
+                id: req.id,
+            })
+        }
+        "transaction_receipt" => {
+            let mut result: Value = json!("There is no receipt you want to find.");
+            if let Some(raw) = req.params[0].as_str() {
+                let data = match hex::decode(raw) {
+                    Ok(data) if data.len() == 32 => data,
+                    _ => {
+                        return Json(RpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            success,
+                            result: json!("Transaction Hex Decode Error"),
+                            id: req.id,
+                        });
+                    }
+                };
+
+                let tx_hash: TxHash = TxHash::from(B256::from_slice(&data));
+                match node.provider.db().get_receipt(tx_hash) {
+                    Ok(Some((receipt, bno))) => {
+                        success = true;
+                        result = json!({
+                            "tx_hash": hex::encode(receipt.tx_hash.hash()),
+                            "block_number": bno,
+                            "index": receipt.index,
+                            "success": receipt.success,
+                            "fee": receipt.fee.to_string(),
+                            "error": receipt.error.map(|error| format!("{:?}", error)),
+                            "error_code": receipt.error.map(|error| error.code()),
+                        });
+                    }
+                    Ok(None) => {}
+                    Err(_e) => {
+                        result = json!("Database Error. Try again");
+                    }
+                }
+            }
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                success,
+                result,
+                id: req.id,
+            })
+        }
         "block_by_number" => {
             let mut result: Value = json!("Initial Error");
             if let Some(raw) = req.params[0].as_str() {
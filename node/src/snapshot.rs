@@ -0,0 +1,331 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use primitives::{
+    block::{Block, Header},
+    encoding::{Decoder, ItemType},
+    error::DecodeError,
+    receipt::TransactionReceipt,
+    types::{Account, Address, BlockHash},
+    world::World,
+};
+use provider::{DatabaseTrait, ProviderFactory};
+use tracing::info;
+
+use crate::error::SnapshotError;
+
+// Snapshot file layout:
+//   [block item]
+//   [receipt count: u64 BE]
+//   [receipt item] per transaction of the block, in body order
+//   [parent header item]
+//   [account count: u64 BE]
+//   [address: 20][nonce: u64 BE][balance: u256 BE] per account, sorted by address
+//   [world item]
+// Items use the canonical encoding, so the block carries the header whose state root the state is checked against.
+// The parent header lets the node work out the difficulty of the block after the snapshot.
+// The receipts are checked against the block's receipts root, so the block's transactions can be looked up after import.
+
+/// Full state of one block together with the block
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub block: Block,
+    pub receipts: Vec<TransactionReceipt>,
+    pub parent: Header,
+    pub accounts: HashMap<Address, Account>,
+    pub world: World,
+}
+
+impl Snapshot {
+    pub fn height(&self) -> u64 {
+        self.block.header.height
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut raw = self.block.encode_ref();
+        raw.extend_from_slice(&(self.receipts.len() as u64).to_be_bytes());
+        for receipt in self.receipts.iter() {
+            raw.extend_from_slice(&receipt.encode());
+        }
+        raw.extend_from_slice(&self.parent.encode());
+        raw.extend_from_slice(&(self.accounts.len() as u64).to_be_bytes());
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by_key(|(address, _)| **address);
+        for (address, account) in accounts {
+            raw.extend_from_slice(address.get_addr());
+            raw.extend_from_slice(&account.nonce.to_be_bytes());
+            raw.extend_from_slice(&account.balance.to_be_bytes::<32>());
+        }
+        raw.extend_from_slice(&self.world.encode());
+        raw
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let (block, _) = Block::decode(read_item(&mut decoder, raw, ItemType::Block)?)?;
+        let mut receipts = Vec::new();
+        for _ in 0..decoder.read_u64()? {
+            receipts.push(TransactionReceipt::decode(read_item(
+                &mut decoder,
+                raw,
+                ItemType::Receipt,
+            )?)?);
+        }
+        let parent = Header::decode(read_item(&mut decoder, raw, ItemType::Header)?)?;
+        let mut accounts = HashMap::new();
+        for _ in 0..decoder.read_u64()? {
+            let address = decoder.read_address()?;
+            let nonce = decoder.read_u64()?;
+            let balance = decoder.read_u256()?;
+            accounts.insert(address, Account { nonce, balance });
+        }
+        let world = World::decode(read_item(&mut decoder, raw, ItemType::World)?)?;
+        decoder.finish()?;
+        Ok(Self {
+            block,
+            receipts,
+            parent,
+            accounts,
+            world,
+        })
+    }
+}
+
+// The next framed item, header included
+fn read_item<'a>(
+    decoder: &mut Decoder<'a>,
+    raw: &'a [u8],
+    item_type: ItemType,
+) -> Result<&'a [u8], DecodeError> {
+    let start = decoder.position();
+    decoder.read_item(item_type)?;
+    Ok(&raw[start..decoder.position()])
+}
+
+/// Writes the state at `height`, its block and the block's receipts to `path`.
+/// The file only appears once it is complete, so an interrupted export leaves nothing to import.
+pub fn export_snapshot<DB: DatabaseTrait>(
+    db: &DB,
+    height: u64,
+    path: &Path,
+) -> Result<Snapshot, SnapshotError> {
+    let latest = db.latest_block_number();
+    if height > latest {
+        return Err(SnapshotError::InvalidHeight { height, latest });
+    }
+    if height == 0 {
+        return Err(SnapshotError::Genesis);
+    }
+    let block = db
+        .get_block(height)
+        .map_err(|e| SnapshotError::Database(e.to_string()))?
+        .ok_or(SnapshotError::MissingState(height))?;
+    let mut receipts = Vec::with_capacity(block.body.len());
+    for tx in block.body.iter() {
+        let (receipt, _) = db
+            .get_receipt(tx.hash)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?
+            .ok_or(SnapshotError::MissingReceipts(height))?;
+        receipts.push(receipt);
+    }
+    let parent = db
+        .get_header(height - 1)
+        .map_err(|e| SnapshotError::Database(e.to_string()))?
+        .ok_or(SnapshotError::MissingState(height - 1))?;
+    let (accounts, world) = match db.get_state(height) {
+        Ok((Some(accounts), Some(world))) => (accounts, world),
+        Ok(_) => return Err(SnapshotError::MissingState(height)),
+        Err(e) => return Err(SnapshotError::Database(e.to_string())),
+    };
+    let snapshot = Snapshot {
+        block,
+        receipts,
+        parent,
+        accounts,
+        world,
+    };
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    writer.write_all(&snapshot.encode())?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    std::fs::rename(&partial, path)?;
+    info!(
+        height,
+        accounts = snapshot.accounts.len(),
+        "Exported snapshot."
+    );
+    Ok(snapshot)
+}
+
+/// Starts a database without blocks from the snapshot at `path`. The file is not trusted: its block has to hash to
+/// `trusted`, given by the operator, and its state has to match the block's state root.
+/// Returns the snapshot height, blocks after it are synced through the network.
+pub fn import_snapshot<DB: DatabaseTrait>(
+    provider: &ProviderFactory<DB>,
+    path: &Path,
+    trusted: BlockHash,
+) -> Result<u64, SnapshotError> {
+    let latest = provider.block_number();
+    if latest != 0 {
+        return Err(SnapshotError::NotEmpty(latest));
+    }
+    let snapshot = Snapshot::decode(&std::fs::read(path)?)?;
+    let height = snapshot.height();
+    provider
+        .import_snapshot(
+            snapshot.accounts,
+            snapshot.world,
+            snapshot.block,
+            snapshot.receipts,
+            snapshot.parent,
+            trusted,
+        )
+        .map_err(SnapshotError::Import)?;
+    Ok(height)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use consensus::importer::BlockImporter;
+    use database::{immemorydb::InMemoryDB, mdbx::MDBX};
+    use primitives::{
+        test_utils::sign,
+        transaction::{Transaction, TxTransfer},
+        types::U256,
+    };
+    use provider::{
+        error::ProviderError,
+        test_utils::{import_empty_block, import_transfer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_snapshot_export_and_import() {
+        let dir = std::env::temp_dir().join(format!("pint-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.snapshot");
+
+        let source = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 0);
+        for _ in 0..2 {
+            import_empty_block(&source, Address::random());
+        }
+        let difficulty = source.get_next_difficulty();
+        import_empty_block(&source, Address::random());
+        let exported = export_snapshot(source.db(), 2, &path).unwrap();
+        assert_eq!(exported.accounts.len(), 4);
+        let trusted = exported.block.header.calculate_hash();
+
+        let target = ProviderFactory::new(Arc::new(InMemoryDB::new()), 0);
+        assert_eq!(import_snapshot(&target, &path, trusted).unwrap(), 2);
+        assert_eq!(
+            target.db().get_state(2).unwrap().0,
+            source.db().get_state(2).unwrap().0
+        );
+        assert!(target.db().get_state(1).is_err());
+        // The parent header is kept, so the next block gets the difficulty peers expect
+        assert_eq!(target.get_next_difficulty(), difficulty);
+
+        // The next block is imported on top of the snapshot
+        let next = source.db().get_block(3).unwrap().unwrap();
+        BlockImporter::new(target.clone())
+            .import_new_block(next)
+            .unwrap();
+        assert_eq!(
+            target.db().get_latest_block_header().calculate_hash(),
+            source.db().get_latest_block_header().calculate_hash()
+        );
+        let report = target.check_integrity().unwrap();
+        assert_eq!((report.blocks, report.executed), (3, 1));
+
+        // Only into a database without blocks
+        assert!(matches!(
+            import_snapshot(&target, &path, trusted),
+            Err(SnapshotError::NotEmpty(3))
+        ));
+
+        // A block other than the trusted one is refused
+        let fresh = ProviderFactory::new(Arc::new(InMemoryDB::new()), 0);
+        let other = source.db().get_latest_block_header().calculate_hash();
+        assert!(matches!(
+            import_snapshot(&fresh, &path, other),
+            Err(SnapshotError::Import(ProviderError::UntrustedSnapshot(2)))
+        ));
+
+        // A state that does not match the header is refused
+        let mut forged = exported.clone();
+        let address = *forged.accounts.keys().next().unwrap();
+        forged.accounts.insert(address, Account::new(0, U256::MAX));
+        std::fs::write(&path, forged.encode()).unwrap();
+        assert!(matches!(
+            import_snapshot(&fresh, &path, trusted),
+            Err(SnapshotError::Import(ProviderError::InvalidSnapshot(2)))
+        ));
+        assert_eq!(fresh.block_number(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_import_into_mdbx() {
+        let dir = std::env::temp_dir().join(format!("pint-snapshot-mdbx-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.snapshot");
+
+        let mut funded = InMemoryDB::new();
+        let transfer = TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 0,
+            value: U256::ZERO,
+            data: Vec::new(),
+        };
+        let pint = sign("pint", Transaction::Transfer(transfer)).signer();
+        funded.add_account(pint, Account::new(0, U256::from(1000))).unwrap();
+        let source = ProviderFactory::new(Arc::new(funded), 0);
+        for height in 1..=3 {
+            import_transfer(&source, height, Address::random(), Address::random());
+        }
+        let exported = export_snapshot(source.db(), 2, &path).unwrap();
+        assert_eq!(exported.receipts.len(), 1);
+        let trusted = exported.block.header.calculate_hash();
+
+        let target = ProviderFactory::new(MDBX::new(dir.join("db"), 0).unwrap(), 0);
+        assert_eq!(import_snapshot(&target, &path, trusted).unwrap(), 2);
+        // The state and its trie are written together
+        assert_eq!(
+            target.db().get_trie_root(2).unwrap(),
+            source.db().get_trie_root(2).unwrap()
+        );
+        assert_eq!(
+            target.db().get_header(1).unwrap().map(|header| header.calculate_hash()),
+            Some(exported.parent.calculate_hash())
+        );
+        // The block's transactions are found with their receipts
+        let hash = exported.block.body[0].hash;
+        assert_eq!(
+            target.db().get_receipt(hash).unwrap(),
+            Some((exported.receipts[0].clone(), 2))
+        );
+
+        let next = source.db().get_block(3).unwrap().unwrap();
+        BlockImporter::new(target.clone())
+            .import_new_block(next)
+            .unwrap();
+        let report = target.check_integrity().unwrap();
+        assert_eq!((report.blocks, report.executed), (3, 1));
+        drop(target);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -12,6 +12,7 @@ impl BuildArguments {
         let mut res = Self::default();
         res.address = address;
         res.attributes.max_transactions = 20;
+        res.attributes.max_data_bytes = 16 * 1024;
         res.attributes.next_difficulty = 10;
         res
     }
@@ -30,4 +31,6 @@ pub struct PayloadBuilderAttributes {
     pub parent_hash: BlockHash,
     pub next_difficulty: u32,
     pub max_transactions: u32,
+    // Sum of data bytes of all transactions in one block, memos included
+    pub max_data_bytes: usize,
 }
\ No newline at end of file
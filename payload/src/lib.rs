@@ -2,9 +2,11 @@ use primitives::{
     block::{Payload, PayloadHeader},
     handle::{PayloadBuilderHandleMessage, PayloadBuilderResultMessage},
     merkle::calculate_merkle_root,
+    receipt::calculate_receipts_root,
+    transaction::Tx,
     types::{Address, U256},
 };
-use provider::{DatabaseTrait, ProviderFactory, executor::Executor};
+use provider::{DatabaseTrait, ProviderFactory, error::ProviderError, executor::Executor};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tracing::{debug, error, info};
@@ -127,38 +129,56 @@ fn default_paylod<DB: DatabaseTrait>(
     let state_provider = provider.latest();
     let exec_state = state_provider.executable_state()?;
     let max_transactions = attributes.max_transactions;
+    let max_data_bytes = attributes.max_data_bytes;
 
-    let mut executor = Executor {
-        state: exec_state,
-        receipts: Vec::new(),
-    };
+    let mut executor = Executor::new(exec_state, provider.chain_id());
 
     let mut best_txs = pool.best_transactions();
     let mut body = Vec::new();
+    let mut receipts = Vec::new();
     let mut total_fee = U256::ZERO;
 
     let mut count: u32 = 0;
+    let mut data_bytes: usize = 0;
 
     while let Some(pool_tx) = best_txs.next() {
-        match executor.execute_transaction(&pool_tx.transaction) {
-            Ok(receipt) => {
-                if receipt.success {
-                    total_fee += U256::from(receipt.fee);
-                    body.push(pool_tx.tx().tx().clone());
-                }
-                if count >= max_transactions {
-                    break;
-                }
-                count += 1;
-            }
-            Err(_e) => {}
+        if count >= max_transactions {
+            break;
+        }
+        // Memos count as much as Data payloads
+        let tx_data_bytes = pool_tx.tx().tx().transaction().data().len();
+        if data_bytes + tx_data_bytes > max_data_bytes {
+            // Its later nonces can not follow it into this block
+            best_txs.mark_invalid(&pool_tx);
+            continue;
+        }
+
+        let Ok(receipt) = executor.execute_transaction(&pool_tx.transaction);
+        if !receipt.success {
+            debug!(
+                tx_hash = ?receipt.tx_hash,
+                error = ?receipt.error,
+                "Left a failing transaction out of the payload."
+            );
+            best_txs.mark_invalid(&pool_tx);
+            continue;
         }
+        total_fee += U256::from(receipt.fee);
+        receipts.push(receipt.to_transaction_receipt(body.len() as u32));
+        body.push(pool_tx.tx().tx().clone());
+        data_bytes += tx_data_bytes;
+        count += 1;
     }
 
     let next_height = parent_header.height + 1;
     let tx_hashes = body.iter().map(|tx| tx.hash.hash()).collect();
     let transaction_root = calculate_merkle_root(tx_hashes);
-    let state_root = executor.calculate_state_root();
+    let receipts_root = calculate_receipts_root(&receipts);
+    // The header commits to the state after the proposer got the fees
+    executor.reward_proposer(address, total_fee);
+    let state_root = executor
+        .calculate_state_root()
+        .map_err(ProviderError::TrieError)?;
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Time shuld go forward")
@@ -168,6 +188,7 @@ fn default_paylod<DB: DatabaseTrait>(
         previous_hash: parent_header.calculate_hash(),
         transaction_root,
         state_root,
+        receipts_root,
         proposer: address,
         difficulty: attributes.next_difficulty,
         height: next_height,
@@ -182,3 +203,105 @@ fn default_paylod<DB: DatabaseTrait>(
 
     Ok(payload)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use database::immemorydb::InMemoryDB;
+    use primitives::{
+        test_utils::sign,
+        transaction::{Recovered, Transaction, TxTransfer},
+        types::Account,
+    };
+    use transaction_pool::identifier::TransactionOrigin;
+
+    use super::*;
+
+    // Transfer with a memo from the account of `seed`
+    fn memo_transfer(seed: &str, nonce: u64, memo_len: usize) -> Recovered {
+        let tx = Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce,
+            to: Address::random(),
+            fee: 1000,
+            value: U256::from(1),
+            data: vec![7; memo_len],
+        });
+        sign(seed, tx)
+    }
+
+    #[test]
+    fn test_memos_count_toward_max_data_bytes() {
+        // One pending transaction per sender
+        let transfers: Vec<_> = ["alice", "bob", "carol"]
+            .iter()
+            .map(|seed| memo_transfer(seed, 0, 600))
+            .collect();
+        let (provider, pool) = pool_with(transfers);
+        let mut args = BuildArguments::new(
+            Address::random(),
+            provider.db().get_latest_block_header(),
+            10,
+        );
+        args.attributes.max_data_bytes = 1500;
+        let payload = default_paylod(args, provider, pool).unwrap();
+        assert_eq!(payload.body.len(), 2);
+    }
+
+    // Funds the senders and puts the transfers in a pool
+    fn pool_with(
+        transfers: Vec<Recovered>,
+    ) -> (ProviderFactory<Arc<InMemoryDB>>, Pool<Arc<InMemoryDB>>) {
+        let mut db = InMemoryDB::new();
+        for recovered in transfers.iter() {
+            db.add_account(recovered.signer(), Account::new(0, U256::from(10000)))
+                .unwrap();
+        }
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        let pool = Pool::new(provider.clone());
+        for recovered in transfers {
+            pool.add_transaction(TransactionOrigin::External, recovered)
+                .unwrap();
+        }
+        (provider, pool)
+    }
+
+    #[test]
+    fn test_skipped_transaction_holds_back_later_nonces() {
+        let transfers = vec![
+            memo_transfer("alice", 0, 600),
+            memo_transfer("alice", 1, 0),
+            memo_transfer("bob", 0, 0),
+        ];
+        let bob = transfers[2].tx().hash;
+        let (provider, pool) = pool_with(transfers);
+        let mut args = BuildArguments::new(
+            Address::random(),
+            provider.db().get_latest_block_header(),
+            10,
+        );
+        args.attributes.max_data_bytes = 500;
+        let payload = default_paylod(args, provider, pool).unwrap();
+        // Nonce 0 is over the data limit, so nonce 1 would leave a gap
+        let hashes: Vec<_> = payload.body.iter().map(|tx| tx.hash).collect();
+        assert_eq!(hashes, vec![bob]);
+    }
+
+    #[test]
+    fn test_max_transactions_counts_included_transactions() {
+        let transfers = ["alice", "bob", "carol", "dave"]
+            .iter()
+            .map(|seed| memo_transfer(seed, 0, 0))
+            .collect();
+        let (provider, pool) = pool_with(transfers);
+        let mut args = BuildArguments::new(
+            Address::random(),
+            provider.db().get_latest_block_header(),
+            10,
+        );
+        args.attributes.max_transactions = 3;
+        let payload = default_paylod(args, provider, pool).unwrap();
+        assert_eq!(payload.body.len(), 3);
+    }
+}
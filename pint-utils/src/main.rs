@@ -1,4 +1,4 @@
-use primitives::{transaction::Transaction, types::U256};
+use primitives::{transaction::{Transaction, TxTransfer}, types::U256};
 use tokio::time::Duration;
 
 use e2e_test::{
@@ -16,13 +16,14 @@ async fn main() {
 
     tokio::time::sleep(Duration::from_secs(3)).await;
 
-    let tx = Transaction {
+    let tx = Transaction::Transfer(TxTransfer {
         chain_id: 0,
         nonce: 0,
         to: addr_apple,
         fee: 5,
         value: U256::from(1000),
-    };
+        data: Vec::new(),
+    });
 
     let signed = create_signed(&key_pint, tx);
     let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
@@ -31,24 +32,26 @@ async fn main() {
     tokio::time::sleep(Duration::from_secs(15)).await;
 
     for i in 1..5 {
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce: 5 - i,
             to: addr_apple,
             fee: 5 * i as u128,
             value: U256::from(1000 * i),
-        };
+            data: Vec::new(),
+        });
 
         let signed = create_signed(&key_pint, tx);
         let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
 
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce: 4 - i,
             to: addr_banana,
             fee: 5 * i as u128,
             value: U256::from(1000 * i),
-        };
+            data: Vec::new(),
+        });
 
         let signed = create_signed(&_key_apple, tx);
         let _ = send_tx_to_rpc(signed.clone(), boot_node_url).await.unwrap();
@@ -0,0 +1,122 @@
+use std::{
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Subcommand};
+use keystore::{Keystore, Mnemonic, error::KeystoreError};
+use primitives::types::Address;
+
+// Default Miner Address: 28dcb1338b900419cd613a8fb273ae36e7ec2b1c
+const DEFAULT_MINER_ADDRESS: &str = "28dcb1338b900419cd613a8fb273ae36e7ec2b1c";
+// Read when no --password-file is given, before falling back to the prompt
+const PASSWORD_ENV: &str = "PINT_KEYSTORE_PASSWORD";
+
+/// The password is never taken on the command line, where it would show up in
+/// the process list and the shell history
+#[derive(Args)]
+pub struct PasswordArgs {
+    /// File holding the keystore password on its first line
+    #[arg(long)]
+    password_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum AccountCommand {
+    /// Generate a new key and store it encrypted
+    New {
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// List addresses in the keystore
+    List,
+    /// Import a hex encoded secret key
+    Import {
+        secret: String,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Generate a new mnemonic phrase
+    Mnemonic {
+        #[arg(long, default_value_t = 24)]
+        words: usize,
+    },
+    /// Import an account derived from a mnemonic phrase
+    Recover {
+        phrase: String,
+        #[arg(long, default_value_t = 0)]
+        index: u32,
+        #[arg(long, default_value_t = String::new())]
+        passphrase: String,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+}
+
+// Password from --password-file, then PINT_KEYSTORE_PASSWORD, otherwise the first line of stdin
+fn read_password(args: PasswordArgs) -> Result<String, KeystoreError> {
+    if let Some(path) = args.password_file {
+        let content = std::fs::read_to_string(path)?;
+        return Ok(first_line(&content));
+    }
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+    eprintln!("Password:");
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(first_line(&line))
+}
+
+fn first_line(content: &str) -> String {
+    content.lines().next().unwrap_or_default().to_string()
+}
+
+pub fn run_account_command(
+    command: AccountCommand,
+    keystore_dir: &Path,
+) -> Result<(), KeystoreError> {
+    let keystore = Keystore::new(keystore_dir);
+    match command {
+        AccountCommand::New { password } => {
+            let address = keystore.create(&read_password(password)?)?;
+            println!("{}", address.get_addr_hex());
+        }
+        AccountCommand::List => {
+            for address in keystore.list()? {
+                println!("{}", address.get_addr_hex());
+            }
+        }
+        AccountCommand::Import { secret, password } => {
+            let secret = hex::decode(secret.trim_start_matches("0x"))?;
+            let address = keystore.import(&secret, &read_password(password)?)?;
+            println!("{}", address.get_addr_hex());
+        }
+        AccountCommand::Mnemonic { words } => {
+            println!("{}", Mnemonic::generate(words)?.phrase());
+        }
+        AccountCommand::Recover {
+            phrase,
+            index,
+            passphrase,
+            password,
+        } => {
+            let key = Mnemonic::from_phrase(&phrase)?.derive_account(&passphrase, index)?;
+            let address = keystore.store(&key, &read_password(password)?)?;
+            println!("{}", address.get_addr_hex());
+        }
+    }
+    Ok(())
+}
+
+/// --miner-address if given, otherwise the first keystore account, otherwise the default address.
+pub fn resolve_miner_address(miner_address: Option<String>, keystore_dir: &Path) -> Address {
+    let miner_address = match miner_address {
+        Some(address) => address,
+        None => match Keystore::new(keystore_dir).list() {
+            Ok(addresses) if !addresses.is_empty() => addresses[0].get_addr_hex(),
+            _ => DEFAULT_MINER_ADDRESS.to_string(),
+        },
+    };
+    Address::from_hex(miner_address).expect("Wrong miner address! Node is shut.")
+}
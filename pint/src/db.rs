@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use database::{
+    DBImpl, error::DatabaseError, mdbx::MDBX, segment::SegmentDB, traits::DatabaseTrait,
+};
+use node::{
+    chain_file::{export_blocks, import_blocks},
+    datadir::{DataDir, DataDirLock},
+    error::{ChainFileError, NodeLaunchError, SnapshotError},
+    snapshot::{export_snapshot, import_snapshot},
+};
+use primitives::types::{B256, ChainId};
+use provider::{ProviderFactory, check::Inconsistency};
+use thiserror::Error;
+use tracing::info;
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Verify indexes, chain linkage and state of every stored block
+    Check,
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotCommand {
+    /// Write the full state at a block, with the block, to a file
+    Export {
+        // Defaults to the latest block
+        #[arg(long)]
+        height: Option<u64>,
+        file: PathBuf,
+    },
+    /// Start an empty database from a snapshot file. Later blocks are synced from peers.
+    Import {
+        // Hash of the snapshot block, from a source the operator trusts rather than the file
+        #[arg(long)]
+        block_hash: B256,
+        file: PathBuf,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum DbCommandError {
+    #[error("No database at {}", .0.display())]
+    NoDatabase(PathBuf),
+    #[error("{0}")]
+    DataDir(NodeLaunchError),
+    #[error("{0}")]
+    Database(DatabaseError),
+    #[error("Inconsistent database at {0}")]
+    Inconsistent(Inconsistency),
+    #[error("{0}")]
+    ChainFile(ChainFileError),
+    #[error("{0}")]
+    Snapshot(SnapshotError),
+}
+
+// The database a node with the same --segment-db choice runs on
+fn open_db(
+    datadir: &DataDir,
+    chain_id: ChainId,
+    segment_db: bool,
+) -> Result<DBImpl, DbCommandError> {
+    let db = match segment_db {
+        true => SegmentDB::new(datadir.segments_path(), chain_id).map(DBImpl::SegmentDB),
+        false => MDBX::new(datadir.db_path(), chain_id).map(DBImpl::MDBX),
+    };
+    db.map_err(DbCommandError::Database)
+}
+
+// A running node would change the tables under the command
+fn open_existing(
+    datadir: &DataDir,
+    chain_id: ChainId,
+    segment_db: bool,
+) -> Result<(DataDirLock, DBImpl), DbCommandError> {
+    let lock = datadir.lock().map_err(DbCommandError::DataDir)?;
+    let path = match segment_db {
+        true => datadir.segments_path(),
+        false => datadir.db_path(),
+    };
+    if !path.exists() {
+        return Err(DbCommandError::NoDatabase(path));
+    }
+    let db = open_db(datadir, chain_id, segment_db)?;
+    Ok((lock, db))
+}
+
+pub fn run_db_command(
+    command: DbCommand,
+    datadir: &DataDir,
+    chain_id: ChainId,
+    segment_db: bool,
+) -> Result<(), DbCommandError> {
+    let (_lock, db) = open_existing(datadir, chain_id, segment_db)?;
+    match command {
+        DbCommand::Check => {
+            let provider = ProviderFactory::new(db, chain_id);
+            let report = provider
+                .check_integrity()
+                .map_err(DbCommandError::Inconsistent)?;
+            println!(
+                "Database is consistent: {} blocks checked, {} re-executed",
+                report.blocks, report.executed
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Writes blocks `from..=to` (the latest block by default) to `file`
+pub fn run_export(
+    datadir: &DataDir,
+    chain_id: ChainId,
+    from: u64,
+    to: Option<u64>,
+    file: &Path,
+    segment_db: bool,
+) -> Result<(), DbCommandError> {
+    let (_lock, db) = open_existing(datadir, chain_id, segment_db)?;
+    let to = to.unwrap_or_else(|| db.latest_block_number());
+    let exported = export_blocks(&db, from, to, file).map_err(DbCommandError::ChainFile)?;
+    info!(exported, from, to, "Export finished.");
+    Ok(())
+}
+
+/// Validates and imports the blocks of `file`, creating the database if needed
+pub fn run_import(
+    datadir: &DataDir,
+    chain_id: ChainId,
+    file: &Path,
+    segment_db: bool,
+) -> Result<(), DbCommandError> {
+    let _lock = datadir.lock().map_err(DbCommandError::DataDir)?;
+    datadir.create_dirs().map_err(DbCommandError::DataDir)?;
+    let db = match segment_db {
+        true => SegmentDB::genesis_state(datadir.segments_path(), chain_id).map(DBImpl::SegmentDB),
+        false => MDBX::genesis_state(datadir.db_path(), chain_id).map(DBImpl::MDBX),
+    }
+    .map_err(DbCommandError::Database)?;
+    let provider = ProviderFactory::new(db, chain_id);
+    let report = import_blocks(provider.clone(), file).map_err(DbCommandError::ChainFile)?;
+    info!(
+        imported = report.imported,
+        skipped = report.skipped,
+        latest = provider.block_number(),
+        "Import finished."
+    );
+    Ok(())
+}
+
+pub fn run_snapshot_command(
+    command: SnapshotCommand,
+    datadir: &DataDir,
+    chain_id: ChainId,
+    segment_db: bool,
+) -> Result<(), DbCommandError> {
+    match command {
+        SnapshotCommand::Export { height, file } => {
+            let (_lock, db) = open_existing(datadir, chain_id, segment_db)?;
+            let height = height.unwrap_or_else(|| db.latest_block_number());
+            export_snapshot(&db, height, &file).map_err(DbCommandError::Snapshot)?;
+        }
+        SnapshotCommand::Import { block_hash, file } => {
+            let _lock = datadir.lock().map_err(DbCommandError::DataDir)?;
+            datadir.create_dirs().map_err(DbCommandError::DataDir)?;
+            let db = open_db(datadir, chain_id, segment_db)?;
+            let provider = ProviderFactory::new(db, chain_id);
+            let height = import_snapshot(&provider, &file, block_hash.into())
+                .map_err(DbCommandError::Snapshot)?;
+            info!(height, "Snapshot import finished.");
+        }
+    }
+    Ok(())
+}
@@ -11,7 +11,7 @@ use transaction_pool::identifier::TransactionOrigin;
 pub fn init_txs<DB: DatabaseTrait>(node: &Node<DB>) {
     // Test code! Initial transactions
     // From: pint, To: apple, Fee: 10, Value: 1000, Nonce: 0
-    let tx = "0000000000000000000000000000000008041f667c366ee714d6cbefe2a8477ad7488f100000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000003e8e124cac1252a8595c4da5e4d810d231a68571e8b590da337c17a67980e9452ef4e4dbd0a4b7312bd778b5a28dde2e73d152c07a56c5cb246d84f2d6f6d5631aa00";
+    let tx = "0102000000a4010100000059000000000000000000000000000000000008041f667c366ee714d6cbefe2a8477ad7488f100000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000003e80000000005bc5e561dd0ad9d3aacf47df4348c4347e1b398b4b37b4d3f795c9661efa8e6760f8ddc71cd6e9441516e6718bd5b1cfef6db1b74f95a881fbc7da98e17e9cb0000000000";
     let data = hex::decode(tx).unwrap();
     let (signed, _) = SignedTransaction::decode(&data).unwrap();
 
@@ -22,7 +22,7 @@ pub fn init_txs<DB: DatabaseTrait>(node: &Node<DB>) {
         error!("Tx1 add failed");
     }
     // From: pint, To: banana, Fee: 10, Value: 1000, Nonce: 1
-    let tx = "00000000000000000000000000000001b2aaaf07a29937c3b833dca1c9659d98a95690700000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000003e8c1f3d993c37465ba08cf75eecddb01b214f84d77be915543c47374ae22d4cc6b78354616140743272fd536194a866ad0bd3c6d2d3f4531ee52d3c6bad99b5d1a01";
+    let tx = "0102000000a40101000000590000000000000000000000000000000001b2aaaf07a29937c3b833dca1c9659d98a95690700000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000003e800000000f28285be642d901a10eae9870fb2fcec027e931038ab2f6c68ca27126bdf2fce7df85e9b75ea4096879151f72f3dc6fe04dc9bf91ea1f749b8a4c2296c6f10b30000000000";
     let data = hex::decode(tx).unwrap();
     let (signed, _) = SignedTransaction::decode(&data).unwrap();
 
@@ -33,7 +33,7 @@ pub fn init_txs<DB: DatabaseTrait>(node: &Node<DB>) {
         error!("Tx2 add failed");
     }
     // From: chain, To: banana, Fee: 5, Value: 1000, Nonce: 0
-    let tx = "00000000000000000000000000000000b2aaaf07a29937c3b833dca1c9659d98a95690700000000000000000000000000000000500000000000000000000000000000000000000000000000000000000000003e806cc9be9a58dbba4fa5459512c6d5c3d100bbfcb71cfffb669037243babb0c8678077cba676a8eb659f35a148b551dfadaef085cccbac97729c5a743cab9eec901";
+    let tx = "0102000000a40101000000590000000000000000000000000000000000b2aaaf07a29937c3b833dca1c9659d98a95690700000000000000000000000000000000500000000000000000000000000000000000000000000000000000000000003e80000000079d547cc68bad647c08b1349dfa87c4df368dd2d4dff4de00084613d3e88a9c413a8e2ecb31f1b56ca7b844f08b787ebf036744f34a25a0517c210197bab4a9c0100000000";
     let data = hex::decode(tx).unwrap();
     let (signed, _) = SignedTransaction::decode(&data).unwrap();
 
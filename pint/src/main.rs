@@ -1,20 +1,37 @@
-use std::net::{IpAddr, Ipv4Addr};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+};
 
-use clap::Parser;
-use database::mdbx::get_db_path;
+use clap::{Parser, Subcommand};
+use database::prune::PruneMode;
 use network::builder::NetworkConfig;
-use node::{builder::LaunchContext, configs::BlockConfig};
-use primitives::types::Address;
+use node::{
+    builder::LaunchContext,
+    configs::{BlockConfig, ChainConfig, PruneConfig},
+    datadir::DataDir,
+};
 use tokio::signal;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
-use crate::init::init_txs;
+use crate::{
+    account::{AccountCommand, resolve_miner_address, run_account_command},
+    db::{
+        DbCommand, SnapshotCommand, run_db_command, run_export, run_import, run_snapshot_command,
+    },
+    init::init_txs,
+};
+mod account;
+mod db;
 mod init;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short, long, default_value_t = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))]
     address: IpAddr,
 
@@ -24,9 +41,9 @@ struct Args {
     #[arg(short, long, default_value_t = 8888)]
     rpc_port: u16,
 
-    // Default Miner Address: 28dcb1338b900419cd613a8fb273ae36e7ec2b1c
-    #[arg(short, long, default_value_t = String::from("28dcb1338b900419cd613a8fb273ae36e7ec2b1c"))]
-    miner_address: String,
+    // Falls back to the first keystore account, then to the default miner address
+    #[arg(short, long)]
+    miner_address: Option<String>,
 
     #[arg(long, default_value_t = false)]
     boot_node: bool,
@@ -34,6 +51,16 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     in_memory_db: bool,
 
+    /// Store the chain in append-only segment files under <datadir>/segments instead of MDBX.
+    /// The whole chain is replayed into memory on start and kept there, so memory grows with it.
+    /// Also selects the database of the db, export, import and snapshot commands.
+    #[arg(long, default_value_t = false)]
+    segment_db: bool,
+
+    // Holds the database and the keystore. Defaults to <data_dir>/pint
+    #[arg(long)]
+    datadir: Option<PathBuf>,
+
     #[arg(long, default_value_t = false)]
     test: bool,
 
@@ -42,6 +69,43 @@ struct Args {
 
     #[arg(short, long, default_value_t = String::from("boot_node"))]
     name: String,
+
+    #[arg(long, default_value_t = 0)]
+    chain_id: u64,
+
+    // State history to keep: "archive", "minimal" or a number of blocks
+    #[arg(long, default_value_t = PruneMode::Archive)]
+    prune: PruneMode,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage keystore accounts
+    Account {
+        #[command(subcommand)]
+        command: AccountCommand,
+    },
+    /// Inspect the node database
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// Write blocks to a file in the canonical encoding. Continues an interrupted export of the same file.
+    Export {
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        // Defaults to the latest block
+        #[arg(long)]
+        to: Option<u64>,
+        file: PathBuf,
+    },
+    /// Validate and import the blocks of an exported file. Run again to continue after an interruption.
+    Import { file: PathBuf },
+    /// Export the state at a block, or start a new node from it
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
 }
 
 #[tokio::main]
@@ -60,25 +124,84 @@ async fn main() {
         .init();
 
     let args = Args::parse();
+    let datadir = match &args.datadir {
+        Some(root) => DataDir::new(root),
+        None => DataDir::default(),
+    };
+    match args.command {
+        Some(Command::Account { command }) => {
+            if let Err(err) = run_account_command(command, &datadir.keystore_path()) {
+                error!(error = ?err, "Account command failed.");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Db { command }) => {
+            if let Err(err) = run_db_command(command, &datadir, args.chain_id, args.segment_db) {
+                error!(error = %err, "Database command failed.");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Export { from, to, file }) => {
+            if let Err(err) = run_export(&datadir, args.chain_id, from, to, &file, args.segment_db)
+            {
+                error!(error = %err, "Export failed.");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Import { file }) => {
+            if let Err(err) = run_import(&datadir, args.chain_id, &file, args.segment_db) {
+                error!(error = %err, "Import failed.");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Snapshot { command }) => {
+            if let Err(err) =
+                run_snapshot_command(command, &datadir, args.chain_id, args.segment_db)
+            {
+                error!(error = %err, "Snapshot command failed.");
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
     info!(node_name = &args.name, "Try to launch PintChain Node.");
     // info!("({}) Try to launch PintChain Node.", args.name);
 
     if args.remove_data {
-        let pathbuf = get_db_path();
-        if pathbuf.exists() {
-            std::fs::remove_dir_all(&pathbuf).expect("Failed to remove DB directory");
+        // Never wipe a database another node is running on
+        let _lock = match datadir.lock() {
+            Ok(lock) => lock,
+            Err(err) => {
+                error!(error = %err, "Failed to remove DB data.");
+                return;
+            }
+        };
+        for pathbuf in [datadir.db_path(), datadir.segments_path()] {
+            if pathbuf.exists() {
+                std::fs::remove_dir_all(&pathbuf).expect("Failed to remove DB directory");
+            }
         }
         info!("Removing DB data Ok");
     }
 
-    let miner_address =
-        Address::from_hex(args.miner_address).expect("Wrong miner address! Node is shut.");
+    let miner_address = resolve_miner_address(args.miner_address, &datadir.keystore_path());
+    info!(miner_address = miner_address.get_addr_hex(), "Miner address");
 
     let mut network_config = NetworkConfig::new(args.address, args.port, args.rpc_port);
     network_config.boot_node.is_boot_node = args.boot_node;
     let block_config: BlockConfig = BlockConfig::new(miner_address);
-    let launch_context =
+    let mut launch_context =
         LaunchContext::new(network_config.clone(), block_config, args.in_memory_db);
+    launch_context.chain_config = ChainConfig::new(args.chain_id);
+    launch_context.prune_config = PruneConfig::new(args.prune);
+    launch_context.segment_db = args.segment_db;
+    launch_context.datadir = datadir;
 
     let node = match launch_context.launch().await {
         Ok(node) => node,
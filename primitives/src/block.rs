@@ -5,9 +5,13 @@ use anyhow::bail;
 use libmdbx::orm::{Decodable, Encodable};
 use sha2::{Digest, Sha256};
 
+use crate::encoding::{Decoder, Encoder, ItemType};
 use crate::error::{BlockValidatioError, DecodeError};
 use crate::types::{Address, B256, COINBASE_ADDR};
-use crate::{transaction::SignedTransaction, types::BlockHash};
+use crate::{
+    transaction::{SignedTransaction, Tx},
+    types::BlockHash,
+};
 
 /// Block hash
 #[derive(Debug, Default, Clone)]
@@ -15,6 +19,7 @@ pub struct Header {
     pub previous_hash: BlockHash, // 32
     pub transaction_root: B256,   // 32
     pub state_root: B256,         // 32
+    pub receipts_root: B256,      // 32
     pub timestamp: u64,           // 8
     pub proposer: Address,        // 20
     pub nonce: u64,               // 8
@@ -29,6 +34,7 @@ impl Header {
             previous_hash: Default::default(),
             transaction_root: Default::default(),
             state_root: Default::default(),
+            receipts_root: Default::default(),
             timestamp: 0,
             proposer: COINBASE_ADDR,
             nonce: 0,
@@ -39,31 +45,43 @@ impl Header {
     }
 
     pub fn encode(&self) -> Vec<u8> {
-        let mut raw = [0u8; 176];
-        raw[0..32].copy_from_slice(&self.previous_hash.hash().0);
-        raw[32..64].copy_from_slice(&self.transaction_root.0);
-        raw[64..96].copy_from_slice(&self.state_root.0);
-        raw[96..104].copy_from_slice(&self.timestamp.to_be_bytes());
-        raw[104..124].copy_from_slice(&self.proposer.get_addr());
-        raw[124..132].copy_from_slice(&self.nonce.to_be_bytes());
-        raw[132..136].copy_from_slice(&self.difficulty.to_be_bytes());
-        raw[136..144].copy_from_slice(&self.height.to_be_bytes());
-        raw[144..176].copy_from_slice(&self.total_fee.to_be_bytes::<32>());
-        raw.to_vec()
+        Encoder::new()
+            .put_b256(self.previous_hash.hash())
+            .put_b256(self.transaction_root)
+            .put_b256(self.state_root)
+            .put_b256(self.receipts_root)
+            .put_u64(self.timestamp)
+            .put_address(self.proposer)
+            .put_u64(self.nonce)
+            .put_u32(self.difficulty)
+            .put_u64(self.height)
+            .put_u256(self.total_fee)
+            .finish(ItemType::Header)
     }
 
-    pub fn decode(buf: [u8; 176]) -> Self {
-        Self {
-            previous_hash: BlockHash::from(B256::from_slice(&buf[0..32])),
-            transaction_root: B256::from_slice(&buf[32..64]),
-            state_root: B256::from_slice(&buf[64..96]),
-            timestamp: u64::from_be_bytes(buf[96..104].try_into().unwrap()),
-            proposer: Address::from_byte(buf[104..124].try_into().unwrap()),
-            nonce: u64::from_be_bytes(buf[124..132].try_into().unwrap()),
-            difficulty: u32::from_be_bytes(buf[132..136].try_into().unwrap()),
-            height: u64::from_be_bytes(buf[136..144].try_into().unwrap()),
-            total_fee: U256::from_be_slice(&buf[144..176]),
-        }
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let header = Self::decode_from(&mut decoder)?;
+        decoder.finish()?;
+        Ok(header)
+    }
+
+    pub(crate) fn decode_from(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+        let mut payload = decoder.read_item(ItemType::Header)?;
+        let header = Self {
+            previous_hash: BlockHash::from(payload.read_b256()?),
+            transaction_root: payload.read_b256()?,
+            state_root: payload.read_b256()?,
+            receipts_root: payload.read_b256()?,
+            timestamp: payload.read_u64()?,
+            proposer: payload.read_address()?,
+            nonce: payload.read_u64()?,
+            difficulty: payload.read_u32()?,
+            height: payload.read_u64()?,
+            total_fee: payload.read_u256()?,
+        };
+        payload.finish()?;
+        Ok(header)
     }
 
     pub fn calculate_hash(&self) -> BlockHash {
@@ -71,6 +89,7 @@ impl Header {
         hasher.update(self.previous_hash.hash());
         hasher.update(self.transaction_root);
         hasher.update(self.state_root);
+        hasher.update(self.receipts_root);
         hasher.update(self.timestamp.to_be_bytes());
         hasher.update(self.proposer.get_addr());
         hasher.update(self.difficulty.to_be_bytes());
@@ -98,38 +117,29 @@ impl Block {
     }
 
     pub fn encode_ref(&self) -> Vec<u8> {
-        let mut res: Vec<u8> = Vec::new();
-        let header = self.header.encode();
-        res = [res, header].concat();
-
-        // Recoverd -> SignedTransaction
-        for recovered in self.body.iter() {
-            let encoded = recovered.encode();
-            res = [res, encoded].concat();
+        let mut encoder = Encoder::new();
+        encoder.put_item(&Header::encode(&self.header));
+        encoder.put_u32(self.body.len() as u32);
+        for signed in self.body.iter() {
+            encoder.put_item(&signed.encode());
         }
-        res
+        encoder.finish(ItemType::Block)
     }
 
+    /// Strict decoding: `buf` must hold exactly one block.
     pub fn decode(buf: &[u8]) -> Result<(Self, usize), DecodeError> {
-        let mut used_byte = 0;
-        if buf.len() < 176 {
-            return Err(DecodeError::TooShortRawData(buf.to_vec()));
-        }
-
-        let (header_raw, mut body_raw) = buf.split_at(176);
-        used_byte += 176;
-        let header = Header::decode(header_raw.try_into().unwrap());
+        let mut decoder = Decoder::new(buf);
+        let mut payload = decoder.read_item(ItemType::Block)?;
+        let header = Header::decode_from(&mut payload)?;
+        let tx_count = payload.read_u32()?;
         let mut body = Vec::new();
-
-        while body_raw.len() >= 149 {
-            let (tx_raw, remains) = body_raw.split_at(149);
-            used_byte += 149;
-            let (signed, _) = SignedTransaction::decode(&tx_raw.to_vec()).unwrap();
-            body_raw = remains;
-            body.push(signed);
+        for _ in 0..tx_count {
+            body.push(SignedTransaction::decode_from(&mut payload)?);
         }
+        payload.finish()?;
+        decoder.finish()?;
 
-        Ok((Self { header, body }, used_byte))
+        Ok((Self { header, body }, decoder.position()))
     }
 
     pub fn header(&self) -> &Header {
@@ -141,37 +151,76 @@ impl Encodable for Block {
     type Encoded = Vec<u8>;
 
     fn encode(self) -> Self::Encoded {
-        let mut res: Vec<u8> = Vec::new();
-        let header = self.header.encode();
-        res = [res, header].concat();
-
-        // Recoverd -> SignedTransaction
-        for recovered in self.body.iter() {
-            let encoded = recovered.encode();
-            res = [res, encoded].concat();
-        }
-        res
+        self.encode_ref()
     }
 }
 
 impl Decodable for Block {
     fn decode(b: &[u8]) -> anyhow::Result<Self> {
-        if b.len() < 176 {
-            bail!("Too short raw data: {} bytes", b.len());
+        match Block::decode(b) {
+            Ok((block, _)) => Ok(block),
+            Err(e) => bail!("e: {:?}", e),
         }
+    }
+}
 
-        let (header_raw, mut body_raw) = b.split_at(176);
-        let header = Header::decode(header_raw.try_into().unwrap());
-        let mut body = Vec::new();
+impl Encodable for Header {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        Header::encode(&self)
+    }
+}
+
+impl Decodable for Header {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match Header::decode(b) {
+            Ok(header) => Ok(header),
+            Err(e) => bail!("e: {:?}", e),
+        }
+    }
+}
+
+/// Transactions of a block, stored apart from its header.
+/// Encoded as the block body is inside a block: a u32 count and the signed transactions.
+#[derive(Debug, Clone, Default)]
+pub struct BlockBody(pub Vec<SignedTransaction>);
 
-        while body_raw.len() >= 149 {
-            let (tx_raw, remains) = body_raw.split_at(149);
-            let (signed, _) = SignedTransaction::decode(&tx_raw.to_vec()).unwrap();
-            body_raw = remains;
-            body.push(signed);
+impl BlockBody {
+    pub fn encode_ref(&self) -> Vec<u8> {
+        let mut raw = (self.0.len() as u32).to_be_bytes().to_vec();
+        for signed in self.0.iter() {
+            raw.extend_from_slice(&signed.encode());
+        }
+        raw
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let tx_count = decoder.read_u32()?;
+        let mut body = Vec::new();
+        for _ in 0..tx_count {
+            body.push(SignedTransaction::decode_from(&mut decoder)?);
         }
+        decoder.finish()?;
+        Ok(Self(body))
+    }
+}
+
+impl Encodable for BlockBody {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        self.encode_ref()
+    }
+}
 
-        Ok(Self { header, body })
+impl Decodable for BlockBody {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match BlockBody::decode(b) {
+            Ok(body) => Ok(body),
+            Err(e) => bail!("e: {:?}", e),
+        }
     }
 }
 
@@ -181,6 +230,7 @@ pub struct PayloadHeader {
     pub previous_hash: BlockHash,
     pub transaction_root: B256,
     pub state_root: B256,
+    pub receipts_root: B256,
     pub proposer: Address,
     pub difficulty: u32,
     pub timestamp: u64,
@@ -194,6 +244,7 @@ impl PayloadHeader {
             previous_hash: self.previous_hash,
             transaction_root: self.transaction_root,
             state_root: self.state_root,
+            receipts_root: self.receipts_root,
             timestamp: self.timestamp,
             proposer: self.proposer,
             nonce,
@@ -218,6 +269,7 @@ impl fmt::Display for Payload {
         writeln!(f, "    Previous Hash: {:?}", self.header.previous_hash)?;
         writeln!(f, "    Tx Root      : {:?}", self.header.transaction_root)?;
         writeln!(f, "    State Root   : {:?}", self.header.state_root)?;
+        writeln!(f, "    Receipts Root: {:?}", self.header.receipts_root)?;
         writeln!(f, "    Proposer     : {:?}", self.header.proposer)?;
         writeln!(f, "    Difficulty   : {}", self.header.difficulty)?;
         writeln!(f, "    Timestamp    : {}", self.header.timestamp)?;
@@ -227,12 +279,13 @@ impl fmt::Display for Payload {
         for (i, tx) in self.body.iter().enumerate() {
             writeln!(
                 f,
-                "    {}. hash: {:?}, to: {:?}, value: {}, fee: {}",
+                "    {}. {} hash: {:?}, to: {:?}, value: {}, fee: {}",
                 i + 1,
+                tx.tx_type(),
                 tx.hash,
-                tx.tx.to,
-                tx.tx.value,
-                tx.tx.fee
+                tx.to(),
+                tx.value(),
+                tx.fee()
             )?;
         }
         write!(f, "}}")
@@ -246,6 +299,7 @@ impl fmt::Display for Block {
         writeln!(f, "    Previous Hash: {:?}", self.header.previous_hash)?;
         writeln!(f, "    Tx Root      : {:?}", self.header.transaction_root)?;
         writeln!(f, "    State Root   : {:?}", self.header.state_root)?;
+        writeln!(f, "    Receipts Root: {:?}", self.header.receipts_root)?;
         writeln!(f, "    Proposer     : {:?}", self.header.proposer)?;
         writeln!(f, "    Difficulty   : {}", self.header.difficulty)?;
         writeln!(f, "    Timestamp    : {}", self.header.timestamp)?;
@@ -256,12 +310,13 @@ impl fmt::Display for Block {
         for (i, tx) in self.body.iter().enumerate() {
             writeln!(
                 f,
-                "    {}. hash: {:?}, to: {:?}, value: {}, fee: {}",
+                "    {}. {} hash: {:?}, to: {:?}, value: {}, fee: {}",
                 i + 1,
+                tx.tx_type(),
                 tx.hash,
-                tx.tx.to,
-                tx.tx.value,
-                tx.tx.fee
+                tx.to(),
+                tx.value(),
+                tx.fee()
             )?;
         }
 
@@ -296,3 +351,57 @@ impl Default for BlockValidationResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        signature::Signature,
+        transaction::{Transaction, TxTransfer},
+    };
+
+    #[test]
+    fn test_block_encode_and_decode() {
+        let mut block = Block::genesis_block();
+        block.header.height = 3;
+        block.header.total_fee = U256::from(15);
+        for nonce in 0..3 {
+            let tx = Transaction::Transfer(TxTransfer {
+                chain_id: 0,
+                nonce,
+                to: Address::random(),
+                fee: 5,
+                value: U256::from(100),
+                data: Vec::new(),
+            });
+            let hash = tx.encode_for_signing();
+            block.body.push(SignedTransaction::new(tx, Signature::dummy(), hash));
+        }
+
+        let encoded = block.encode_ref();
+        let (decoded, used) = Block::decode(&encoded).unwrap();
+        assert_eq!(used, encoded.len());
+        assert_eq!(decoded.body.len(), 3);
+        assert_eq!(
+            decoded.header.calculate_hash(),
+            block.header.calculate_hash()
+        );
+        for (a, b) in decoded.body.iter().zip(block.body.iter()) {
+            assert_eq!(a.hash, b.hash);
+        }
+
+        let mut trailing = encoded.clone();
+        trailing.extend_from_slice(&[0u8; 4]);
+        assert!(matches!(
+            Block::decode(&trailing),
+            Err(DecodeError::TrailingBytes(4))
+        ));
+
+        // A stored body is the block's body without the header
+        let body = BlockBody(block.body.clone()).encode_ref();
+        assert!(encoded.ends_with(&body));
+        let decoded = BlockBody::decode(&body).unwrap();
+        assert_eq!(decoded.0.len(), 3);
+        assert_eq!(decoded.0[2].hash, block.body[2].hash);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    error::DecodeError,
+    types::{Address, B256, U256},
+};
+
+// Canonical encoding shared by the database and the network.
+//
+// Every item is framed as
+//   [version: u8][item type: u8][payload length: u32 BE][payload]
+// so that a decoder always knows how many bytes belong to the item, and new
+// fields or item types can be introduced behind a new version byte.
+pub const ENCODING_VERSION: u8 = 0x01;
+pub const ITEM_HEADER_LEN: usize = 1 + 1 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ItemType {
+    Transaction = 0x01,
+    SignedTransaction = 0x02,
+    Header = 0x03,
+    Block = 0x04,
+    World = 0x05,
+    MultisigPolicy = 0x06,
+    Receipt = 0x07,
+}
+
+impl TryFrom<u8> for ItemType {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(Self::Transaction),
+            0x02 => Ok(Self::SignedTransaction),
+            0x03 => Ok(Self::Header),
+            0x04 => Ok(Self::Block),
+            0x05 => Ok(Self::World),
+            0x06 => Ok(Self::MultisigPolicy),
+            0x07 => Ok(Self::Receipt),
+            _ => Err(DecodeError::InvalidItemType(value)),
+        }
+    }
+}
+
+/// Builds the payload of one item and frames it
+#[derive(Debug, Default)]
+pub struct Encoder {
+    payload: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put_u8(&mut self, value: u8) -> &mut Self {
+        self.payload.push(value);
+        self
+    }
+
+    pub fn put_u32(&mut self, value: u32) -> &mut Self {
+        self.payload.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_u64(&mut self, value: u64) -> &mut Self {
+        self.payload.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_u128(&mut self, value: u128) -> &mut Self {
+        self.payload.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_u256(&mut self, value: U256) -> &mut Self {
+        self.payload.extend_from_slice(&value.to_be_bytes::<32>());
+        self
+    }
+
+    pub fn put_b256(&mut self, value: B256) -> &mut Self {
+        self.payload.extend_from_slice(&value.0);
+        self
+    }
+
+    pub fn put_address(&mut self, value: Address) -> &mut Self {
+        self.payload.extend_from_slice(value.get_addr());
+        self
+    }
+
+    /// Fixed-size bytes. The decoder must know the length.
+    pub fn put_fixed(&mut self, value: &[u8]) -> &mut Self {
+        self.payload.extend_from_slice(value);
+        self
+    }
+
+    /// Variable-size bytes, prefixed with their u32 length.
+    pub fn put_bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.put_u32(value.len() as u32);
+        self.payload.extend_from_slice(value);
+        self
+    }
+
+    /// An already framed item (nested item)
+    pub fn put_item(&mut self, item: &[u8]) -> &mut Self {
+        self.payload.extend_from_slice(item);
+        self
+    }
+
+    pub fn finish(&self, item_type: ItemType) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(ITEM_HEADER_LEN + self.payload.len());
+        raw.push(ENCODING_VERSION);
+        raw.push(item_type as u8);
+        raw.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        raw.extend_from_slice(&self.payload);
+        raw
+    }
+
+    /// The payload without a frame, for formats that frame their records themselves
+    pub fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+}
+
+/// Reads fields out of a buffer, failing on short data.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn read_fixed(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.remaining() < len {
+            return Err(DecodeError::TooShortRawData(self.buf.to_vec()));
+        }
+        let res = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(res)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_fixed(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.read_fixed(4)?.try_into()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_be_bytes(self.read_fixed(8)?.try_into()?))
+    }
+
+    pub fn read_u128(&mut self) -> Result<u128, DecodeError> {
+        Ok(u128::from_be_bytes(self.read_fixed(16)?.try_into()?))
+    }
+
+    pub fn read_u256(&mut self) -> Result<U256, DecodeError> {
+        Ok(U256::from_be_bytes::<32>(self.read_fixed(32)?.try_into()?))
+    }
+
+    pub fn read_b256(&mut self) -> Result<B256, DecodeError> {
+        Ok(B256::from_slice(self.read_fixed(32)?))
+    }
+
+    pub fn read_address(&mut self) -> Result<Address, DecodeError> {
+        Ok(Address::from_byte(self.read_fixed(20)?.try_into()?))
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.read_u32()? as usize;
+        self.read_fixed(len)
+    }
+
+    /// Reads one framed item of the given type and returns a decoder over its payload.
+    pub fn read_item(&mut self, item_type: ItemType) -> Result<Decoder<'a>, DecodeError> {
+        let version = self.read_u8()?;
+        if version != ENCODING_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let found = ItemType::try_from(self.read_u8()?)?;
+        if found != item_type {
+            return Err(DecodeError::UnexpectedItemType(found as u8));
+        }
+        let len = self.read_u32()? as usize;
+        Ok(Decoder::new(self.read_fixed(len)?))
+    }
+
+    /// Strict decoding: every byte must have been consumed.
+    pub fn finish(&self) -> Result<(), DecodeError> {
+        match self.remaining() {
+            0 => Ok(()),
+            n => Err(DecodeError::TrailingBytes(n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_item_roundtrip() {
+        let mut encoder = Encoder::new();
+        encoder.put_u64(7).put_bytes(b"pint");
+        let raw = encoder.finish(ItemType::Header);
+        assert_eq!(raw.len(), ITEM_HEADER_LEN + 8 + 4 + 4);
+
+        let mut decoder = Decoder::new(&raw);
+        let mut payload = decoder.read_item(ItemType::Header).unwrap();
+        assert_eq!(payload.read_u64().unwrap(), 7);
+        assert_eq!(payload.read_bytes().unwrap(), b"pint");
+        payload.finish().unwrap();
+        decoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_encoding_rejects_bad_frames() {
+        let raw = Encoder::new().put_u64(7).finish(ItemType::Header);
+
+        // wrong item type
+        let res = Decoder::new(&raw).read_item(ItemType::Block);
+        assert!(matches!(res, Err(DecodeError::UnexpectedItemType(0x03))));
+
+        // unknown version
+        let mut bad = raw.clone();
+        bad[0] = 0xff;
+        let res = Decoder::new(&bad).read_item(ItemType::Header);
+        assert!(matches!(res, Err(DecodeError::UnsupportedVersion(0xff))));
+
+        // truncated payload
+        let res = Decoder::new(&raw[..raw.len() - 1]).read_item(ItemType::Header);
+        assert!(matches!(res, Err(DecodeError::TooShortRawData(_))));
+
+        // trailing bytes
+        let mut long = raw.clone();
+        long.push(0);
+        let mut decoder = Decoder::new(&long);
+        decoder.read_item(ItemType::Header).unwrap();
+        assert!(matches!(decoder.finish(), Err(DecodeError::TrailingBytes(1))));
+    }
+}
@@ -1,6 +1,8 @@
 use std::array::TryFromSliceError;
 use thiserror::Error;
 
+use crate::types::Address;
+
 #[derive(Debug, Error)]
 pub enum BlockImportError {
     #[error("Consensus block importer is Noob")]
@@ -13,6 +15,8 @@ pub enum BlockImportError {
     AlreadyImportedBlock,
     #[error("Block is not chained")]
     NotChainedBlock,
+    #[error("Block failed validation")]
+    InvalidBlock,
 }
 
 #[derive(Debug, Error)]
@@ -41,6 +45,18 @@ pub enum DecodeError {
     InvalidSignature(SignatureError),
     #[error("Slice error. Invalid raw data")]
     TryFromSliceError(TryFromSliceError),
+    #[error("Unsupported encoding version")]
+    UnsupportedVersion(u8),
+    #[error("Invalid item type")]
+    InvalidItemType(u8),
+    #[error("Unexpected item type")]
+    UnexpectedItemType(u8),
+    #[error("Raw data has trailing bytes")]
+    TrailingBytes(usize),
+    #[error("Invalid transaction type")]
+    InvalidTxType(u8),
+    #[error("Invalid receipt error code")]
+    InvalidReceiptError(u32),
 }
 
 impl From<TryFromSliceError> for DecodeError {
@@ -63,10 +79,14 @@ impl From<hex::FromHexError> for AddressError {
     }
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum SignatureError {
     #[error("Signature has invalid parity")]
     InvalidParity(u64),
+    #[error("Signature r or s is out of range")]
+    InvalidScalar,
+    #[error("Signature s is not in the lower half of the curve order")]
+    HighS,
 }
 
 #[derive(Debug, Clone, Error)]
@@ -79,6 +99,12 @@ pub enum RecoveryError {
     AddressError(AddressError),
     #[error("Recover from digest Error")]
     RecoveryFromDigestError,
+    #[error("Only multisig transactions carry cosignatures")]
+    UnexpectedCosignatures,
+    #[error("Transaction has too many signatures")]
+    TooManySignatures(usize),
+    #[error("Invalid signature")]
+    SignatureError(SignatureError),
 }
 
 impl From<AddressError> for RecoveryError {
@@ -86,3 +112,19 @@ impl From<AddressError> for RecoveryError {
         Self::AddressError(err)
     }
 }
+
+impl From<SignatureError> for RecoveryError {
+    fn from(err: SignatureError) -> Self {
+        Self::SignatureError(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MultisigError {
+    #[error("Multisig owner count is out of range")]
+    InvalidOwnerCount(usize),
+    #[error("Multisig threshold is out of range")]
+    InvalidThreshold(u8),
+    #[error("Multisig owner is listed twice")]
+    DuplicateOwner(Address),
+}
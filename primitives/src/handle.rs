@@ -41,7 +41,18 @@ pub enum NetworkHandleMessage {
     RemoveUnresponsivePeer(u64),
 }
 
+// Type, protocol version and payload length
+pub const FRAME_HEADER_LEN: usize = 1 + 1 + 8;
+// Largest payload a peer may announce. A bigger frame is a protocol violation.
+pub const MAX_FRAME_PAYLOAD: u64 = 16 * 1024 * 1024;
+
 impl NetworkHandleMessage {
+    /// Payload length announced by the frame at the start of `buf`, once its header has arrived
+    pub fn frame_payload_len(buf: &[u8]) -> Option<u64> {
+        let raw = buf.get(2..FRAME_HEADER_LEN)?;
+        Some(u64::from_be_bytes(raw.try_into().ok()?))
+    }
+
     pub fn encode(&self) -> Vec<u8> {
         match self {
             Self::PeerConnectionTest => {
@@ -241,45 +252,49 @@ impl NetworkHandleMessage {
     }
 
     // First Byte: Message Type
-    // Second Byte: Payload Length
-    // Third Byte: Protocol Version
+    // Second Byte: Protocol Version
+    // Next 8 Bytes: Payload Length
     // remains: Data
+    //
+    // Returns the number of bytes used. `(None, 0)` means the frame is not complete yet.
     pub fn decode(
         buf: &[u8],
         _addr: SocketAddr,
     ) -> Result<(Option<NetworkHandleMessage>, usize), DecodeError> {
-        if buf.len() < 3 {
-            return Ok((None, buf.len()));
+        if buf.len() < 10 {
+            return Ok((None, 0));
         }
 
         let msg_type = buf[0];
         let protocol_version = buf[1];
         let mut payload_len_raw = [0u8; 8];
         payload_len_raw.copy_from_slice(&buf[2..10]);
-        let payload_length = usize::from_be_bytes(payload_len_raw) as u64;
+        let payload_length = u64::from_be_bytes(payload_len_raw) as usize;
 
-        if (buf.len() as u64) < (10 + payload_length) {
-            return Ok((None, buf.len()));
+        if buf.len() - 10 < payload_length {
+            return Ok((None, 0));
         }
 
+        let data = &buf[10..10 + payload_length];
+        let mut buf_used = 10;
+
         if protocol_version > 0 {
             info!("Not proper protocol version.");
-            return Ok((None, buf.len()));
+            return Ok((None, buf_used + payload_length));
         }
 
-        let data = &buf[10..];
-        let mut buf_used = 10;
         match msg_type {
             // PeerConnectionTest
             0x01 => Ok((Some(NetworkHandleMessage::PeerConnectionTest), buf_used)),
             // NewTransaction
             0x02 => {
-                let (signed, _) = SignedTransaction::decode(&data.to_vec())?;
+                let (signed, used) = SignedTransaction::decode(data)?;
+                buf_used += used;
                 Ok((Some(NetworkHandleMessage::NewTransaction(signed)), buf_used))
             }
             // NewPayload
             0x03 => {
-                let (block, used) = Block::decode(&data.to_vec())?;
+                let (block, used) = Block::decode(data)?;
                 buf_used += used;
                 Ok((Some(NetworkHandleMessage::NewPayload(block)), buf_used))
             }
@@ -413,7 +428,7 @@ impl NetworkHandleMessage {
                 buf_used += 6;
                 Ok((Some(NetworkHandleMessage::Pong(ip_addr, port)), buf_used))
             }
-            _ => Ok((None, buf.len())),
+            _ => Ok((None, buf_used + payload_length)),
         }
     }
 }
@@ -568,7 +583,7 @@ impl fmt::Display for ConsensusHandleMessage {
                     "NewTransaction",
                     tx.hash(),
                     tx.signer().get_addr_hex(),
-                    tx.tx().tx.to().get_addr_hex().bright_blue(),
+                    tx.to().map(|to| to.get_addr_hex()).unwrap_or_default().bright_blue(),
                     tx.tx().tx.value().to_string()
                 )
             }
@@ -5,4 +5,9 @@ pub mod block;
 pub mod error;
 pub mod world;
 pub mod handle;
-pub mod merkle;
\ No newline at end of file
+pub mod merkle;
+pub mod multisig;
+pub mod receipt;
+pub mod encoding;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
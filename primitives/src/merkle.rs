@@ -9,18 +9,110 @@ pub fn calculate_merkle_root(mut hashes: Vec<B256>) -> B256 {
     while hashes.len() > 1 {
         let mut next = Vec::new();
         for pair in hashes.chunks(2) {
-            let mut hasher = Sha256::new();
-            if pair.len() == 2 {
-                hasher.update(&pair[0]);
-                hasher.update(&pair[1]);
-            } else {
-                hasher.update(&pair[0]);
-                hasher.update(&pair[0]);
-            }
-            next.push(B256::from_slice(&hasher.finalize()));
+            let right = pair.get(1).unwrap_or(&pair[0]);
+            next.push(hash_pair(&pair[0], right));
         }
         hashes = next;
     }
     
     hashes[0]
-}
\ No newline at end of file
+}
+/// Inclusion proof of one leaf.
+/// `path[i]` is true when the node at level i is the right child, so `siblings[i]` goes on the left.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub siblings: Vec<B256>,
+    pub path: Vec<bool>,
+}
+
+impl MerkleProof {
+    pub fn index(&self) -> usize {
+        self.path
+            .iter()
+            .rev()
+            .fold(0, |index, is_right| (index << 1) | *is_right as usize)
+    }
+}
+
+// Same tree as calculate_merkle_root: an odd node is paired with itself.
+pub fn calculate_merkle_proof(mut hashes: Vec<B256>, mut index: usize) -> Option<MerkleProof> {
+    if index >= hashes.len() {
+        return None;
+    }
+
+    let mut proof = MerkleProof::default();
+    while hashes.len() > 1 {
+        let sibling = match index % 2 {
+            0 => hashes.get(index + 1).unwrap_or(&hashes[index]),
+            _ => &hashes[index - 1],
+        };
+        proof.siblings.push(*sibling);
+        proof.path.push(index % 2 == 1);
+
+        let mut next = Vec::new();
+        for pair in hashes.chunks(2) {
+            let right = pair.get(1).unwrap_or(&pair[0]);
+            next.push(hash_pair(&pair[0], right));
+        }
+        hashes = next;
+        index /= 2;
+    }
+
+    Some(proof)
+}
+
+pub fn verify_merkle_proof(leaf: B256, proof: &MerkleProof, root: B256) -> bool {
+    if proof.siblings.len() != proof.path.len() {
+        return false;
+    }
+
+    let mut current = leaf;
+    for (sibling, is_right) in proof.siblings.iter().zip(proof.path.iter()) {
+        current = match is_right {
+            true => hash_pair(sibling, &current),
+            false => hash_pair(&current, sibling),
+        };
+    }
+    current == root
+}
+
+fn hash_pair(left: &B256, right: &B256) -> B256 {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    B256::from_slice(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merkle_proof_for_every_leaf() {
+        for len in 1..=9 {
+            let leaves: Vec<B256> = (0..len).map(|i| B256::repeat_byte(i as u8 + 1)).collect();
+            let root = calculate_merkle_root(leaves.clone());
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = calculate_merkle_proof(leaves.clone(), index).unwrap();
+                assert_eq!(proof.index(), index);
+                assert!(verify_merkle_proof(*leaf, &proof, root));
+                assert!(!verify_merkle_proof(B256::repeat_byte(0xff), &proof, root));
+            }
+            assert!(calculate_merkle_proof(leaves, len).is_none());
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_rejects_wrong_path() {
+        let leaves: Vec<B256> = (0..4).map(|i| B256::repeat_byte(i + 1)).collect();
+        let root = calculate_merkle_root(leaves.clone());
+
+        let mut proof = calculate_merkle_proof(leaves.clone(), 1).unwrap();
+        proof.path[0] = !proof.path[0];
+        assert!(!verify_merkle_proof(leaves[1], &proof, root));
+
+        proof.path.pop();
+        assert!(!verify_merkle_proof(leaves[1], &proof, root));
+    }
+}
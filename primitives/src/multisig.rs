@@ -0,0 +1,131 @@
+use std::collections::BTreeSet;
+
+use alloy_primitives::B256;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    encoding::{Decoder, Encoder, ItemType},
+    error::{DecodeError, MultisigError},
+    types::Address,
+};
+
+/// Max number of owners of a multisig account, which is also the max number of signatures
+pub const MAX_MULTISIG_OWNERS: usize = 16;
+/// Domain separator of multisig address derivation
+pub const MULTISIG_ADDRESS_DOMAIN: &[u8] = b"PINT_MULTISIG_V1";
+// World key under which the policy is stored in the multisig account's own storage.
+// Reserved: data transactions may not write it, or an account could store a policy of its own.
+pub const MULTISIG_POLICY_KEY: B256 = B256::ZERO;
+
+/// Address of the multisig account registered by `creator` with its transaction `nonce`
+pub fn multisig_address(creator: &Address, nonce: u64) -> Address {
+    let mut hasher = Sha256::new();
+    hasher.update(MULTISIG_ADDRESS_DOMAIN);
+    hasher.update(creator.get_addr());
+    hasher.update(nonce.to_be_bytes());
+    let hash = hasher.finalize();
+    let address: [u8; 20] = hash[hash.len() - 20..].try_into().unwrap();
+    Address::from_byte(address)
+}
+
+/// M-of-N spending policy of a multisig account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigPolicy {
+    pub threshold: u8,
+    pub owners: Vec<Address>,
+}
+
+impl MultisigPolicy {
+    pub fn new(threshold: u8, owners: Vec<Address>) -> Result<Self, MultisigError> {
+        let policy = Self { threshold, owners };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> Result<(), MultisigError> {
+        if self.owners.is_empty() || self.owners.len() > MAX_MULTISIG_OWNERS {
+            return Err(MultisigError::InvalidOwnerCount(self.owners.len()));
+        }
+        if self.threshold == 0 || self.threshold as usize > self.owners.len() {
+            return Err(MultisigError::InvalidThreshold(self.threshold));
+        }
+        let mut seen = BTreeSet::new();
+        for owner in self.owners.iter() {
+            if !seen.insert(owner) {
+                return Err(MultisigError::DuplicateOwner(*owner));
+            }
+        }
+        Ok(())
+    }
+
+    // Each owner counts once, however many times it signed
+    pub fn is_approved_by(&self, signers: &[Address]) -> bool {
+        let approvals: BTreeSet<&Address> = signers
+            .iter()
+            .filter(|signer| self.owners.contains(signer))
+            .collect();
+        approvals.len() >= self.threshold as usize
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder
+            .put_u8(self.threshold)
+            .put_u32(self.owners.len() as u32);
+        for owner in self.owners.iter() {
+            encoder.put_address(*owner);
+        }
+        encoder.finish(ItemType::MultisigPolicy)
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let mut payload = decoder.read_item(ItemType::MultisigPolicy)?;
+        let threshold = payload.read_u8()?;
+        let mut owners = Vec::new();
+        for _ in 0..payload.read_u32()? {
+            owners.push(payload.read_address()?);
+        }
+        payload.finish()?;
+        decoder.finish()?;
+        Ok(Self { threshold, owners })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multisig_policy() {
+        let owners: Vec<Address> = (0..3).map(|_| Address::random()).collect();
+        let policy = MultisigPolicy::new(2, owners.clone()).unwrap();
+        assert_eq!(MultisigPolicy::decode(&policy.encode()).unwrap(), policy);
+
+        assert!(!policy.is_approved_by(&[owners[0]]));
+        // The same owner twice is still one approval
+        assert!(!policy.is_approved_by(&[owners[0], owners[0]]));
+        assert!(!policy.is_approved_by(&[owners[0], Address::random()]));
+        assert!(policy.is_approved_by(&[owners[2], owners[0]]));
+
+        assert_eq!(
+            MultisigPolicy::new(0, owners.clone()),
+            Err(MultisigError::InvalidThreshold(0))
+        );
+        assert_eq!(
+            MultisigPolicy::new(4, owners.clone()),
+            Err(MultisigError::InvalidThreshold(4))
+        );
+        assert_eq!(
+            MultisigPolicy::new(1, vec![owners[0], owners[0]]),
+            Err(MultisigError::DuplicateOwner(owners[0]))
+        );
+        assert_eq!(
+            MultisigPolicy::new(1, Vec::new()),
+            Err(MultisigError::InvalidOwnerCount(0))
+        );
+
+        let creator = Address::random();
+        assert_ne!(multisig_address(&creator, 0), multisig_address(&creator, 1));
+    }
+}
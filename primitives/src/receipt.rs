@@ -0,0 +1,183 @@
+use anyhow::bail;
+use libmdbx::orm::{Decodable, Encodable};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    encoding::{Decoder, Encoder, ItemType},
+    error::DecodeError,
+    merkle::calculate_merkle_root,
+    types::{B256, TxHash},
+};
+
+// Version of the ReceiptError code table. Codes are part of the receipts root, so a code
+// is never renumbered or reused: a changed table comes with a new version.
+pub const RECEIPT_ERRORS_VERSION: u8 = 1;
+
+/// Why a transaction failed, as recorded in its receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ReceiptError {
+    InvalidChainId = 1,
+    SenderHasNotEnoughBalance = 2,
+    SenderHasNoAccount = 3,
+    InvalidNonce = 4,
+    InvalidDataLength = 5,
+    InvalidOutputCount = 6,
+    InvalidMultisigPolicy = 7,
+    MultisigAlreadyExists = 8,
+    MultisigNotFound = 9,
+    MultisigThresholdNotMet = 10,
+    ReservedDataKey = 11,
+}
+
+impl ReceiptError {
+    pub fn code(&self) -> u32 {
+        *self as u32
+    }
+}
+
+impl TryFrom<u32> for ReceiptError {
+    type Error = DecodeError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::InvalidChainId),
+            2 => Ok(Self::SenderHasNotEnoughBalance),
+            3 => Ok(Self::SenderHasNoAccount),
+            4 => Ok(Self::InvalidNonce),
+            5 => Ok(Self::InvalidDataLength),
+            6 => Ok(Self::InvalidOutputCount),
+            7 => Ok(Self::InvalidMultisigPolicy),
+            8 => Ok(Self::MultisigAlreadyExists),
+            9 => Ok(Self::MultisigNotFound),
+            10 => Ok(Self::MultisigThresholdNotMet),
+            11 => Ok(Self::ReservedDataKey),
+            _ => Err(DecodeError::InvalidReceiptError(value)),
+        }
+    }
+}
+
+/// Outcome of one transaction of a block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionReceipt {
+    pub tx_hash: TxHash,
+    // Position of the transaction in the block body
+    pub index: u32,
+    pub success: bool,
+    // Fee paid to the proposer, zero if the transaction failed
+    pub fee: u128,
+    // Why the transaction failed. None on success.
+    pub error: Option<ReceiptError>,
+}
+
+impl TransactionReceipt {
+    pub fn encode(&self) -> Vec<u8> {
+        Encoder::new()
+            .put_b256(self.tx_hash.hash())
+            .put_u32(self.index)
+            .put_u8(self.success as u8)
+            .put_u128(self.fee)
+            .put_u8(RECEIPT_ERRORS_VERSION)
+            .put_u32(self.error.map_or(0, |error| error.code()))
+            .finish(ItemType::Receipt)
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let mut payload = decoder.read_item(ItemType::Receipt)?;
+        let tx_hash = TxHash::from(payload.read_b256()?);
+        let index = payload.read_u32()?;
+        let success = payload.read_u8()? != 0;
+        let fee = payload.read_u128()?;
+        let version = payload.read_u8()?;
+        if version != RECEIPT_ERRORS_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let error = match payload.read_u32()? {
+            0 => None,
+            code => Some(ReceiptError::try_from(code)?),
+        };
+        payload.finish()?;
+        decoder.finish()?;
+        Ok(Self {
+            tx_hash,
+            index,
+            success,
+            fee,
+            error,
+        })
+    }
+
+    pub fn calculate_hash(&self) -> B256 {
+        B256::from_slice(&Sha256::digest(self.encode()))
+    }
+}
+
+/// Merkle root over the receipt hashes, in block order
+pub fn calculate_receipts_root(receipts: &[TransactionReceipt]) -> B256 {
+    calculate_merkle_root(
+        receipts
+            .iter()
+            .map(|receipt| receipt.calculate_hash())
+            .collect(),
+    )
+}
+
+impl Encodable for TransactionReceipt {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        TransactionReceipt::encode(&self)
+    }
+}
+
+impl Decodable for TransactionReceipt {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match TransactionReceipt::decode(b) {
+            Ok(receipt) => Ok(receipt),
+            Err(e) => bail!("e: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_encode_and_decode() {
+        let success = TransactionReceipt {
+            tx_hash: TxHash::from(B256::repeat_byte(1)),
+            index: 0,
+            success: true,
+            fee: 10,
+            error: None,
+        };
+        let failure = TransactionReceipt {
+            tx_hash: TxHash::from(B256::repeat_byte(2)),
+            index: 1,
+            success: false,
+            fee: 0,
+            error: Some(ReceiptError::SenderHasNotEnoughBalance),
+        };
+        for receipt in [&success, &failure] {
+            assert_eq!(
+                &TransactionReceipt::decode(&receipt.encode()).unwrap(),
+                receipt
+            );
+        }
+
+        // The code, not the name, is encoded
+        let mut unknown = TransactionReceipt::encode(&failure);
+        assert_eq!(&unknown[unknown.len() - 5..], &[1, 0, 0, 0, 2]);
+        *unknown.last_mut().unwrap() = 0xff;
+        assert!(matches!(
+            TransactionReceipt::decode(&unknown),
+            Err(DecodeError::InvalidReceiptError(0xff))
+        ));
+
+        let root = calculate_receipts_root(&[success.clone(), failure.clone()]);
+        assert_ne!(root, calculate_receipts_root(&[failure, success]));
+        assert_eq!(calculate_receipts_root(&[]), B256::ZERO);
+    }
+}
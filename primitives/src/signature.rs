@@ -71,16 +71,59 @@ impl Signature {
     }
 }
 
-impl Into<k256::ecdsa::Signature> for Signature {
-    fn into(self) -> k256::ecdsa::Signature {
-        let r_bytes: [u8; 32] = self.r.to_be_bytes();
-        let s_bytes: [u8; 32] = self.s.to_be_bytes();
+// Fails on r or s out of range instead of panicking, and rejects high-s.
+// For every (r, s) the signature (r, n - s) is valid as well, so only the low-s form is accepted.
+impl TryFrom<&Signature> for k256::ecdsa::Signature {
+    type Error = SignatureError;
+
+    fn try_from(signature: &Signature) -> Result<Self, Self::Error> {
+        let r_bytes: [u8; 32] = signature.r.to_be_bytes();
+        let s_bytes: [u8; 32] = signature.s.to_be_bytes();
 
         let mut sig_bytes: [u8; 64] = [0u8; 64];
         sig_bytes[0..32].copy_from_slice(&r_bytes);
         sig_bytes[32..64].copy_from_slice(&s_bytes);
 
-        let sig = k256::ecdsa::Signature::from_slice(&sig_bytes).unwrap();
-        sig
+        let sig = k256::ecdsa::Signature::from_slice(&sig_bytes)
+            .map_err(|_| SignatureError::InvalidScalar)?;
+        if sig.normalize_s().is_some() {
+            return Err(SignatureError::HighS);
+        }
+        Ok(sig)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::SigningKey;
+
+    use super::*;
+
+    #[test]
+    fn test_signature_conversion_is_strict() {
+        let signing_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let (sig, recid) = signing_key.sign_recoverable(b"pint").unwrap();
+        let signature = Signature::from_sig(sig, recid);
+        assert_eq!(k256::ecdsa::Signature::try_from(&signature).unwrap(), sig);
+
+        // Zero and out of range scalars
+        assert!(matches!(
+            k256::ecdsa::Signature::try_from(&Signature::dummy()),
+            Err(SignatureError::InvalidScalar)
+        ));
+        let mut out_of_range = signature.clone();
+        out_of_range.r = U256::MAX;
+        assert!(matches!(
+            k256::ecdsa::Signature::try_from(&out_of_range),
+            Err(SignatureError::InvalidScalar)
+        ));
+
+        // Same signature with s negated
+        let high_s = k256::ecdsa::Signature::from_scalars(sig.r(), -*sig.s()).unwrap();
+        let high_s = Signature::from_sig(high_s, recid);
+        assert!(matches!(
+            k256::ecdsa::Signature::try_from(&high_s),
+            Err(SignatureError::HighS)
+        ));
+    }
+}
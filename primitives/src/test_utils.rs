@@ -0,0 +1,36 @@
+use k256::ecdsa::{RecoveryId, Signature as ECDSASig, SigningKey};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    signature::Signature,
+    transaction::{Recovered, SignedTransaction, Transaction},
+};
+
+// Test keys are derived from a seed, like the dev accounts ("pint", "chain")
+fn signing_key(seed: &str) -> SigningKey {
+    SigningKey::from_bytes(&Sha256::digest(seed.as_bytes())).unwrap()
+}
+
+/// Signs `tx` with the key of `seed`
+pub fn sign(seed: &str, tx: Transaction) -> Recovered {
+    sign_multi(&[seed], tx)
+}
+
+/// First seed signs, the others cosign
+pub fn sign_multi(seeds: &[&str], tx: Transaction) -> Recovered {
+    let tx_hash = tx.encode_for_signing();
+    let mut signatures: Vec<Signature> = seeds
+        .iter()
+        .map(|seed| {
+            let (sig, recid): (ECDSASig, RecoveryId) = signing_key(seed)
+                .sign_digest_recoverable(Sha256::new_with_prefix(tx_hash.hash()))
+                .unwrap();
+            Signature::from_sig(sig, recid)
+        })
+        .collect();
+    let signature = signatures.remove(0);
+    SignedTransaction::new(tx, signature, tx_hash)
+        .with_cosignatures(signatures)
+        .into_recovered()
+        .unwrap()
+}
@@ -1,112 +1,576 @@
+use crate::encoding::{Decoder, Encoder, ItemType};
 use crate::error::DecodeError;
 use crate::error::RecoveryError;
+use crate::multisig::{MAX_MULTISIG_OWNERS, MultisigPolicy};
 use crate::{
     signature::Signature,
     types::{Address, B256, ChainId, TxHash, U256},
 };
 use anyhow::bail;
-use k256::EncodedPoint;
 use k256::ecdsa::RecoveryId;
 use k256::ecdsa::VerifyingKey;
 use libmdbx::orm::Decodable;
 use libmdbx::orm::Encodable;
 use sha2::Digest;
 use sha2::Sha256;
+use std::fmt;
+
+/// Domain separator of the transaction signing hash
+pub const TX_SIGNING_DOMAIN: &[u8] = b"PINT_TX_SIGNING_V1";
+/// Max length of the payload of a data transaction
+pub const MAX_TX_DATA_LEN: usize = 1024;
+/// Max number of outputs of a batch transfer
+pub const MAX_BATCH_OUTPUTS: usize = 64;
+
+/// Transaction kind. First byte of the transaction payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum TxType {
+    Transfer = 0x00,
+    Data = 0x01,
+    BatchTransfer = 0x02,
+    RegisterMultisig = 0x03,
+    MultisigTransfer = 0x04,
+}
+
+impl TryFrom<u8> for TxType {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(Self::Transfer),
+            0x01 => Ok(Self::Data),
+            0x02 => Ok(Self::BatchTransfer),
+            0x03 => Ok(Self::RegisterMultisig),
+            0x04 => Ok(Self::MultisigTransfer),
+            _ => Err(DecodeError::InvalidTxType(value)),
+        }
+    }
+}
+
+impl fmt::Display for TxType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxType::Transfer => write!(f, "Transfer"),
+            TxType::Data => write!(f, "Data"),
+            TxType::BatchTransfer => write!(f, "BatchTransfer"),
+            TxType::RegisterMultisig => write!(f, "RegisterMultisig"),
+            TxType::MultisigTransfer => write!(f, "MultisigTransfer"),
+        }
+    }
+}
 
 pub trait Tx {
+    fn tx_type(&self) -> TxType;
     fn chain_id(&self) -> ChainId;
     fn nonce(&self) -> u64;
-    fn to(&self) -> Address;
+    // Recipient of the value, if the transaction has one
+    fn to(&self) -> Option<Address>;
     fn fee(&self) -> u128;
+    // Total value which leaves the sender (fee excluded)
     fn value(&self) -> U256;
+    // Attached data, signed with the rest of the transaction
+    fn data(&self) -> &[u8];
 }
 
-/// Raw Transaction
-#[derive(Debug, Clone)]
-pub struct Transaction {
+/// Value transfer to one account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxTransfer {
+    pub chain_id: ChainId,
+    pub nonce: u64,
+    pub to: Address,
+    pub fee: u128,
+    pub value: U256,
+    // Optional memo (e.g. a deposit reference). Empty if unused.
+    pub data: Vec<u8>,
+}
+
+impl Tx for TxTransfer {
+    fn tx_type(&self) -> TxType {
+        TxType::Transfer
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn to(&self) -> Option<Address> {
+        Some(self.to)
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    fn value(&self) -> U256 {
+        self.value
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Writes data under `key` in the sender's World storage. Moves no value.
+/// Empty data removes the key. MULTISIG_POLICY_KEY is reserved and refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxData {
+    pub chain_id: ChainId,
+    pub nonce: u64,
+    pub fee: u128,
+    pub key: B256,
+    pub data: Vec<u8>,
+}
+
+impl Tx for TxData {
+    fn tx_type(&self) -> TxType {
+        TxType::Data
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn to(&self) -> Option<Address> {
+        None
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    fn value(&self) -> U256 {
+        U256::ZERO
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// One recipient of a batch transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferOutput {
+    pub to: Address,
+    pub value: U256,
+}
+
+/// Value transfer to many accounts under one nonce and fee.
+/// Applied atomically: all outputs or none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxBatchTransfer {
     pub chain_id: ChainId,
     pub nonce: u64,
+    pub fee: u128,
+    // Optional memo. Empty if unused.
+    pub data: Vec<u8>,
+    pub outputs: Vec<TransferOutput>,
+}
+
+impl Tx for TxBatchTransfer {
+    fn tx_type(&self) -> TxType {
+        TxType::BatchTransfer
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn to(&self) -> Option<Address> {
+        None
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    // Saturates, so an overflowing batch can never be afforded
+    fn value(&self) -> U256 {
+        self.outputs
+            .iter()
+            .fold(U256::ZERO, |total, output| total.saturating_add(output.value))
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Creates a multisig account at `multisig_address(sender, nonce)`.
+/// The sender only pays the fee, it doesn't become an owner unless listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxRegisterMultisig {
+    pub chain_id: ChainId,
+    pub nonce: u64,
+    pub fee: u128,
+    pub threshold: u8,
+    pub owners: Vec<Address>,
+}
+
+impl TxRegisterMultisig {
+    pub fn policy(&self) -> MultisigPolicy {
+        MultisigPolicy {
+            threshold: self.threshold,
+            owners: self.owners.clone(),
+        }
+    }
+}
+
+impl Tx for TxRegisterMultisig {
+    fn tx_type(&self) -> TxType {
+        TxType::RegisterMultisig
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn to(&self) -> Option<Address> {
+        None
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    fn value(&self) -> U256 {
+        U256::ZERO
+    }
+
+    fn data(&self) -> &[u8] {
+        &[]
+    }
+}
+
+/// Value transfer out of a multisig account.
+/// `nonce`, `fee` and `value` are those of `from`. Owners sign it like any other
+/// transaction, their signatures are the signature and cosignatures of the SignedTransaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxMultisigTransfer {
+    pub chain_id: ChainId,
+    pub nonce: u64,
+    pub from: Address,
     pub to: Address,
     pub fee: u128,
     pub value: U256,
+    // Optional memo. Empty if unused.
+    pub data: Vec<u8>,
+}
+
+impl Tx for TxMultisigTransfer {
+    fn tx_type(&self) -> TxType {
+        TxType::MultisigTransfer
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn to(&self) -> Option<Address> {
+        Some(self.to)
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    fn value(&self) -> U256 {
+        self.value
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Raw Transaction (typed envelope)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transaction {
+    Transfer(TxTransfer),
+    Data(TxData),
+    BatchTransfer(TxBatchTransfer),
+    RegisterMultisig(TxRegisterMultisig),
+    MultisigTransfer(TxMultisigTransfer),
 }
 
 impl Transaction {
-    pub const fn raw_len() -> usize {
-        8 + 16 + 20 + 16 + 32
+    // Domain prefix || canonical encoding.
+    // chain_id is part of the encoding, so a signature is only valid on one chain.
+    pub fn signing_preimage(&self) -> Vec<u8> {
+        [TX_SIGNING_DOMAIN, &self.encode()].concat()
     }
+
     pub fn encode_for_signing(&self) -> TxHash {
-        let mut hasher = Sha256::new();
-        hasher.update(self.chain_id.to_be_bytes());
-        hasher.update(self.nonce.to_be_bytes());
-        hasher.update(self.to.get_addr());
-        hasher.update(self.fee.to_be_bytes());
-        hasher.update(self.value.to_string().as_bytes());
-        TxHash::from(B256::from_slice(&hasher.finalize()))
-    }
-
-    fn encode(&self) -> Vec<u8> {
-        let mut arr: [u8; 84] = [0u8; 84];
-        arr[0..8].copy_from_slice(&self.chain_id.to_be_bytes());
-        arr[8..16].copy_from_slice(&self.nonce.to_be_bytes());
-        arr[16..36].copy_from_slice(&self.to.get_addr());
-        arr[36..52].copy_from_slice(&self.fee.to_be_bytes());
-        arr[52..].copy_from_slice(&self.value.to_be_bytes::<32>());
-        arr.to_vec()
-    }
-
-    pub fn raw_decode(data: &[u8]) -> Result<(Self, usize), DecodeError> {
-        let raw: [u8; 84] = data[0..84].try_into()?;
-        let chain_id: ChainId = ChainId::from_be_bytes(raw[0..8].try_into()?);
-        let nonce: u64 = u64::from_be_bytes(raw[8..16].try_into()?);
-        let to = match Address::from_hex(hex::encode(&raw[16..36])) {
-            Ok(addr) => addr,
-            Err(e) => return Err(DecodeError::InvalidAddress(e)),
+        let hash = Sha256::digest(self.signing_preimage());
+        TxHash::from(B256::from_slice(&hash))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.put_u8(self.tx_type() as u8);
+        match self {
+            Transaction::Transfer(tx) => {
+                encoder
+                    .put_u64(tx.chain_id)
+                    .put_u64(tx.nonce)
+                    .put_address(tx.to)
+                    .put_u128(tx.fee)
+                    .put_u256(tx.value)
+                    .put_bytes(&tx.data);
+            }
+            Transaction::Data(tx) => {
+                encoder
+                    .put_u64(tx.chain_id)
+                    .put_u64(tx.nonce)
+                    .put_u128(tx.fee)
+                    .put_b256(tx.key)
+                    .put_bytes(&tx.data);
+            }
+            Transaction::BatchTransfer(tx) => {
+                encoder
+                    .put_u64(tx.chain_id)
+                    .put_u64(tx.nonce)
+                    .put_u128(tx.fee)
+                    .put_bytes(&tx.data)
+                    .put_u32(tx.outputs.len() as u32);
+                for output in tx.outputs.iter() {
+                    encoder.put_address(output.to).put_u256(output.value);
+                }
+            }
+            Transaction::RegisterMultisig(tx) => {
+                encoder
+                    .put_u64(tx.chain_id)
+                    .put_u64(tx.nonce)
+                    .put_u128(tx.fee)
+                    .put_u8(tx.threshold)
+                    .put_u32(tx.owners.len() as u32);
+                for owner in tx.owners.iter() {
+                    encoder.put_address(*owner);
+                }
+            }
+            Transaction::MultisigTransfer(tx) => {
+                encoder
+                    .put_u64(tx.chain_id)
+                    .put_u64(tx.nonce)
+                    .put_address(tx.from)
+                    .put_address(tx.to)
+                    .put_u128(tx.fee)
+                    .put_u256(tx.value)
+                    .put_bytes(&tx.data);
+            }
+        }
+        encoder.finish(ItemType::Transaction)
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let tx = Self::decode_from(&mut decoder)?;
+        decoder.finish()?;
+        Ok(tx)
+    }
+
+    pub(crate) fn decode_from(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+        let mut payload = decoder.read_item(ItemType::Transaction)?;
+        let tx = match TxType::try_from(payload.read_u8()?)? {
+            TxType::Transfer => Transaction::Transfer(TxTransfer {
+                chain_id: payload.read_u64()?,
+                nonce: payload.read_u64()?,
+                to: payload.read_address()?,
+                fee: payload.read_u128()?,
+                value: payload.read_u256()?,
+                data: payload.read_bytes()?.to_vec(),
+            }),
+            TxType::Data => Transaction::Data(TxData {
+                chain_id: payload.read_u64()?,
+                nonce: payload.read_u64()?,
+                fee: payload.read_u128()?,
+                key: payload.read_b256()?,
+                data: payload.read_bytes()?.to_vec(),
+            }),
+            TxType::BatchTransfer => {
+                let chain_id = payload.read_u64()?;
+                let nonce = payload.read_u64()?;
+                let fee = payload.read_u128()?;
+                let data = payload.read_bytes()?.to_vec();
+                let count = payload.read_u32()?;
+                // count comes from the wire, so don't preallocate with it
+                let mut outputs = Vec::new();
+                for _ in 0..count {
+                    outputs.push(TransferOutput {
+                        to: payload.read_address()?,
+                        value: payload.read_u256()?,
+                    });
+                }
+                Transaction::BatchTransfer(TxBatchTransfer {
+                    chain_id,
+                    nonce,
+                    fee,
+                    outputs,
+                    data,
+                })
+            }
+            TxType::RegisterMultisig => {
+                let chain_id = payload.read_u64()?;
+                let nonce = payload.read_u64()?;
+                let fee = payload.read_u128()?;
+                let threshold = payload.read_u8()?;
+                let count = payload.read_u32()?;
+                let mut owners = Vec::new();
+                for _ in 0..count {
+                    owners.push(payload.read_address()?);
+                }
+                Transaction::RegisterMultisig(TxRegisterMultisig {
+                    chain_id,
+                    nonce,
+                    fee,
+                    threshold,
+                    owners,
+                })
+            }
+            TxType::MultisigTransfer => Transaction::MultisigTransfer(TxMultisigTransfer {
+                chain_id: payload.read_u64()?,
+                nonce: payload.read_u64()?,
+                from: payload.read_address()?,
+                to: payload.read_address()?,
+                fee: payload.read_u128()?,
+                value: payload.read_u256()?,
+                data: payload.read_bytes()?.to_vec(),
+            }),
         };
-        let fee: u128 = u128::from_be_bytes(raw[36..52].try_into()?);
-        let value: U256 = U256::from_be_bytes::<32>(raw[52..84].try_into()?);
-
-        Ok((
-            Self {
-                chain_id,
-                nonce,
-                to,
-                fee,
-                value,
-            },
-            84 as usize,
-        ))
-    }
-
-    fn into_signed(self, signature: Signature) -> SignedTransaction {
+        payload.finish()?;
+        Ok(tx)
+    }
+
+    fn into_signed(self, signature: Signature, cosignatures: Vec<Signature>) -> SignedTransaction {
         let hash = self.encode_for_signing();
         SignedTransaction {
             tx: self,
             signature,
+            cosignatures,
             hash,
         }
     }
 }
 
 impl Tx for Transaction {
+    fn tx_type(&self) -> TxType {
+        match self {
+            Transaction::Transfer(tx) => tx.tx_type(),
+            Transaction::Data(tx) => tx.tx_type(),
+            Transaction::BatchTransfer(tx) => tx.tx_type(),
+            Transaction::RegisterMultisig(tx) => tx.tx_type(),
+            Transaction::MultisigTransfer(tx) => tx.tx_type(),
+        }
+    }
+
     fn chain_id(&self) -> ChainId {
-        self.chain_id
+        match self {
+            Transaction::Transfer(tx) => tx.chain_id(),
+            Transaction::Data(tx) => tx.chain_id(),
+            Transaction::BatchTransfer(tx) => tx.chain_id(),
+            Transaction::RegisterMultisig(tx) => tx.chain_id(),
+            Transaction::MultisigTransfer(tx) => tx.chain_id(),
+        }
     }
 
     fn nonce(&self) -> u64 {
-        self.nonce
+        match self {
+            Transaction::Transfer(tx) => tx.nonce(),
+            Transaction::Data(tx) => tx.nonce(),
+            Transaction::BatchTransfer(tx) => tx.nonce(),
+            Transaction::RegisterMultisig(tx) => tx.nonce(),
+            Transaction::MultisigTransfer(tx) => tx.nonce(),
+        }
     }
 
-    fn to(&self) -> Address {
-        self.to
+    fn to(&self) -> Option<Address> {
+        match self {
+            Transaction::Transfer(tx) => tx.to(),
+            Transaction::Data(tx) => tx.to(),
+            Transaction::BatchTransfer(tx) => tx.to(),
+            Transaction::RegisterMultisig(tx) => tx.to(),
+            Transaction::MultisigTransfer(tx) => tx.to(),
+        }
     }
 
     fn fee(&self) -> u128 {
-        self.fee
+        match self {
+            Transaction::Transfer(tx) => tx.fee(),
+            Transaction::Data(tx) => tx.fee(),
+            Transaction::BatchTransfer(tx) => tx.fee(),
+            Transaction::RegisterMultisig(tx) => tx.fee(),
+            Transaction::MultisigTransfer(tx) => tx.fee(),
+        }
     }
 
     fn value(&self) -> U256 {
-        self.value
+        match self {
+            Transaction::Transfer(tx) => tx.value(),
+            Transaction::Data(tx) => tx.value(),
+            Transaction::BatchTransfer(tx) => tx.value(),
+            Transaction::RegisterMultisig(tx) => tx.value(),
+            Transaction::MultisigTransfer(tx) => tx.value(),
+        }
+    }
+
+    fn data(&self) -> &[u8] {
+        match self {
+            Transaction::Transfer(tx) => tx.data(),
+            Transaction::Data(tx) => tx.data(),
+            Transaction::BatchTransfer(tx) => tx.data(),
+            Transaction::RegisterMultisig(tx) => tx.data(),
+            Transaction::MultisigTransfer(tx) => tx.data(),
+        }
+    }
+}
+
+impl From<TxTransfer> for Transaction {
+    fn from(tx: TxTransfer) -> Self {
+        Transaction::Transfer(tx)
+    }
+}
+
+impl From<TxData> for Transaction {
+    fn from(tx: TxData) -> Self {
+        Transaction::Data(tx)
+    }
+}
+
+impl From<TxBatchTransfer> for Transaction {
+    fn from(tx: TxBatchTransfer) -> Self {
+        Transaction::BatchTransfer(tx)
+    }
+}
+
+impl From<TxRegisterMultisig> for Transaction {
+    fn from(tx: TxRegisterMultisig) -> Self {
+        Transaction::RegisterMultisig(tx)
+    }
+}
+
+impl From<TxMultisigTransfer> for Transaction {
+    fn from(tx: TxMultisigTransfer) -> Self {
+        Transaction::MultisigTransfer(tx)
     }
 }
 
@@ -115,6 +579,8 @@ impl Tx for Transaction {
 pub struct SignedTransaction {
     pub tx: Transaction,
     pub signature: Signature,
+    // Signatures of the other multisig owners. Empty for every other transaction type.
+    pub cosignatures: Vec<Signature>,
     pub hash: TxHash,
 }
 
@@ -127,35 +593,61 @@ impl SignedTransaction {
         Self {
             tx,
             signature,
+            cosignatures: Vec::new(),
             hash,
         }
     }
 
-    pub fn encode(&self) -> Vec<u8> {
-        let tx_arr = self.tx.encode();
-        let sig_arr: Vec<u8> = self.signature.as_bytes().to_vec();
-        [tx_arr, sig_arr].concat()
+    pub fn with_cosignatures(mut self, cosignatures: Vec<Signature>) -> Self {
+        self.cosignatures = cosignatures;
+        self
     }
 
-    pub fn decode(raw: &[u8]) -> Result<(Self, usize), crate::error::DecodeError> {
-        let size = raw.len();
-        let (tx, tx_size) = Transaction::raw_decode(&raw)?;
-
-        const SIG_RAW_LEN: usize = Signature::raw_len();
-        if size < tx_size + SIG_RAW_LEN {
-            return Err(DecodeError::TooShortRawData(raw.to_vec()));
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder
+            .put_item(&self.tx.encode())
+            .put_fixed(&self.signature.as_bytes())
+            .put_u32(self.cosignatures.len() as u32);
+        for cosignature in self.cosignatures.iter() {
+            encoder.put_fixed(&cosignature.as_bytes());
         }
+        encoder.finish(ItemType::SignedTransaction)
+    }
+
+    /// Strict decoding: `raw` must hold exactly one signed transaction.
+    pub fn decode(raw: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let signed = Self::decode_from(&mut decoder)?;
+        decoder.finish()?;
+        Ok((signed, decoder.position()))
+    }
 
-        let sig_raw: [u8; SIG_RAW_LEN] = raw[tx_size..tx_size + SIG_RAW_LEN].try_into()?;
+    pub(crate) fn decode_from(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+        let mut payload = decoder.read_item(ItemType::SignedTransaction)?;
+        let tx = Transaction::decode_from(&mut payload)?;
+        let sig_raw: [u8; Signature::raw_len()] =
+            payload.read_fixed(Signature::raw_len())?.try_into()?;
         let signature = Signature::raw_decode(&sig_raw)?;
-        let signed = tx.into_signed(signature);
-        Ok((signed, size))
+        let count = payload.read_u32()?;
+        let mut cosignatures = Vec::new();
+        for _ in 0..count {
+            let sig_raw: [u8; Signature::raw_len()] =
+                payload.read_fixed(Signature::raw_len())?.try_into()?;
+            cosignatures.push(Signature::raw_decode(&sig_raw)?);
+        }
+        payload.finish()?;
+        Ok(tx.into_signed(signature, cosignatures))
     }
 
     pub fn recover_signer(&self) -> Result<Address, RecoveryError> {
-        let y_parity: u8 = if self.signature.y_parity() { 1 } else { 0 };
+        self.recover_address(&self.signature)
+    }
+
+    fn recover_address(&self, signature: &Signature) -> Result<Address, RecoveryError> {
+        let y_parity: u8 = if signature.y_parity() { 1 } else { 0 };
         let recid = RecoveryId::from_byte(y_parity).unwrap(); // safe!
-        let signature: k256::ecdsa::Signature = self.signature.clone().into();
+        let signature = k256::ecdsa::Signature::try_from(signature)?;
         let hash = self.hash;
 
         let recovered_key = match VerifyingKey::recover_from_digest(
@@ -167,23 +659,44 @@ impl SignedTransaction {
             Err(_) => return Err(RecoveryError::RecoveryFromDigestError),
         };
 
-        let recovered_pubkey_uncompressed: EncodedPoint = recovered_key.to_encoded_point(false);
-        let recovered_pubkey_bytes = recovered_pubkey_uncompressed.as_bytes();
-        let recovered_address: [u8; 20] = recovered_pubkey_bytes
-            [recovered_pubkey_bytes.len() - 20..]
-            .try_into()
-            .expect("slice is not 20 bytes");
-
-        Ok(Address::from_byte(recovered_address))
+        Ok(Address::from_public_key(&recovered_key))
     }
 
+    // The signer of a multisig transfer is the multisig account, which owns the nonce and the balance.
+    // Whether the owners who signed meet the threshold is checked against the stored policy.
     pub fn into_recovered(self) -> Result<Recovered, RecoveryError> {
-        let signer = self.recover_signer()?;
-        Ok(Recovered { tx: self, signer })
+        let mut approvals = vec![self.recover_signer()?];
+        let signer = match &self.tx {
+            Transaction::MultisigTransfer(tx) => {
+                let count = 1 + self.cosignatures.len();
+                if count > MAX_MULTISIG_OWNERS {
+                    return Err(RecoveryError::TooManySignatures(count));
+                }
+                for cosignature in self.cosignatures.iter() {
+                    approvals.push(self.recover_address(cosignature)?);
+                }
+                tx.from
+            }
+            _ => {
+                if !self.cosignatures.is_empty() {
+                    return Err(RecoveryError::UnexpectedCosignatures);
+                }
+                approvals[0]
+            }
+        };
+        Ok(Recovered {
+            tx: self,
+            signer,
+            approvals,
+        })
     }
 }
 
 impl Tx for SignedTransaction {
+    fn tx_type(&self) -> TxType {
+        self.transaction().tx_type()
+    }
+
     fn chain_id(&self) -> ChainId {
         self.transaction().chain_id()
     }
@@ -192,7 +705,7 @@ impl Tx for SignedTransaction {
         self.transaction().nonce()
     }
 
-    fn to(&self) -> Address {
+    fn to(&self) -> Option<Address> {
         self.transaction().to()
     }
 
@@ -203,6 +716,10 @@ impl Tx for SignedTransaction {
     fn value(&self) -> U256 {
         self.transaction().value()
     }
+
+    fn data(&self) -> &[u8] {
+        self.transaction().data()
+    }
 }
 
 // For db encode, decode
@@ -210,36 +727,16 @@ impl Encodable for SignedTransaction {
     type Encoded = Vec<u8>;
 
     fn encode(self) -> Self::Encoded {
-        let tx_arr = self.tx.encode();
-        let sig_arr: Vec<u8> = self.signature.as_bytes().to_vec();
-        [tx_arr, sig_arr].concat()
+        SignedTransaction::encode(&self)
     }
 }
 
 impl Decodable for SignedTransaction {
     fn decode(raw: &[u8]) -> anyhow::Result<Self> {
-        let size = raw.len();
-        let (tx, tx_size) = match Transaction::raw_decode(&raw) {
-            Ok(res) => res,
-            Err(e) => {
-                bail!("e: {:?}", e);
-            }
-        };
-
-        const SIG_RAW_LEN: usize = Signature::raw_len();
-        if size < tx_size + SIG_RAW_LEN {
-            bail!("Too short raw data!. {:?}", size);
+        match SignedTransaction::decode(raw) {
+            Ok((signed, _)) => Ok(signed),
+            Err(e) => bail!("e: {:?}", e),
         }
-
-        let sig_raw: [u8; SIG_RAW_LEN] = raw[tx_size..tx_size + SIG_RAW_LEN].try_into()?;
-        let signature = match Signature::raw_decode(&sig_raw) {
-            Ok(sig) => sig,
-            Err(e) => {
-                bail!("e: {:?}", e);
-            }
-        };
-        let signed = tx.into_signed(signature);
-        Ok(signed)
     }
 }
 
@@ -248,6 +745,8 @@ impl Decodable for SignedTransaction {
 pub struct Recovered {
     tx: SignedTransaction,
     signer: Address,
+    // Addresses recovered from every signature the transaction carries
+    approvals: Vec<Address>,
 }
 
 impl Recovered {
@@ -259,12 +758,20 @@ impl Recovered {
         self.signer
     }
 
+    pub fn approvals(&self) -> &[Address] {
+        &self.approvals
+    }
+
     pub fn hash(&self) -> TxHash {
         self.tx().hash
     }
 }
 
 impl Tx for Recovered {
+    fn tx_type(&self) -> TxType {
+        self.tx().tx_type()
+    }
+
     fn chain_id(&self) -> ChainId {
         self.tx().chain_id()
     }
@@ -273,7 +780,7 @@ impl Tx for Recovered {
         self.tx().nonce()
     }
 
-    fn to(&self) -> Address {
+    fn to(&self) -> Option<Address> {
         self.tx().to()
     }
 
@@ -284,11 +791,17 @@ impl Tx for Recovered {
     fn value(&self) -> U256 {
         self.tx().value()
     }
+
+    fn data(&self) -> &[u8] {
+        self.tx().data()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::encoding::ITEM_HEADER_LEN;
+    use crate::error::SignatureError;
     use k256::{
         EncodedPoint,
         ecdsa::{RecoveryId, Signature as ECDSASig, SigningKey},
@@ -320,13 +833,14 @@ mod tests {
         let receiver = Address::from_byte(receiver.try_into().unwrap());
         dbg!(receiver.get_addr_hex());
 
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce: 2,
             to: receiver,
             fee: 5,
             value: U256::from(1000),
-        };
+            data: Vec::new(),
+        });
 
         let tx_hash = tx.encode_for_signing();
         let digest = Sha256::new_with_prefix(tx_hash.hash());
@@ -343,4 +857,228 @@ mod tests {
 
         assert_eq!(sender, recovered_sender);
     }
+
+    #[test]
+    fn test_primitives_decode_rejects_trailing_bytes() {
+        let tx = Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 1,
+            value: U256::from(1),
+            data: Vec::new(),
+        });
+        let signed = SignedTransaction::new(tx.clone(), Signature::dummy(), tx.encode_for_signing());
+
+        let mut encoded = signed.encode();
+        assert!(SignedTransaction::decode(&encoded).is_ok());
+        assert!(SignedTransaction::decode(&encoded[..encoded.len() - 1]).is_err());
+
+        encoded.push(0);
+        assert!(matches!(
+            SignedTransaction::decode(&encoded),
+            Err(DecodeError::TrailingBytes(1))
+        ));
+    }
+
+    #[test]
+    fn test_primitives_signature_is_bound_to_chain_id() {
+        let (signing_key, sender) = create_key_pairs("pint".as_bytes());
+        let sender = Address::from_byte(sender.try_into().unwrap());
+
+        let tx = Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 5,
+            value: U256::from(1000),
+            data: Vec::new(),
+        });
+        let tx_hash = tx.encode_for_signing();
+        let (sig, recid): (ECDSASig, RecoveryId) = signing_key
+            .sign_digest_recoverable(Sha256::new_with_prefix(tx_hash.hash()))
+            .unwrap();
+        let signature = Signature::from_sig(sig, recid);
+
+        let signed = SignedTransaction::new(tx.clone(), signature.clone(), tx_hash);
+        assert_eq!(signed.recover_signer().unwrap(), sender);
+
+        // Same signature replayed on another chain
+        let mut replayed = tx;
+        if let Transaction::Transfer(tx) = &mut replayed {
+            tx.chain_id = 1;
+        }
+        let replayed_hash = replayed.encode_for_signing();
+        assert_ne!(replayed_hash, tx_hash);
+        let replayed = SignedTransaction::new(replayed, signature, replayed_hash);
+        assert_ne!(replayed.recover_signer().ok(), Some(sender));
+    }
+
+    #[test]
+    fn test_primitives_encode_and_decode_data_transaction() {
+        let tx = Transaction::Data(TxData {
+            chain_id: 0,
+            nonce: 1,
+            fee: 3,
+            key: B256::repeat_byte(7),
+            data: b"pint".to_vec(),
+        });
+        assert_eq!(tx.tx_type(), TxType::Data);
+        assert_eq!(tx.to(), None);
+        assert_eq!(tx.value(), U256::ZERO);
+
+        let signed = SignedTransaction::new(tx.clone(), Signature::dummy(), tx.encode_for_signing());
+        let encoded = signed.encode();
+        let (decoded, used) = SignedTransaction::decode(&encoded).unwrap();
+        assert_eq!(used, encoded.len());
+        assert_eq!(decoded.transaction(), &tx);
+
+        // Unknown transaction type
+        let mut raw = tx.encode();
+        raw[ITEM_HEADER_LEN] = 0xff;
+        assert!(matches!(Transaction::decode(&raw), Err(DecodeError::InvalidTxType(0xff))));
+    }
+
+    #[test]
+    fn test_primitives_encode_and_decode_batch_transfer() {
+        let outputs: Vec<TransferOutput> = (1..=3)
+            .map(|value| TransferOutput { to: Address::random(), value: U256::from(value) })
+            .collect();
+        let tx = Transaction::BatchTransfer(TxBatchTransfer {
+            chain_id: 0,
+            nonce: 4,
+            fee: 2,
+            outputs,
+            data: Vec::new(),
+        });
+        assert_eq!(tx.tx_type(), TxType::BatchTransfer);
+        assert_eq!(tx.value(), U256::from(6));
+        assert_eq!(Transaction::decode(&tx.encode()).unwrap(), tx);
+
+        // Output count larger than the payload
+        let mut raw = tx.encode();
+        let count_at = ITEM_HEADER_LEN + 1 + 8 + 8 + 16;
+        raw[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(Transaction::decode(&raw).is_err());
+    }
+
+    #[test]
+    fn test_primitives_data_is_signed() {
+        let tx = TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 16,
+            value: U256::from(1),
+            data: b"deposit-0001".to_vec(),
+        };
+        let original: Transaction = tx.clone().into();
+        let decoded = Transaction::decode(&original.encode()).unwrap();
+        assert_eq!(decoded.data(), b"deposit-0001");
+
+        let mut tampered = tx;
+        tampered.data = b"deposit-0002".to_vec();
+        let tampered: Transaction = tampered.into();
+        assert_ne!(tampered.encode_for_signing(), original.encode_for_signing());
+    }
+
+    fn sign_with(signing_key: &SigningKey, hash: TxHash) -> Signature {
+        let (sig, recid): (ECDSASig, RecoveryId) = signing_key
+            .sign_digest_recoverable(Sha256::new_with_prefix(hash.hash()))
+            .unwrap();
+        Signature::from_sig(sig, recid)
+    }
+
+    #[test]
+    fn test_primitives_multisig_transfer_recovers_every_signer() {
+        let keys: Vec<(SigningKey, Address)> = ["pint", "chain", "apple"]
+            .iter()
+            .map(|seed| {
+                let (key, address) = create_key_pairs(seed.as_bytes());
+                (key, Address::from_byte(address.try_into().unwrap()))
+            })
+            .collect();
+        let multisig = Address::random();
+
+        let tx = Transaction::MultisigTransfer(TxMultisigTransfer {
+            chain_id: 0,
+            nonce: 0,
+            from: multisig,
+            to: Address::random(),
+            fee: 3,
+            value: U256::from(100),
+            data: Vec::new(),
+        });
+        let hash = tx.encode_for_signing();
+        let signed = SignedTransaction::new(tx, sign_with(&keys[0].0, hash), hash)
+            .with_cosignatures(vec![sign_with(&keys[2].0, hash)]);
+
+        let (decoded, _) = SignedTransaction::decode(&signed.encode()).unwrap();
+        assert_eq!(decoded.cosignatures.len(), 1);
+        let recovered = decoded.into_recovered().unwrap();
+        assert_eq!(recovered.signer(), multisig);
+        assert_eq!(recovered.approvals(), &[keys[0].1, keys[2].1]);
+
+        // Cosignatures on a single signer transaction
+        let tx = Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 1,
+            value: U256::from(1),
+            data: Vec::new(),
+        });
+        let hash = tx.encode_for_signing();
+        let signed = SignedTransaction::new(tx, sign_with(&keys[0].0, hash), hash)
+            .with_cosignatures(vec![sign_with(&keys[1].0, hash)]);
+        assert!(matches!(
+            signed.into_recovered(),
+            Err(RecoveryError::UnexpectedCosignatures)
+        ));
+
+        let tx = Transaction::RegisterMultisig(TxRegisterMultisig {
+            chain_id: 0,
+            nonce: 0,
+            fee: 1,
+            threshold: 2,
+            owners: keys.iter().map(|(_, address)| *address).collect(),
+        });
+        assert_eq!(Transaction::decode(&tx.encode()).unwrap(), tx);
+    }
+
+    #[test]
+    fn test_primitives_malformed_signature_is_rejected() {
+        let (signing_key, _) = create_key_pairs("pint".as_bytes());
+        let tx = Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce: 0,
+            to: Address::random(),
+            fee: 1,
+            value: U256::from(1),
+            data: Vec::new(),
+        });
+        let hash = tx.encode_for_signing();
+        let signature = sign_with(&signing_key, hash);
+
+        // (r, n - s) with the flipped parity recovers the same key, but isn't canonical
+        let sig: k256::ecdsa::Signature = (&signature).try_into().unwrap();
+        let high_s = k256::ecdsa::Signature::from_scalars(sig.r(), -*sig.s()).unwrap();
+        let mut malleated = Signature::from_sig(high_s, RecoveryId::from_byte(0).unwrap());
+        malleated.y_parity = !signature.y_parity;
+        let signed = SignedTransaction::new(tx.clone(), malleated, hash);
+        assert!(matches!(
+            signed.into_recovered(),
+            Err(RecoveryError::SignatureError(SignatureError::HighS))
+        ));
+
+        // Out of range scalars from the wire don't panic
+        let mut raw = SignedTransaction::new(tx, signature, hash).encode();
+        let sig_at = raw.len() - 4 - Signature::raw_len();
+        raw[sig_at..sig_at + 64].fill(0xff);
+        let (decoded, _) = SignedTransaction::decode(&raw).unwrap();
+        assert!(matches!(
+            decoded.into_recovered(),
+            Err(RecoveryError::SignatureError(SignatureError::InvalidScalar))
+        ));
+    }
 }
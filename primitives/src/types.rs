@@ -1,5 +1,6 @@
 // This project use alloy_primitives in only this file.
 pub use alloy_primitives::{B256, U256};
+use k256::{EncodedPoint, ecdsa::VerifyingKey};
 use libmdbx::orm::{Decodable, Encodable};
 use rand::Rng;
 use serde::Serialize;
@@ -74,6 +75,16 @@ impl Address {
         Self(address)
     }
 
+    // Last 20 bytes of the uncompressed public key
+    pub fn from_public_key(key: &VerifyingKey) -> Self {
+        let pubkey_uncompressed: EncodedPoint = key.to_encoded_point(false);
+        let pubkey_bytes = pubkey_uncompressed.as_bytes();
+        let address: [u8; ADDR_LEN] = pubkey_bytes[pubkey_bytes.len() - ADDR_LEN..]
+            .try_into()
+            .expect("slice is not 20 bytes");
+        Self(address)
+    }
+
     pub fn from_hex(address: String) -> Result<Self, AddressError> {
         let bytes = hex::decode(address)?;
         if bytes.len() != ADDR_LEN {
@@ -115,7 +126,13 @@ impl Encodable for Address {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+impl Decodable for Address {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        Ok(Address(b.try_into()?))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Account {
     pub nonce: u64,
     pub balance: U256,
@@ -1,20 +1,95 @@
+use std::collections::BTreeMap;
+
 use alloy_primitives::B256;
+use anyhow::bail;
 use libmdbx::orm::{Decodable, Encodable};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    encoding::{Decoder, Encoder, ItemType},
+    error::DecodeError,
+    types::Address,
+};
 
-#[derive(Debug, Clone)]
-pub struct World {}
+// Storage of each account: address -> (key -> value)
+// BTreeMap keeps the iteration order fixed, so encoding and hash are deterministic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct World {
+    storage: BTreeMap<Address, BTreeMap<B256, Vec<u8>>>,
+}
 
 impl World {
     pub fn new() -> Self {
-        Self {  }
+        Self::default()
+    }
+
+    pub fn get(&self, address: &Address, key: &B256) -> Option<&Vec<u8>> {
+        self.storage.get(address)?.get(key)
+    }
+
+    // Empty value removes the key
+    pub fn set(&mut self, address: Address, key: B256, value: Vec<u8>) {
+        if value.is_empty() {
+            self.remove(&address, &key);
+            return;
+        }
+        self.storage.entry(address).or_default().insert(key, value);
     }
 
+    pub fn remove(&mut self, address: &Address, key: &B256) -> Option<Vec<u8>> {
+        let account_storage = self.storage.get_mut(address)?;
+        let res = account_storage.remove(key);
+        if account_storage.is_empty() {
+            self.storage.remove(address);
+        }
+        res
+    }
+
+    pub fn account_storage(&self, address: &Address) -> Option<&BTreeMap<B256, Vec<u8>>> {
+        self.storage.get(address)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+
+    // Empty world contributes zero, so a chain without storage keeps its state roots
     pub fn calculate_hash(&self) -> B256 {
-        B256::default()
+        if self.is_empty() {
+            return B256::default();
+        }
+        B256::from_slice(&Sha256::digest(self.encode()))
     }
 
     pub fn encode(&self) -> Vec<u8> {
-        Vec::with_capacity(32)
+        let mut encoder = Encoder::new();
+        encoder.put_u32(self.storage.len() as u32);
+        for (address, account_storage) in self.storage.iter() {
+            encoder
+                .put_address(*address)
+                .put_u32(account_storage.len() as u32);
+            for (key, value) in account_storage.iter() {
+                encoder.put_b256(*key).put_bytes(value);
+            }
+        }
+        encoder.finish(ItemType::World)
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(raw);
+        let mut payload = decoder.read_item(ItemType::World)?;
+        let mut world = World::new();
+        for _ in 0..payload.read_u32()? {
+            let address = payload.read_address()?;
+            for _ in 0..payload.read_u32()? {
+                let key = payload.read_b256()?;
+                let value = payload.read_bytes()?.to_vec();
+                world.set(address, key, value);
+            }
+        }
+        payload.finish()?;
+        decoder.finish()?;
+        Ok(world)
     }
 }
 
@@ -22,14 +97,41 @@ impl Encodable for World {
     type Encoded = Vec<u8>;
 
     fn encode(self) -> Self::Encoded {
-        let res = Vec::new();
-        res
+        World::encode(&self)
     }
 }
 
 impl Decodable for World {
-    fn decode(_b: &[u8]) -> anyhow::Result<Self> {
-        let res = World::new();
-        Ok(res)
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match World::decode(b) {
+            Ok(world) => Ok(world),
+            Err(e) => bail!("e: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_encode_and_decode() {
+        let (alice, bob) = (Address::random(), Address::random());
+        let mut world = World::new();
+        assert_eq!(world.calculate_hash(), B256::default());
+
+        world.set(alice, B256::repeat_byte(1), b"apple".to_vec());
+        world.set(alice, B256::repeat_byte(2), b"banana".to_vec());
+        world.set(bob, B256::repeat_byte(1), b"pint".to_vec());
+
+        let decoded = World::decode(&World::encode(&world)).unwrap();
+        assert_eq!(decoded, world);
+        assert_eq!(decoded.get(&alice, &B256::repeat_byte(2)).unwrap(), b"banana");
+        assert_ne!(world.calculate_hash(), B256::default());
+
+        // Removing the last key drops the account
+        world.set(bob, B256::repeat_byte(1), Vec::new());
+        assert!(world.account_storage(&bob).is_none());
+        assert_ne!(world.calculate_hash(), decoded.calculate_hash());
     }
-}
\ No newline at end of file
+}
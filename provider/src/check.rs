@@ -0,0 +1,319 @@
+use std::{collections::HashMap, convert::Infallible, fmt, sync::Arc};
+
+use database::{changeset::changes_of, error::DatabaseError, traits::DatabaseTrait};
+use primitives::{
+    block::Block,
+    merkle::calculate_merkle_root,
+    types::{Account, Address},
+    world::World,
+};
+use tracing::info;
+
+use crate::{
+    ProviderFactory,
+    executor::Executor,
+    state::ExecutableState,
+    trie::{StateTrie, empty_root, state_root},
+};
+
+/// First inconsistency found by `check_integrity`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency {
+    pub height: u64,
+    pub table: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {} ({}): {}", self.height, self.table, self.reason)
+    }
+}
+
+impl std::error::Error for Inconsistency {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    pub blocks: u64,
+    // Blocks whose parent state was pruned are not re-executed
+    pub executed: u64,
+}
+
+// State after the last replayed block
+struct Replay {
+    accounts: Arc<HashMap<Address, Account>>,
+    world: Arc<World>,
+    trie: StateTrie,
+}
+
+fn inconsistency(height: u64, table: &'static str, reason: impl Into<String>) -> Inconsistency {
+    Inconsistency {
+        height,
+        table,
+        reason: reason.into(),
+    }
+}
+
+impl<DB: DatabaseTrait + Clone> ProviderFactory<DB> {
+    /// Verifies every stored block against the indexes, its parent and a re-execution over the parent state.
+    /// The states are replayed forward once from the oldest one kept, and nothing is written.
+    /// Stops at the first inconsistency. A wrong stored account value is found at the next block
+    /// that changes the account, or against the latest state.
+    pub fn check_integrity(&self) -> Result<CheckReport, Inconsistency> {
+        let latest = self.db().latest_block_number();
+        let oldest_state = self
+            .db()
+            .oldest_state()
+            .map_err(|e| inconsistency(0, "States", format!("failed to read: {}", e)))?;
+
+        // A database started from a snapshot has no blocks between genesis and the snapshot
+        let snapshot = self
+            .db()
+            .snapshot_height()
+            .map_err(|e| inconsistency(0, "Metadata", format!("failed to read: {}", e)))?;
+        let first = snapshot.unwrap_or(1).max(1);
+
+        let mut replay = match latest > oldest_state {
+            true => Some(self.replay_from(oldest_state)?),
+            false => None,
+        };
+        let mut report = CheckReport::default();
+        let mut parent: Option<Block> = None;
+        for height in std::iter::once(0).chain(first..=latest) {
+            if Some(height) == snapshot {
+                parent = None;
+            }
+            let block = self.check_block(height, parent.as_ref())?;
+            report.blocks += 1;
+            if height > oldest_state
+                && let Some(replay) = replay.as_mut()
+            {
+                self.check_execution(&block, replay)?;
+                report.executed += 1;
+            }
+            if height % 1000 == 0 && height > 0 {
+                info!(height, latest, "Checked blocks.");
+            }
+            parent = Some(block);
+        }
+
+        if let Some(replay) = replay {
+            let (stored_accounts, _) = self
+                .db()
+                .get_state(latest)
+                .map_err(|e| inconsistency(latest, "States", format!("failed to read: {}", e)))?;
+            if stored_accounts.as_ref() != Some(&*replay.accounts) {
+                return Err(inconsistency(
+                    latest,
+                    "PlainAccounts",
+                    "stored accounts differ from re-execution",
+                ));
+            }
+        }
+        Ok(report)
+    }
+
+    // Stored state the replay starts from, with its trie opened at the stored root
+    fn replay_from(&self, height: u64) -> Result<Replay, Inconsistency> {
+        let (accounts, world) = self
+            .db()
+            .get_state(height)
+            .map_err(|e| inconsistency(height, "States", format!("failed to read: {}", e)))?;
+        let accounts = accounts.ok_or_else(|| inconsistency(height, "PlainAccounts", "state is missing"))?;
+        let world = world.ok_or_else(|| inconsistency(height, "States", "world is missing"))?;
+
+        let store = Arc::new(self.db().clone());
+        let trie = match self.db().get_trie_root(height) {
+            Ok(Some(root)) => StateTrie::new(root, store),
+            // The genesis root is never written, its nodes go out with block 1
+            Ok(None) if height == 0 => {
+                let mut trie = StateTrie::new(empty_root(), store);
+                for (address, account) in accounts.iter() {
+                    trie.insert(address, account)
+                        .map_err(|e| inconsistency(height, "TrieNodes", format!("{:?}", e)))?;
+                }
+                trie
+            }
+            Ok(None) => return Err(inconsistency(height, "TrieRoots", "root is missing")),
+            Err(e) => {
+                return Err(inconsistency(
+                    height,
+                    "TrieRoots",
+                    format!("failed to read: {}", e),
+                ));
+            }
+        };
+        Ok(Replay {
+            accounts: Arc::new(accounts),
+            world: Arc::new(world),
+            trie,
+        })
+    }
+
+    // Indexes, chain linkage and transaction root of one block
+    fn check_block(&self, height: u64, parent: Option<&Block>) -> Result<Block, Inconsistency> {
+        let block = self
+            .db()
+            .get_block(height)
+            .map_err(|e| inconsistency(height, "Headers", format!("failed to read: {}", e)))?
+            .ok_or_else(|| inconsistency(height, "Headers", "block is missing"))?;
+        if block.header.height != height {
+            return Err(inconsistency(
+                height,
+                "Headers",
+                format!("stored under the wrong height {}", block.header.height),
+            ));
+        }
+
+        let hash = block.header.calculate_hash();
+        match self.db().get_block_by_hash(hash) {
+            Ok(Some(indexed)) if indexed.header.height == height => {}
+            Ok(Some(indexed)) => {
+                return Err(inconsistency(
+                    height,
+                    "BlockByHash",
+                    format!("hash points to block {}", indexed.header.height),
+                ));
+            }
+            Ok(None) => return Err(inconsistency(height, "BlockByHash", "hash is not indexed")),
+            Err(e) if matches!(e.downcast_ref(), Some(DatabaseError::DataNotExists)) => {
+                return Err(inconsistency(height, "BlockByHash", "hash is not indexed"));
+            }
+            Err(e) => {
+                return Err(inconsistency(
+                    height,
+                    "BlockByHash",
+                    format!("failed to read: {}", e),
+                ));
+            }
+        }
+
+        for transaction in block.body.iter() {
+            match self.db().get_transaction_by_hash(transaction.hash) {
+                Ok(Some((_, block_no))) if block_no == height => {}
+                Ok(Some((_, block_no))) => {
+                    return Err(inconsistency(
+                        height,
+                        "Transactions",
+                        format!("{:?} points to block {}", transaction.hash, block_no),
+                    ));
+                }
+                Ok(None) => {
+                    return Err(inconsistency(
+                        height,
+                        "Transactions",
+                        format!("{:?} is not indexed", transaction.hash),
+                    ));
+                }
+                Err(e) => {
+                    return Err(inconsistency(
+                        height,
+                        "Transactions",
+                        format!("failed to read: {}", e),
+                    ));
+                }
+            }
+        }
+
+        if let Some(parent) = parent
+            && block.header.previous_hash != parent.header.calculate_hash()
+        {
+            return Err(inconsistency(
+                height,
+                "Headers",
+                "previous hash does not match the parent",
+            ));
+        }
+
+        let tx_hashes = block.body.iter().map(|tx| tx.hash.hash()).collect();
+        if calculate_merkle_root(tx_hashes) != block.header.transaction_root {
+            return Err(inconsistency(
+                height,
+                "Bodies",
+                "transaction root does not match the body",
+            ));
+        }
+        Ok(block)
+    }
+
+    // Replays the block over the parent state and compares with what was stored for it
+    fn check_execution(&self, block: &Block, replay: &mut Replay) -> Result<(), Inconsistency> {
+        let height = block.header.height;
+        let state = ExecutableState {
+            accounts_base: replay.accounts.clone(),
+            accounts_write: (*replay.accounts).clone(),
+            field_base: replay.world.clone(),
+            field_write: (*replay.world).clone(),
+            trie: replay.trie.clone(),
+        };
+        let mut executor = Executor::new(state, self.chain_id());
+        let (_, world) = executor.execute_block(block).map_err(|e| {
+            inconsistency(height, "Bodies", format!("re-execution failed: {:?}", e))
+        })?;
+
+        let changed = executor.state.changed_accounts();
+        let Ok(changes) = changes_of(&changed, |address| {
+            Ok::<_, Infallible>(replay.accounts.get(address).copied())
+        });
+        let stored_changes = self
+            .db()
+            .get_account_changes(height)
+            .map_err(|e| inconsistency(height, "AccountChangeSets", format!("failed to read: {}", e)))?;
+        if stored_changes != changes {
+            return Err(inconsistency(
+                height,
+                "PlainAccounts",
+                "stored account changes differ from re-execution",
+            ));
+        }
+        let stored_world = self
+            .db()
+            .get_world(height)
+            .map_err(|e| inconsistency(height, "States", format!("failed to read: {}", e)))?;
+        if stored_world.as_ref() != Some(&world) {
+            return Err(inconsistency(
+                height,
+                "States",
+                "stored world differs from re-execution",
+            ));
+        }
+
+        let trie = executor
+            .state
+            .updated_trie()
+            .map_err(|e| inconsistency(height, "TrieNodes", format!("{:?}", e)))?;
+        match self.db().get_trie_root(height) {
+            Ok(Some(root)) if root == trie.root() => {}
+            Ok(Some(_)) => {
+                return Err(inconsistency(
+                    height,
+                    "TrieRoots",
+                    "stored root differs from re-execution",
+                ));
+            }
+            Ok(None) => return Err(inconsistency(height, "TrieRoots", "root is missing")),
+            Err(e) => {
+                return Err(inconsistency(
+                    height,
+                    "TrieRoots",
+                    format!("failed to read: {}", e),
+                ));
+            }
+        }
+        if state_root(trie.root(), world.calculate_hash()) != block.header.state_root {
+            return Err(inconsistency(
+                height,
+                "Headers",
+                "state root differs from re-execution",
+            ));
+        }
+
+        // The nodes under the stored root are read back from the database from now on
+        *replay = Replay {
+            accounts: Arc::new(executor.state.accounts_write),
+            world: Arc::new(world),
+            trie: StateTrie::new(trie.root(), Arc::new(self.db().clone())),
+        };
+        Ok(())
+    }
+}
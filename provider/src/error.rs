@@ -1,4 +1,5 @@
-use primitives::{error::RecoveryError, types::TxHash};
+use database::error::DatabaseError;
+use primitives::{error::{MultisigError, RecoveryError}, receipt::ReceiptError, types::{Address, B256, ChainId, TxHash}};
 use thiserror::Error;
 
 #[derive(Clone, Debug, Error)]
@@ -9,6 +10,14 @@ pub enum ExecutionError {
     TransactionRecoveryError(RecoveryError),
     #[error("Total fee is diffrent")]
     TotalFeeisDifferent,
+    #[error("Transaction has invalid chain id")]
+    InvalidChainId(ChainId),
+    #[error("State root is different")]
+    InvalidStateRoot,
+    #[error("Receipts root is different")]
+    InvalidReceiptsRoot,
+    #[error("State trie Error")]
+    TrieError(TrieError),
 }
 
 #[derive(Debug, Error)]
@@ -19,6 +28,32 @@ pub enum ProviderError {
     ExecutionError(ExecutionError),
     #[error("State is not exist")]
     StateNotExist(u64),
+    #[error("State at block {0} has been pruned")]
+    StatePruned(u64),
+    #[error("State trie Error")]
+    TrieError(TrieError),
+    #[error("Snapshot state does not match the state root of block {0}")]
+    InvalidSnapshot(u64),
+    #[error("Snapshot block {0} is not the trusted block")]
+    UntrustedSnapshot(u64),
+}
+
+impl ProviderError {
+    // Keeps pruned states apart from other database failures
+    pub fn from_database(error: Box<dyn std::error::Error>) -> Self {
+        match error.downcast_ref::<DatabaseError>() {
+            Some(DatabaseError::Pruned(block_no)) => ProviderError::StatePruned(*block_no),
+            _ => ProviderError::DatabaseError(error),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum TrieError {
+    #[error("Trie node is missing")]
+    MissingNode(B256),
+    #[error("Database Error")]
+    DatabaseError(String),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -27,6 +62,14 @@ pub enum StateExecutionError {
     TransactionExecutionError(TxHash, TxExecutionError),
 }
 
+impl StateExecutionError {
+    pub fn receipt_error(&self) -> ReceiptError {
+        match self {
+            StateExecutionError::TransactionExecutionError(_, e) => e.receipt_error(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum TxExecutionError {
     #[error("Sender has not enough balance")]
@@ -35,4 +78,36 @@ pub enum TxExecutionError {
     SenderHasNoAccount,
     #[error("Invalid nonce value")]
     NonceError(u64, u64),
+    #[error("Invalid data length")]
+    InvalidDataLength(usize),
+    #[error("Invalid number of batch outputs")]
+    InvalidOutputCount(usize),
+    #[error("Invalid multisig policy")]
+    InvalidMultisigPolicy(MultisigError),
+    #[error("Multisig account already exists")]
+    MultisigAlreadyExists(Address),
+    #[error("Account is not a multisig account")]
+    MultisigNotFound(Address),
+    #[error("Not enough multisig owners signed")]
+    MultisigThresholdNotMet,
+    #[error("Data key is reserved for multisig policies")]
+    ReservedDataKey,
+}
+
+impl TxExecutionError {
+    // Code recorded in the receipt. The details are left out: they are not part of the receipts root.
+    pub fn receipt_error(&self) -> ReceiptError {
+        match self {
+            TxExecutionError::SenderHasNotEnoughBalance => ReceiptError::SenderHasNotEnoughBalance,
+            TxExecutionError::SenderHasNoAccount => ReceiptError::SenderHasNoAccount,
+            TxExecutionError::NonceError(..) => ReceiptError::InvalidNonce,
+            TxExecutionError::InvalidDataLength(_) => ReceiptError::InvalidDataLength,
+            TxExecutionError::InvalidOutputCount(_) => ReceiptError::InvalidOutputCount,
+            TxExecutionError::InvalidMultisigPolicy(_) => ReceiptError::InvalidMultisigPolicy,
+            TxExecutionError::MultisigAlreadyExists(_) => ReceiptError::MultisigAlreadyExists,
+            TxExecutionError::MultisigNotFound(_) => ReceiptError::MultisigNotFound,
+            TxExecutionError::MultisigThresholdNotMet => ReceiptError::MultisigThresholdNotMet,
+            TxExecutionError::ReservedDataKey => ReceiptError::ReservedDataKey,
+        }
+    }
 }
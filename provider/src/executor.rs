@@ -1,18 +1,19 @@
 use std::{collections::HashMap, convert::Infallible};
 
-use primitives::{block::Block, transaction::Recovered, types::{Account, Address, TxHash, B256, U256}, world::World};
+use primitives::{block::Block, receipt::{ReceiptError, TransactionReceipt}, transaction::{Recovered, Tx}, types::{Account, Address, ChainId, TxHash, B256, U256}, world::World};
 
-use crate::{error::ExecutionError, state::{ExecutableState}};
+use crate::{error::{ExecutionError, TrieError}, state::{ExecutableState}};
 
 pub struct Executor {
     pub state: ExecutableState,
     pub receipts: Vec<Receipt>,
+    pub chain_id: ChainId,
 }
 
 impl Executor {
 
-    pub fn new(state: ExecutableState) -> Self {
-        Self { state, receipts: Vec::new() }
+    pub fn new(state: ExecutableState, chain_id: ChainId) -> Self {
+        Self { state, receipts: Vec::new(), chain_id }
     }
 
     pub fn state(&mut self) -> &mut ExecutableState {
@@ -21,11 +22,17 @@ impl Executor {
     pub fn execute_transaction(&mut self, tx: &Recovered) 
     -> Result<Receipt, Infallible>{
         let mut receipt = Receipt { tx_hash: tx.hash(), fee: 0, success: true, error: None };
+        if tx.chain_id() != self.chain_id {
+            receipt.success = false;
+            receipt.error = Some(ReceiptError::InvalidChainId);
+            self.receipts.push(receipt.clone());
+            return Ok(receipt);
+        }
         receipt.fee = match self.state.execute_transaction(tx) {
             Ok(fee) => fee,
             Err(err) => {
                 receipt.success = false;
-                receipt.error = Some(ExecutionError::StateExecutionError(err));
+                receipt.error = Some(err.receipt_error());
                 0
             }
         };
@@ -39,6 +46,10 @@ impl Executor {
         let proposer = block.header().proposer;
         let mut fee_sum = U256::ZERO;
         for transaction in transactions.iter() {
+            // Replay protection: a transaction signed for another chain invalidates the block
+            if transaction.chain_id() != self.chain_id {
+                return Err(ExecutionError::InvalidChainId(transaction.chain_id()));
+            }
             let recovered = match transaction.clone().into_recovered() {
                 Ok(recovered) => recovered,
                 Err(e) => {
@@ -60,23 +71,30 @@ impl Executor {
         }
 
         // update mining results
-        match self.state().accounts_write.get_mut(&proposer) {
-            Some(account) => {
-                account.add_balance(fee_sum);
-            }
-            None => {
-                let mut new_account = Account::default();
-                new_account.add_balance(fee_sum);
-                self.state().accounts_write.insert(proposer, new_account);
-            }
-        }
+        self.reward_proposer(proposer, fee_sum);
 
         Ok((self.state.accounts_write.clone(), self.state.field_write.clone()))
     }
 
-    pub fn calculate_state_root(&self) -> B256 {
+    // Receipts of every executed transaction, indexed in execution order.
+    // After execute_block this is one receipt per transaction of the block body.
+    pub fn transaction_receipts(&self) -> Vec<TransactionReceipt> {
+        self.receipts
+            .iter()
+            .enumerate()
+            .map(|(index, receipt)| receipt.to_transaction_receipt(index as u32))
+            .collect()
+    }
+
+    pub fn calculate_state_root(&self) -> Result<B256, TrieError> {
         self.state.calculate_state_root()
     }
+
+    // Fees of the block go to its proposer
+    pub fn reward_proposer(&mut self, proposer: Address, fee: U256) {
+        let account = self.state().accounts_write.entry(proposer).or_default();
+        account.add_balance(fee);
+    }
 }
 
 
@@ -86,5 +104,368 @@ pub struct Receipt {
     pub tx_hash: TxHash,
     pub fee: u128,
     pub success: bool,
-    pub error: Option<ExecutionError>,
-}
\ No newline at end of file
+    pub error: Option<ReceiptError>,
+}
+
+impl Receipt {
+    // Persisted form, part of the receipts root
+    pub fn to_transaction_receipt(&self, index: u32) -> TransactionReceipt {
+        TransactionReceipt { tx_hash: self.tx_hash, index, success: self.success, fee: self.fee, error: self.error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use database::{immemorydb::InMemoryDB, prune::PruneMode, traits::{DatabaseTrait, TrieUpdate}};
+    use primitives::{multisig::{multisig_address, MultisigPolicy, MULTISIG_POLICY_KEY}, receipt::calculate_receipts_root, signature::Signature, test_utils::{sign, sign_multi}, transaction::{SignedTransaction, Transaction, TransferOutput, TxBatchTransfer, TxData, TxMultisigTransfer, TxRegisterMultisig, TxTransfer}};
+
+    use crate::{check::CheckReport, error::{ExecutionError, ProviderError, StateExecutionError, TxExecutionError}, test_utils::import_transfer, ProviderFactory};
+
+    use super::*;
+
+    #[test]
+    fn test_execute_block_rejects_cross_chain_transaction() {
+        let provider = ProviderFactory::new(Arc::new(InMemoryDB::genesis_state()), 1);
+        let state = provider.latest().executable_state().unwrap();
+        let mut executor = Executor::new(state, provider.chain_id());
+
+        // Signed for chain 0
+        let tx = Transaction::Transfer(TxTransfer { chain_id: 0, nonce: 0, to: Address::random(), fee: 1, value: U256::from(1), data: Vec::new() });
+        let hash = tx.encode_for_signing();
+        let mut block = Block::genesis_block();
+        block.header.height = 1;
+        block.body.push(SignedTransaction::new(tx, Signature::dummy(), hash));
+
+        assert!(matches!(executor.execute_block(&block), Err(ExecutionError::InvalidChainId(0))));
+    }
+
+    fn batch(values: &[(Address, u64)]) -> Transaction {
+        Transaction::BatchTransfer(TxBatchTransfer {
+            chain_id: 0,
+            nonce: 0,
+            fee: 10,
+            outputs: values
+                .iter()
+                .map(|(to, value)| TransferOutput { to: *to, value: U256::from(*value) })
+                .collect(),
+            data: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_execute_batch_transfer() {
+        let (alice, bob) = (Address::random(), Address::random());
+        let probe = sign("pint", batch(&[(alice, 1)]));
+        let mut db = InMemoryDB::new();
+        db.add_account(probe.signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        let state = provider.latest().executable_state().unwrap();
+        let mut executor = Executor::new(state, provider.chain_id());
+
+        // alice appears twice, both outputs are credited
+        let tx = sign("pint", batch(&[(alice, 100), (bob, 200), (alice, 300)]));
+        let receipt = executor.execute_transaction(&tx).unwrap();
+        assert!(receipt.success);
+
+        let accounts = &executor.state.accounts_write;
+        assert_eq!(accounts[&alice].balance(), U256::from(400));
+        assert_eq!(accounts[&bob].balance(), U256::from(200));
+        assert_eq!(accounts[&tx.signer()].balance(), U256::from(1000 - 600 - 10));
+        assert_eq!(accounts[&tx.signer()].nonce(), 1);
+    }
+
+    #[test]
+    fn test_execute_batch_transfer_is_atomic() {
+        let (alice, bob) = (Address::random(), Address::random());
+        let probe = sign("pint", batch(&[(alice, 1)]));
+        let mut db = InMemoryDB::new();
+        db.add_account(probe.signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        let state = provider.latest().executable_state().unwrap();
+        let mut executor = Executor::new(state, provider.chain_id());
+
+        // The first output is affordable, the whole batch is not
+        let tx = sign("pint", batch(&[(alice, 500), (bob, 500)]));
+        let receipt = executor.execute_transaction(&tx).unwrap();
+        assert!(!receipt.success);
+        // Recorded as its code, the amounts are left out of the receipt
+        assert_eq!(executor.transaction_receipts()[0].error, Some(ReceiptError::SenderHasNotEnoughBalance));
+
+        let accounts = &executor.state.accounts_write;
+        assert!(!accounts.contains_key(&alice));
+        assert!(!accounts.contains_key(&bob));
+        assert_eq!(accounts[&tx.signer()].balance(), U256::from(1000));
+        assert_eq!(accounts[&tx.signer()].nonce(), 0);
+
+        // Empty batch
+        let tx = sign("pint", batch(&[]));
+        assert!(matches!(
+            executor.state.execute_transaction(&tx),
+            Err(StateExecutionError::TransactionExecutionError(_, TxExecutionError::InvalidOutputCount(0)))
+        ));
+    }
+
+    #[test]
+    fn test_execute_data_writes_world() {
+        let key = B256::repeat_byte(1);
+        let store = |nonce, data: &[u8]| {
+            sign("pint", Transaction::Data(TxData { chain_id: 0, nonce, fee: 10, key, data: data.to_vec() }))
+        };
+        let probe = store(0, b"");
+        let mut db = InMemoryDB::new();
+        db.add_account(probe.signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        let state = provider.latest().executable_state().unwrap();
+        let mut executor = Executor::new(state, provider.chain_id());
+        let empty_root = executor.calculate_state_root().unwrap();
+
+        assert!(executor.execute_transaction(&store(0, b"order-42")).unwrap().success);
+        assert_eq!(executor.state.field_write.get(&probe.signer(), &key).unwrap(), b"order-42");
+        assert_ne!(executor.calculate_state_root().unwrap(), empty_root);
+
+        // Empty data removes the key
+        assert!(executor.execute_transaction(&store(1, b"")).unwrap().success);
+        assert!(executor.state.field_write.is_empty());
+    }
+
+    #[test]
+    fn test_imported_block_proves_accounts() {
+        let receiver = Address::random();
+        let proposer = Address::random();
+        let tx = sign("pint", Transaction::Transfer(TxTransfer { chain_id: 0, nonce: 0, to: receiver, fee: 10, value: U256::from(100), data: Vec::new() }));
+        let mut db = InMemoryDB::new();
+        db.add_account(tx.signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+
+        // Build the block the way the payload builder does
+        let mut executor = Executor::new(provider.latest().executable_state().unwrap(), 0);
+        // Reading the genesis trie builds it in memory without writing it
+        provider.latest().account_proof(tx.signer()).unwrap();
+        assert!(provider.db().get_trie_root(0).unwrap().is_none());
+        assert!(executor.execute_transaction(&tx).unwrap().success);
+        executor.reward_proposer(proposer, U256::from(10));
+        let mut block = Block::genesis_block();
+        block.header.height = 1;
+        block.header.proposer = proposer;
+        block.header.total_fee = U256::from(10);
+        block.header.state_root = executor.calculate_state_root().unwrap();
+        block.header.receipts_root = calculate_receipts_root(&executor.transaction_receipts());
+        block.body.push(tx.tx().clone());
+
+        let mut wrong_root = block.clone();
+        wrong_root.header.state_root = B256::ZERO;
+        assert!(provider.import_new_block(wrong_root).is_err());
+        let mut wrong_receipts = block.clone();
+        wrong_receipts.header.receipts_root = B256::ZERO;
+        assert!(matches!(
+            provider.import_new_block(wrong_receipts),
+            Err(ProviderError::ExecutionError(ExecutionError::InvalidReceiptsRoot))
+        ));
+        provider.import_new_block(block.clone()).unwrap();
+
+        let (receipt, block_no) = provider.db().get_receipt(tx.tx().hash).unwrap().unwrap();
+        assert_eq!(block_no, 1);
+        assert_eq!((receipt.index, receipt.success, receipt.fee), (0, true, 10));
+        // The genesis nodes the block builds on are written with it
+        assert!(provider.db().get_trie_root(0).unwrap().is_none());
+        assert_eq!(provider.db().get_trie_root(1).unwrap(), Some(provider.latest().state_trie().unwrap().root()));
+
+        for address in [tx.signer(), receiver, proposer, Address::random()] {
+            let proof = provider.latest().account_proof(address).unwrap();
+            assert!(proof.verify(block.header.state_root));
+        }
+        let proof = provider.latest().account_proof(receiver).unwrap();
+        assert_eq!(proof.account.unwrap().balance(), U256::from(100));
+    }
+
+    // Imports a block at `height` with pint's transfer of 100 to `to`
+    #[test]
+    fn test_state_is_kept_for_every_height() {
+        let receiver = Address::random();
+        let proposer = Address::random();
+        let mut db = InMemoryDB::new();
+        db.add_account(sign("pint", batch(&[])).signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+
+        for height in [1, 2] {
+            import_transfer(&provider, height, receiver, proposer);
+        }
+
+        // Only the changed accounts are stored per block, older states are rebuilt from them
+        let balance = |height: u64, address: Address| {
+            let (accounts, _) = provider.db().get_state(height).unwrap();
+            accounts.unwrap().get(&address).map(|account| account.balance())
+        };
+        assert_eq!(balance(0, receiver), None);
+        assert_eq!(balance(1, receiver), Some(U256::from(100)));
+        assert_eq!(balance(2, receiver), Some(U256::from(200)));
+        assert_eq!(balance(1, proposer), Some(U256::from(10)));
+        assert_eq!(provider.db().basic(&receiver).unwrap().unwrap().balance(), U256::from(200));
+        assert!(provider.db().get_state(3).unwrap().0.is_none());
+    }
+
+    #[test]
+    fn test_pruned_state_is_reported() {
+        let receiver = Address::random();
+        let proposer = Address::random();
+        let mut db = InMemoryDB::new().with_prune_mode(PruneMode::KeepLast(1));
+        db.add_account(sign("pint", batch(&[])).signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        for height in [1, 2, 3] {
+            import_transfer(&provider, height, receiver, proposer);
+        }
+
+        // States 0 and 1 fall out of the window
+        assert_eq!(provider.db().prune().unwrap(), 2);
+        assert_eq!(provider.db().oldest_state().unwrap(), 2);
+        assert_eq!(provider.db().prune().unwrap(), 0);
+        assert!(matches!(provider.state_by_block_number(1).account(receiver), Err(ProviderError::StatePruned(1))));
+        assert_eq!(provider.state_by_block_number(2).account(receiver).unwrap().unwrap().balance(), U256::from(200));
+        assert_eq!(provider.latest().account(receiver).unwrap().unwrap().balance(), U256::from(300));
+        assert!(provider.latest().state_trie().is_ok());
+
+        // The chain keeps growing on top of the pruned history
+        import_transfer(&provider, 4, receiver, proposer);
+        assert_eq!(provider.db().prune().unwrap(), 1);
+        assert_eq!(provider.latest().account(receiver).unwrap().unwrap().balance(), U256::from(400));
+    }
+
+    #[test]
+    fn test_check_integrity_reports_first_inconsistency() {
+        let receiver = Address::random();
+        let proposer = Address::random();
+        let mut db = InMemoryDB::new();
+        db.add_account(sign("pint", batch(&[])).signer(), Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        for height in [1, 2] {
+            import_transfer(&provider, height, receiver, proposer);
+        }
+        assert_eq!(provider.check_integrity().unwrap(), CheckReport { blocks: 3, executed: 2 });
+        // The genesis trie is rebuilt in memory, not written
+        assert_eq!(provider.db().get_trie_root(0).unwrap(), None);
+
+        // An empty block whose stored state credits the receiver out of nowhere
+        let (accounts, world) = provider.db().get_state(2).unwrap();
+        let mut accounts = accounts.unwrap();
+        accounts.get_mut(&receiver).unwrap().add_balance(U256::from(1));
+        let mut block = Block::genesis_block();
+        block.header.height = 3;
+        block.header.previous_hash = provider.db().get_latest_block_header().calculate_hash();
+        let trie = TrieUpdate { root: provider.db().get_trie_root(2).unwrap().unwrap(), nodes: Vec::new() };
+        provider.db().update(accounts, world.unwrap(), block, trie, Vec::new()).unwrap();
+
+        let inconsistency = provider.check_integrity().unwrap_err();
+        assert_eq!((inconsistency.height, inconsistency.table), (3, "PlainAccounts"));
+    }
+
+    #[test]
+    fn test_check_integrity_reports_missing_trie_nodes() {
+        let receiver = Address::random();
+        let proposer = Address::random();
+        let funded = || {
+            let mut db = InMemoryDB::new();
+            db.add_account(sign("pint", batch(&[])).signer(), Account::new(0, U256::from(1000))).unwrap();
+            ProviderFactory::new(Arc::new(db), 0)
+        };
+        let source = funded();
+        for height in [1, 2, 3] {
+            import_transfer(&source, height, receiver, proposer);
+        }
+        let state = |height: u64| {
+            let (accounts, world) = source.db().get_state(height).unwrap();
+            (accounts.unwrap(), world.unwrap(), source.db().get_block(height).unwrap().unwrap())
+        };
+        let (accounts, world, block) = state(2);
+        let parent = source.db().get_header(1).unwrap().unwrap();
+        let receipts = vec![source.db().get_receipt(block.body[0].hash).unwrap().unwrap().0];
+
+        let complete = funded();
+        let trusted = block.header.calculate_hash();
+        complete.import_snapshot(accounts.clone(), world.clone(), block.clone(), receipts.clone(), parent.clone(), trusted).unwrap();
+        import_transfer(&complete, 3, receiver, proposer);
+        assert_eq!(complete.check_integrity().unwrap(), CheckReport { blocks: 3, executed: 1 });
+        // The snapshot block's transactions keep their receipts
+        assert_eq!(complete.db().get_receipt(block.body[0].hash).unwrap(), Some((receipts[0].clone(), 2)));
+
+        // Receipts that do not match the receipts root are refused
+        let mut forged = receipts.clone();
+        forged[0].fee += 1;
+        assert!(matches!(
+            funded().import_snapshot(accounts.clone(), world.clone(), block.clone(), forged, parent.clone(), trusted),
+            Err(ProviderError::InvalidSnapshot(2))
+        ));
+
+        // The snapshot state and the next block with their trie roots but without the nodes
+        let nodeless = funded();
+        let root = |height: u64| source.db().get_trie_root(height).unwrap().unwrap();
+        nodeless.db().import_snapshot(accounts, world, block, receipts, parent, TrieUpdate { root: root(2), nodes: Vec::new() }).unwrap();
+        let (accounts, world, block) = state(3);
+        nodeless.db().update(accounts, world, block, TrieUpdate { root: root(3), nodes: Vec::new() }, Vec::new()).unwrap();
+        let inconsistency = nodeless.check_integrity().unwrap_err();
+        assert_eq!((inconsistency.height, inconsistency.table), (3, "TrieNodes"));
+    }
+
+    #[test]
+    fn test_execute_multisig_transfer() {
+        let owner = |seed: &str| sign(seed, batch(&[])).signer();
+        let (pint, apple, banana) = (owner("pint"), owner("apple"), owner("banana"));
+        let receiver = Address::random();
+
+        let mut db = InMemoryDB::new();
+        db.add_account(pint, Account::new(0, U256::from(1000))).unwrap();
+        let provider = ProviderFactory::new(Arc::new(db), 0);
+        let state = provider.latest().executable_state().unwrap();
+        let mut executor = Executor::new(state, provider.chain_id());
+
+        // 2-of-3, registered and funded by pint
+        let register = sign("pint", Transaction::RegisterMultisig(TxRegisterMultisig { chain_id: 0, nonce: 0, fee: 10, threshold: 2, owners: vec![pint, apple, banana] }));
+        assert!(executor.execute_transaction(&register).unwrap().success);
+        let multisig = multisig_address(&pint, 0);
+        let fund = sign("pint", Transaction::Transfer(TxTransfer { chain_id: 0, nonce: 1, to: multisig, fee: 10, value: U256::from(500), data: Vec::new() }));
+        assert!(executor.execute_transaction(&fund).unwrap().success);
+
+        let spend = |seeds: &[&str]| {
+            sign_multi(seeds, Transaction::MultisigTransfer(TxMultisigTransfer { chain_id: 0, nonce: 0, from: multisig, to: receiver, fee: 10, value: U256::from(100), data: Vec::new() }))
+        };
+
+        // One owner, the same owner twice, and an owner with a stranger are all below the threshold
+        for seeds in [&["apple"][..], &["apple", "apple"], &["apple", "chain"]] {
+            assert!(matches!(
+                executor.state.execute_transaction(&spend(seeds)),
+                Err(StateExecutionError::TransactionExecutionError(_, TxExecutionError::MultisigThresholdNotMet))
+            ));
+        }
+
+        let tx = spend(&["banana", "apple"]);
+        assert_eq!(tx.signer(), multisig);
+        assert!(executor.execute_transaction(&tx).unwrap().success);
+        let accounts = &executor.state.accounts_write;
+        assert_eq!(accounts[&multisig].balance(), U256::from(500 - 100 - 10));
+        assert_eq!(accounts[&multisig].nonce(), 1);
+        assert_eq!(accounts[&receiver].balance(), U256::from(100));
+
+        // A plain account has no policy
+        let tx = sign_multi(&["pint", "apple"], Transaction::MultisigTransfer(TxMultisigTransfer { chain_id: 0, nonce: 2, from: pint, to: receiver, fee: 10, value: U256::from(1), data: Vec::new() }));
+        assert!(matches!(
+            executor.state.execute_transaction(&tx),
+            Err(StateExecutionError::TransactionExecutionError(_, TxExecutionError::MultisigNotFound(_)))
+        ));
+
+        // and can not store one of its own
+        let policy = MultisigPolicy { threshold: 0, owners: vec![apple] }.encode();
+        let store = sign("pint", Transaction::Data(TxData { chain_id: 0, nonce: 2, fee: 10, key: MULTISIG_POLICY_KEY, data: policy.clone() }));
+        assert!(matches!(
+            executor.state.execute_transaction(&store),
+            Err(StateExecutionError::TransactionExecutionError(_, TxExecutionError::ReservedDataKey))
+        ));
+        // A stored policy that is not valid approves nothing
+        executor.state.field_write.set(pint, MULTISIG_POLICY_KEY, policy);
+        let tx = sign("pint", Transaction::MultisigTransfer(TxMultisigTransfer { chain_id: 0, nonce: 2, from: pint, to: receiver, fee: 10, value: U256::from(1), data: Vec::new() }));
+        assert!(matches!(
+            executor.state.execute_transaction(&tx),
+            Err(StateExecutionError::TransactionExecutionError(_, TxExecutionError::MultisigNotFound(_)))
+        ));
+    }
+}
@@ -1,20 +1,32 @@
+pub mod check;
 pub mod error;
 pub mod executor;
 pub mod state;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod trie;
 
 pub use database::traits::DatabaseTrait;
 use primitives::{
-    block::Block,
-    types::{Account, Address},
+    block::{Block, Header},
+    receipt::{TransactionReceipt, calculate_receipts_root},
+    types::{Account, Address, B256, BlockHash, ChainId},
+    world::World,
 };
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use tracing::{error, info};
 
-use crate::{error::ProviderError, executor::Executor, state::ExecutableState};
+use crate::{
+    error::{ExecutionError, ProviderError},
+    executor::Executor,
+    state::ExecutableState,
+    trie::{AccountProof, StateTrie, empty_root},
+};
 
 #[derive(Debug, Clone)]
 pub struct ProviderFactory<DB: DatabaseTrait> {
     db: DB,
+    chain_id: ChainId,
 }
 
 impl<DB: DatabaseTrait + Clone> ProviderFactory<DB> {
@@ -44,14 +56,18 @@ impl<DB: DatabaseTrait + Clone> ProviderFactory<DB> {
         new_difficulty
     }
 
-    pub fn new(db: DB) -> Self {
-        Self { db }
+    pub fn new(db: DB, chain_id: ChainId) -> Self {
+        Self { db, chain_id }
     }
 
     pub fn db(&self) -> &DB {
         &self.db
     }
 
+    pub fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
     pub fn block_number(&self) -> u64 {
         self.db.latest_block_number()
     }
@@ -61,7 +77,7 @@ impl<DB: DatabaseTrait + Clone> ProviderFactory<DB> {
         self.state_by_block_number(block_no)
     }
 
-    fn state_by_block_number(&self, block_no: u64) -> Provider<DB> {
+    pub fn state_by_block_number(&self, block_no: u64) -> Provider<DB> {
         Provider {
             db: self.db.clone(),
             block_no: block_no,
@@ -79,19 +95,81 @@ impl<DB: DatabaseTrait + Clone> ProviderFactory<DB> {
             }
         };
 
-        let mut executor = Executor::new(state);
+        let mut executor = Executor::new(state, self.chain_id);
 
-        let (new_account_state, new_field_state) = match executor.execute_block(&block) {
-            Ok((account, field)) => (account, field),
+        let new_field_state = match executor.execute_block(&block) {
+            Ok((_, field)) => field,
             Err(e) => {
                 error!(error = ?e, "Failed to execute block.");
                 return Err(ProviderError::ExecutionError(e));
             }
         };
 
-        // update results
+        let trie = executor
+            .state
+            .updated_trie()
+            .map_err(ProviderError::TrieError)?;
+        let state_root = trie::state_root(trie.root(), new_field_state.calculate_hash());
+        if state_root != block.header.state_root {
+            error!(height = block.header.height, "State root is different.");
+            return Err(ProviderError::ExecutionError(ExecutionError::InvalidStateRoot));
+        }
+
+        let receipts = executor.transaction_receipts();
+        if calculate_receipts_root(&receipts) != block.header.receipts_root {
+            error!(height = block.header.height, "Receipts root is different.");
+            return Err(ProviderError::ExecutionError(ExecutionError::InvalidReceiptsRoot));
+        }
+
+        // update results, only the accounts the block touched are written
         info!("Imported New Block. {}", &block);
-        let _ = self.db.update(new_account_state, new_field_state, block);
+        let changed_accounts = executor.state.changed_accounts();
+        self.db
+            .update(changed_accounts, new_field_state, block, trie.trie_update(), receipts)
+            .map_err(ProviderError::DatabaseError)
+    }
+
+    /// Starts an empty database from the full state at `block`, once it matches the block's state root.
+    /// Blocks after it are then imported as usual.
+    /// Starts the database from the state at `block`. The snapshot comes from an untrusted source:
+    /// the block has to be the one the operator trusts, and the state has to match its state root.
+    /// The block's receipts have to match its receipts root, they are stored with it.
+    pub fn import_snapshot(
+        &self,
+        accounts: HashMap<Address, Account>,
+        world: World,
+        block: Block,
+        receipts: Vec<TransactionReceipt>,
+        parent: Header,
+        trusted: BlockHash,
+    ) -> Result<(), ProviderError> {
+        let height = block.header.height;
+        if block.header.calculate_hash() != trusted {
+            error!(height, "Snapshot block is not the trusted block.");
+            return Err(ProviderError::UntrustedSnapshot(height));
+        }
+        if parent.calculate_hash() != block.header.previous_hash || parent.height + 1 != height {
+            error!(height, "Snapshot parent header does not match the block.");
+            return Err(ProviderError::InvalidSnapshot(height));
+        }
+        if calculate_receipts_root(&receipts) != block.header.receipts_root {
+            error!(height, "Snapshot receipts root is different.");
+            return Err(ProviderError::InvalidSnapshot(height));
+        }
+        let mut trie = StateTrie::new(empty_root(), Arc::new(self.db.clone()));
+        for (address, account) in accounts.iter() {
+            trie.insert(address, account)
+                .map_err(ProviderError::TrieError)?;
+        }
+        if trie::state_root(trie.root(), world.calculate_hash()) != block.header.state_root {
+            error!(height, "Snapshot state root is different.");
+            return Err(ProviderError::InvalidSnapshot(height));
+        }
+
+        self.db
+            .import_snapshot(accounts, world, block, receipts, parent, trie.trie_update())
+            .map_err(ProviderError::DatabaseError)?;
+        info!(height, "Imported snapshot.");
         Ok(())
     }
 }
@@ -109,10 +187,28 @@ impl<DB: DatabaseTrait> Provider<DB> {
         Ok(self.db.basic(&address)?)
     }
 
+    /// Account at this provider's block, failing with StatePruned once that state is gone
+    pub fn account(&self, address: Address) -> Result<Option<Account>, ProviderError> {
+        match self.db.get_state(self.block_no) {
+            Ok((Some(accounts), _)) => Ok(accounts.get(&address).copied()),
+            Ok((None, _)) => Err(ProviderError::StateNotExist(self.block_no)),
+            Err(e) => Err(ProviderError::from_database(e)),
+        }
+    }
+
+    pub fn storage(
+        &self,
+        address: Address,
+        key: B256,
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let (_, world) = self.db.get_state(self.block_no)?;
+        Ok(world.and_then(|world| world.get(&address, &key).cloned()))
+    }
+
     pub fn executable_state(&self) -> Result<ExecutableState, ProviderError> {
         let (accounts_base, field_base) = match self.db.get_state(self.block_no) {
             Ok((account, field)) => (account, field),
-            Err(e) => return Err(ProviderError::DatabaseError(e)),
+            Err(e) => return Err(ProviderError::from_database(e)),
         };
 
         if accounts_base.is_none() || field_base.is_none() {
@@ -125,11 +221,62 @@ impl<DB: DatabaseTrait> Provider<DB> {
         let field_write = field_base.clone().unwrap();
         let field_base = Arc::new(field_base.unwrap());
 
+        let trie = self.state_trie_with(&accounts_base)?;
+
         Ok(ExecutableState {
             accounts_base,
             accounts_write,
             field_base,
             field_write,
+            trie,
+        })
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block_no
+    }
+
+    pub fn account_proof(&self, address: Address) -> Result<AccountProof, ProviderError> {
+        let (accounts, world) = match self.db.get_state(self.block_no) {
+            Ok((Some(accounts), Some(world))) => (accounts, world),
+            Ok(_) => return Err(ProviderError::StateNotExist(self.block_no)),
+            Err(e) => return Err(ProviderError::from_database(e)),
+        };
+        let trie = self.state_trie_with(&accounts)?;
+        Ok(AccountProof {
+            address,
+            account: accounts.get(&address).copied(),
+            account_root: trie.root(),
+            world_hash: world.calculate_hash(),
+            siblings: trie.proof(&address).map_err(ProviderError::TrieError)?,
         })
     }
+
+    pub fn state_trie(&self) -> Result<StateTrie, ProviderError> {
+        match self.db.get_state(self.block_no) {
+            Ok((Some(accounts), _)) => self.state_trie_with(&accounts),
+            Ok((None, _)) => Err(ProviderError::StateNotExist(self.block_no)),
+            Err(e) => Err(ProviderError::from_database(e)),
+        }
+    }
+
+    // Blocks imported before the trie existed (and genesis) have no stored root.
+    // Their trie is built in memory from the full account set and never written here:
+    // its nodes stay dirty and are persisted with the next block built on top of it.
+    fn state_trie_with(&self, accounts: &HashMap<Address, Account>) -> Result<StateTrie, ProviderError> {
+        let store = Arc::new(self.db.clone());
+        if let Some(root) = self
+            .db
+            .get_trie_root(self.block_no)
+            .map_err(ProviderError::DatabaseError)?
+        {
+            return Ok(StateTrie::new(root, store));
+        }
+
+        let mut trie = StateTrie::new(empty_root(), store);
+        for (address, account) in accounts.iter() {
+            trie.insert(address, account).map_err(ProviderError::TrieError)?;
+        }
+        Ok(trie)
+    }
 }
@@ -1,14 +1,18 @@
 use std::{collections::HashMap, sync::Arc};
 
 use primitives::{
-    merkle::calculate_merkle_root,
-    transaction::{Recovered, Tx},
+    multisig::{MULTISIG_POLICY_KEY, MultisigPolicy, multisig_address},
+    transaction::{
+        MAX_BATCH_OUTPUTS, MAX_TX_DATA_LEN, Recovered, Transaction, Tx, TxBatchTransfer, TxData,
+        TxMultisigTransfer, TxRegisterMultisig, TxTransfer,
+    },
     types::{Account, Address, B256, U256},
     world::World,
 };
-use sha2::{Digest, Sha256};
-
-use crate::error::{StateExecutionError, TxExecutionError};
+use crate::{
+    error::{StateExecutionError, TrieError, TxExecutionError},
+    trie::{StateTrie, state_root},
+};
 
 // #[derive(Debug)]
 // pub struct State {
@@ -22,6 +26,8 @@ pub struct ExecutableState {
     pub accounts_write: HashMap<Address, Account>,
     pub field_base: Arc<World>,
     pub field_write: World,
+    // Account trie of the base state
+    pub trie: StateTrie,
 }
 
 impl ExecutableState {
@@ -29,26 +35,181 @@ impl ExecutableState {
         &mut self,
         transaction: &Recovered,
     ) -> Result<u128, StateExecutionError> {
-        let sender = transaction.signer();
-        let receiver = transaction.to();
+        if transaction.data().len() > MAX_TX_DATA_LEN {
+            return Err(StateExecutionError::TransactionExecutionError(
+                transaction.hash(),
+                TxExecutionError::InvalidDataLength(transaction.data().len()),
+            ));
+        }
 
-        let mut sender_account = match self.accounts_write.get(&sender) {
-            Some(account) => account.clone(),
-            // sender must have balance because of fee
+        match transaction.tx().transaction() {
+            Transaction::Transfer(tx) => self.execute_transfer(transaction, tx),
+            Transaction::Data(tx) => self.execute_data(transaction, tx),
+            Transaction::BatchTransfer(tx) => self.execute_batch_transfer(transaction, tx),
+            Transaction::RegisterMultisig(tx) => self.execute_register_multisig(transaction, tx),
+            Transaction::MultisigTransfer(tx) => self.execute_multisig_transfer(transaction, tx),
+        }
+    }
+
+    fn execute_transfer(
+        &mut self,
+        transaction: &Recovered,
+        tx: &TxTransfer,
+    ) -> Result<u128, StateExecutionError> {
+        self.transfer(transaction, tx.to, tx.value, tx.fee)
+    }
+
+    fn transfer(
+        &mut self,
+        transaction: &Recovered,
+        to: Address,
+        value: U256,
+        fee: u128,
+    ) -> Result<u128, StateExecutionError> {
+        let mut sender_account = self.sender_account(transaction)?;
+        sender_account.sub_balance(value);
+        sender_account.sub_balance(U256::from(fee));
+        sender_account.increase_nonce();
+        self.accounts_write.insert(transaction.signer(), sender_account);
+
+        // Read receiver after writing sender, sender can be the receiver
+        let mut receiver_account = self.accounts_write.get(&to).copied().unwrap_or_default();
+        receiver_account.add_balance(value);
+        self.accounts_write.insert(to, receiver_account);
+
+        Ok(fee)
+    }
+
+    fn execute_data(
+        &mut self,
+        transaction: &Recovered,
+        tx: &TxData,
+    ) -> Result<u128, StateExecutionError> {
+        if tx.key == MULTISIG_POLICY_KEY {
+            return Err(StateExecutionError::TransactionExecutionError(
+                transaction.hash(),
+                TxExecutionError::ReservedDataKey,
+            ));
+        }
+
+        let mut sender_account = self.sender_account(transaction)?;
+        sender_account.sub_balance(U256::from(tx.fee));
+        sender_account.increase_nonce();
+        self.accounts_write.insert(transaction.signer(), sender_account);
+
+        self.field_write.set(transaction.signer(), tx.key, tx.data.clone());
+        Ok(tx.fee)
+    }
+
+    // All checks run before the first write, so either every output is applied or none is.
+    fn execute_batch_transfer(
+        &mut self,
+        transaction: &Recovered,
+        tx: &TxBatchTransfer,
+    ) -> Result<u128, StateExecutionError> {
+        if tx.outputs.is_empty() || tx.outputs.len() > MAX_BATCH_OUTPUTS {
+            return Err(StateExecutionError::TransactionExecutionError(
+                transaction.hash(),
+                TxExecutionError::InvalidOutputCount(tx.outputs.len()),
+            ));
+        }
+
+        let mut sender_account = self.sender_account(transaction)?;
+        sender_account.sub_balance(tx.value());
+        sender_account.sub_balance(U256::from(tx.fee));
+        sender_account.increase_nonce();
+        self.accounts_write.insert(transaction.signer(), sender_account);
+
+        // Outputs may repeat a receiver or name the sender, so always read the latest write
+        for output in tx.outputs.iter() {
+            let mut receiver_account = self.accounts_write.get(&output.to).copied().unwrap_or_default();
+            receiver_account.add_balance(output.value);
+            self.accounts_write.insert(output.to, receiver_account);
+        }
+
+        Ok(tx.fee)
+    }
+
+    // The policy is kept in the World storage of the multisig account, so it is part of the state root.
+    fn execute_register_multisig(
+        &mut self,
+        transaction: &Recovered,
+        tx: &TxRegisterMultisig,
+    ) -> Result<u128, StateExecutionError> {
+        let policy = tx.policy();
+        if let Err(e) = policy.validate() {
+            return Err(StateExecutionError::TransactionExecutionError(
+                transaction.hash(),
+                TxExecutionError::InvalidMultisigPolicy(e),
+            ));
+        }
+
+        let address = multisig_address(&transaction.signer(), tx.nonce);
+        if self.field_write.get(&address, &MULTISIG_POLICY_KEY).is_some() {
+            return Err(StateExecutionError::TransactionExecutionError(
+                transaction.hash(),
+                TxExecutionError::MultisigAlreadyExists(address),
+            ));
+        }
+
+        let mut sender_account = self.sender_account(transaction)?;
+        sender_account.sub_balance(U256::from(tx.fee));
+        sender_account.increase_nonce();
+        self.accounts_write.insert(transaction.signer(), sender_account);
+
+        self.field_write.set(address, MULTISIG_POLICY_KEY, policy.encode());
+        Ok(tx.fee)
+    }
+
+    // Signer of a multisig transfer is the multisig account, approvals are the owners who signed.
+    fn execute_multisig_transfer(
+        &mut self,
+        transaction: &Recovered,
+        tx: &TxMultisigTransfer,
+    ) -> Result<u128, StateExecutionError> {
+        let policy = match self.multisig_policy(&tx.from) {
+            Some(policy) => policy,
             None => {
                 return Err(StateExecutionError::TransactionExecutionError(
                     transaction.hash(),
-                    TxExecutionError::SenderHasNoAccount,
+                    TxExecutionError::MultisigNotFound(tx.from),
                 ));
             }
         };
 
-        let mut receiver_account = match self.accounts_write.get(&receiver) {
+        if !policy.is_approved_by(transaction.approvals()) {
+            return Err(StateExecutionError::TransactionExecutionError(
+                transaction.hash(),
+                TxExecutionError::MultisigThresholdNotMet,
+            ));
+        }
+
+        self.transfer(transaction, tx.to, tx.value, tx.fee)
+    }
+
+    // A stored policy is only trusted if it is still valid
+    pub fn multisig_policy(&self, address: &Address) -> Option<MultisigPolicy> {
+        let raw = self.field_write.get(address, &MULTISIG_POLICY_KEY)?;
+        MultisigPolicy::decode(raw)
+            .ok()
+            .filter(|policy| policy.validate().is_ok())
+    }
+
+    // Checks what every transaction type needs from the sender: balance for fee + value, and nonce.
+    fn sender_account(&self, transaction: &Recovered) -> Result<Account, StateExecutionError> {
+        let sender_account = match self.accounts_write.get(&transaction.signer()) {
             Some(account) => account.clone(),
-            None => Account::default(),
+            // sender must have balance because of fee
+            None => {
+                return Err(StateExecutionError::TransactionExecutionError(
+                    transaction.hash(),
+                    TxExecutionError::SenderHasNoAccount,
+                ));
+            }
         };
 
-        if U256::from(transaction.fee()) > sender_account.balance() - transaction.value() {
+        let required = U256::from(transaction.fee()).checked_add(transaction.value());
+        if required.is_none_or(|required| required > sender_account.balance()) {
             return Err(StateExecutionError::TransactionExecutionError(
                 transaction.hash(),
                 TxExecutionError::SenderHasNotEnoughBalance,
@@ -62,38 +223,32 @@ impl ExecutableState {
             ));
         }
 
-        sender_account.sub_balance(transaction.value());
-        sender_account.sub_balance(U256::from(transaction.fee()));
-        receiver_account.add_balance(transaction.value());
-        sender_account.increase_nonce();
-
-        self.accounts_write.insert(sender, sender_account);
-        self.accounts_write.insert(receiver, receiver_account);
+        Ok(sender_account)
+    }
 
-        // TODO: Update World.
-        Ok(transaction.fee())
+    /// Accounts that differ from the base state, with their new values
+    pub fn changed_accounts(&self) -> HashMap<Address, Account> {
+        self.accounts_write
+            .iter()
+            .filter(|(address, account)| self.accounts_base.get(address) != Some(account))
+            .map(|(address, account)| (*address, *account))
+            .collect()
     }
 
-    pub fn calculate_state_root(&self) -> B256 {
-        let mut entries: Vec<_> = self.accounts_write.iter().collect();
-        // Address + ord!
-        entries.sort_by_key(|(k, _)| *k);
+    // Applies this state's account changes to the trie of the base state
+    pub fn updated_trie(&self) -> Result<StateTrie, TrieError> {
+        let mut trie = self.trie.clone();
+        let mut changed: Vec<_> = self.changed_accounts().into_iter().collect();
+        // Order doesn't change the root, but keeps the dirty set deterministic
+        changed.sort_by_key(|(address, _)| *address);
+        for (address, account) in changed {
+            trie.insert(&address, &account)?;
+        }
+        Ok(trie)
+    }
 
-        let mut entry_hashes: Vec<B256> = entries
-            .iter()
-            .map(|(k, v)| {
-                let mut hasher = Sha256::new();
-                hasher.update(k.get_addr_hex());
-                hasher.update(v.balance.to_be_bytes::<32>());
-                hasher.update(v.nonce.to_be_bytes());
-                B256::from_slice(&hasher.finalize())
-            })
-            .collect();
-
-        let world_hash = self.field_write.calculate_hash();
-        entry_hashes.push(world_hash);
-
-        let state_root = calculate_merkle_root(entry_hashes);
-        state_root
+    pub fn calculate_state_root(&self) -> Result<B256, TrieError> {
+        let account_root = self.updated_trie()?.root();
+        Ok(state_root(account_root, self.field_write.calculate_hash()))
     }
 }
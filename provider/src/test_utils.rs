@@ -0,0 +1,67 @@
+use primitives::{
+    block::Block,
+    merkle::calculate_merkle_root,
+    receipt::calculate_receipts_root,
+    test_utils::sign,
+    transaction::{Transaction, TxTransfer},
+    types::{Address, U256},
+};
+
+use crate::{DatabaseTrait, ProviderFactory, executor::Executor, trie::state_root};
+
+/// Valid empty block on top of the latest one, mined by `proposer`
+pub fn empty_block<DB: DatabaseTrait>(provider: &ProviderFactory<DB>, proposer: Address) -> Block {
+    let latest = provider.db().get_latest_block_header();
+    let mut block = Block::genesis_block();
+    block.header.height = latest.height + 1;
+    block.header.previous_hash = latest.calculate_hash();
+    block.header.proposer = proposer;
+    block.header.receipts_root = calculate_receipts_root(&[]);
+
+    let state = provider.latest().executable_state().unwrap();
+    let mut executor = Executor::new(state, provider.chain_id());
+    let (_, world) = executor.execute_block(&block).unwrap();
+    let trie = executor.state.updated_trie().unwrap();
+    block.header.state_root = state_root(trie.root(), world.calculate_hash());
+    block
+}
+
+pub fn import_empty_block<DB: DatabaseTrait>(provider: &ProviderFactory<DB>, proposer: Address) {
+    provider
+        .import_new_block(empty_block(provider, proposer))
+        .unwrap();
+}
+
+/// Imports block `height` holding one transfer of 100 from the account of seed "pint", with a fee of 10.
+/// The account has to be funded and have sent one transaction per block before.
+pub fn import_transfer<DB: DatabaseTrait>(
+    provider: &ProviderFactory<DB>,
+    height: u64,
+    to: Address,
+    proposer: Address,
+) {
+    let tx = sign(
+        "pint",
+        Transaction::Transfer(TxTransfer {
+            chain_id: 0,
+            nonce: height - 1,
+            to,
+            fee: 10,
+            value: U256::from(100),
+            data: Vec::new(),
+        }),
+    );
+    let mut executor = Executor::new(provider.latest().executable_state().unwrap(), 0);
+    assert!(executor.execute_transaction(&tx).unwrap().success);
+    executor.reward_proposer(proposer, U256::from(10));
+    let mut block = Block::genesis_block();
+    block.header.height = height;
+    block.header.previous_hash = provider.db().get_latest_block_header().calculate_hash();
+    block.header.transaction_root = calculate_merkle_root(vec![tx.hash().hash()]);
+    block.header.proposer = proposer;
+    block.header.total_fee = U256::from(10);
+    block.header.state_root = executor.calculate_state_root().unwrap();
+    block.header.receipts_root = calculate_receipts_root(&executor.transaction_receipts());
+    block.body.push(tx.tx().clone());
+    provider.import_new_block(block).unwrap();
+}
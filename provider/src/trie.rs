@@ -0,0 +1,286 @@
+use std::{collections::HashMap, fmt, sync::Arc, sync::LazyLock};
+
+use database::traits::{DatabaseTrait, TrieUpdate};
+use primitives::types::{Account, Address, B256};
+use sha2::{Digest, Sha256};
+
+use crate::error::TrieError;
+
+// Sparse merkle tree over the 160 bits of an Address.
+//
+// Each leaf is the hash of one account, an absent account is B256::ZERO.
+// Every inner node is sha256(left || right) and is stored by its hash, so nodes
+// are shared between blocks and an update only touches the 160 nodes on one path.
+pub const TRIE_DEPTH: usize = 160;
+
+// DEFAULT_HASHES[h] is the root of an empty subtree of height h
+static DEFAULT_HASHES: LazyLock<Vec<B256>> = LazyLock::new(|| {
+    let mut hashes = vec![B256::ZERO];
+    for h in 0..TRIE_DEPTH {
+        hashes.push(hash_node(&hashes[h], &hashes[h]));
+    }
+    hashes
+});
+
+pub fn empty_root() -> B256 {
+    DEFAULT_HASHES[TRIE_DEPTH]
+}
+
+pub fn account_leaf_hash(address: &Address, account: &Account) -> B256 {
+    let mut hasher = Sha256::new();
+    hasher.update(address.get_addr());
+    hasher.update(account.balance.to_be_bytes::<32>());
+    hasher.update(account.nonce.to_be_bytes());
+    B256::from_slice(&hasher.finalize())
+}
+
+fn hash_node(left: &B256, right: &B256) -> B256 {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    B256::from_slice(&hasher.finalize())
+}
+
+// Bit of the path at depth (0 = root), most significant bit first. 1 goes right.
+fn path_bit(address: &Address, depth: usize) -> bool {
+    let byte = address.get_addr()[depth / 8];
+    (byte >> (7 - depth % 8)) & 1 == 1
+}
+
+/// Where persisted trie nodes are read from
+pub trait TrieStore: Send + Sync {
+    fn trie_node(&self, hash: &B256) -> Result<Option<(B256, B256)>, TrieError>;
+}
+
+impl<DB: DatabaseTrait> TrieStore for DB {
+    fn trie_node(&self, hash: &B256) -> Result<Option<(B256, B256)>, TrieError> {
+        self.get_trie_node(*hash)
+            .map_err(|e| TrieError::DatabaseError(e.to_string()))
+    }
+}
+
+#[derive(Clone)]
+pub struct StateTrie {
+    root: B256,
+    store: Arc<dyn TrieStore>,
+    // Nodes created since the trie was opened, not yet persisted
+    dirty: HashMap<B256, (B256, B256)>,
+}
+
+impl fmt::Debug for StateTrie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateTrie")
+            .field("root", &self.root)
+            .field("dirty", &self.dirty.len())
+            .finish()
+    }
+}
+
+impl StateTrie {
+    pub fn new(root: B256, store: Arc<dyn TrieStore>) -> Self {
+        Self {
+            root,
+            store,
+            dirty: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> B256 {
+        self.root
+    }
+
+    /// New nodes to persist, as (hash, left || right)
+    pub fn dirty_nodes(&self) -> Vec<(B256, [u8; 64])> {
+        self.dirty
+            .iter()
+            .map(|(hash, (left, right))| {
+                let mut raw = [0u8; 64];
+                raw[..32].copy_from_slice(left.as_slice());
+                raw[32..].copy_from_slice(right.as_slice());
+                (*hash, raw)
+            })
+            .collect()
+    }
+
+    /// Root and new nodes, as written with the block
+    pub fn trie_update(&self) -> TrieUpdate {
+        TrieUpdate {
+            root: self.root,
+            nodes: self.dirty_nodes(),
+        }
+    }
+
+    fn children(&self, node: &B256, height: usize) -> Result<(B256, B256), TrieError> {
+        if *node == DEFAULT_HASHES[height] {
+            let child = DEFAULT_HASHES[height - 1];
+            return Ok((child, child));
+        }
+        if let Some(children) = self.dirty.get(node) {
+            return Ok(*children);
+        }
+        self.store
+            .trie_node(node)?
+            .ok_or(TrieError::MissingNode(*node))
+    }
+
+    // Siblings from the leaf level up to the root
+    fn siblings(&self, address: &Address) -> Result<Vec<B256>, TrieError> {
+        let mut siblings = Vec::with_capacity(TRIE_DEPTH);
+        let mut node = self.root;
+        for depth in 0..TRIE_DEPTH {
+            let (left, right) = self.children(&node, TRIE_DEPTH - depth)?;
+            if path_bit(address, depth) {
+                siblings.push(left);
+                node = right;
+            } else {
+                siblings.push(right);
+                node = left;
+            }
+        }
+        siblings.reverse();
+        Ok(siblings)
+    }
+
+    pub fn insert(&mut self, address: &Address, account: &Account) -> Result<(), TrieError> {
+        let siblings = self.siblings(address)?;
+        let mut current = account_leaf_hash(address, account);
+        for (height, sibling) in siblings.iter().enumerate() {
+            let (left, right) = match path_bit(address, TRIE_DEPTH - 1 - height) {
+                true => (*sibling, current),
+                false => (current, *sibling),
+            };
+            current = hash_node(&left, &right);
+            if current != DEFAULT_HASHES[height + 1] {
+                self.dirty.insert(current, (left, right));
+            }
+        }
+        self.root = current;
+        Ok(())
+    }
+
+    pub fn proof(&self, address: &Address) -> Result<Vec<B256>, TrieError> {
+        self.siblings(address)
+    }
+}
+
+/// Checks `account` (None if the address has no account) against an account trie root.
+/// `siblings` go from the leaf level up to the root.
+pub fn verify_account_proof(
+    address: &Address,
+    account: Option<&Account>,
+    siblings: &[B256],
+    root: B256,
+) -> bool {
+    if siblings.len() != TRIE_DEPTH {
+        return false;
+    }
+
+    let mut current = match account {
+        Some(account) => account_leaf_hash(address, account),
+        None => B256::ZERO,
+    };
+    for (height, sibling) in siblings.iter().enumerate() {
+        current = match path_bit(address, TRIE_DEPTH - 1 - height) {
+            true => hash_node(sibling, &current),
+            false => hash_node(&current, sibling),
+        };
+    }
+    current == root
+}
+
+/// Proof of one account (or of its absence) against a header's state_root
+#[derive(Debug, Clone)]
+pub struct AccountProof {
+    pub address: Address,
+    pub account: Option<Account>,
+    pub account_root: B256,
+    pub world_hash: B256,
+    // From the leaf level up to the root
+    pub siblings: Vec<B256>,
+}
+
+impl AccountProof {
+    pub fn verify(&self, state_root: B256) -> bool {
+        verify_account_proof(&self.address, self.account.as_ref(), &self.siblings, self.account_root)
+            && self::state_root(self.account_root, self.world_hash) == state_root
+    }
+}
+
+/// Root stored in the header: accounts trie and World together
+pub fn state_root(account_root: B256, world_hash: B256) -> B256 {
+    hash_node(&account_root, &world_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use database::immemorydb::InMemoryDB;
+    use primitives::types::U256;
+
+    use super::*;
+
+    fn new_trie() -> StateTrie {
+        StateTrie::new(empty_root(), Arc::new(Arc::new(InMemoryDB::new())))
+    }
+
+    #[test]
+    fn test_trie_proofs() {
+        let mut trie = new_trie();
+        let accounts: Vec<(Address, Account)> = (0..5)
+            .map(|i| (Address::random(), Account::new(i, U256::from(i * 100))))
+            .collect();
+        for (address, account) in accounts.iter() {
+            trie.insert(address, account).unwrap();
+        }
+
+        for (address, account) in accounts.iter() {
+            let proof = trie.proof(address).unwrap();
+            assert!(verify_account_proof(address, Some(account), &proof, trie.root()));
+            assert!(!verify_account_proof(address, Some(&Account::new(99, U256::MAX)), &proof, trie.root()));
+        }
+
+        // Absent account
+        let stranger = Address::random();
+        let proof = trie.proof(&stranger).unwrap();
+        assert!(verify_account_proof(&stranger, None, &proof, trie.root()));
+    }
+
+    #[test]
+    fn test_trie_root_is_independent_of_order() {
+        let accounts: Vec<(Address, Account)> = (0..5)
+            .map(|i| (Address::random(), Account::new(i, U256::from(i))))
+            .collect();
+
+        let mut forward = new_trie();
+        for (address, account) in accounts.iter() {
+            forward.insert(address, account).unwrap();
+        }
+        let mut backward = new_trie();
+        for (address, account) in accounts.iter().rev() {
+            backward.insert(address, account).unwrap();
+        }
+        assert_eq!(forward.root(), backward.root());
+
+        // Updating an account changes the root
+        let before = forward.root();
+        forward.insert(&accounts[0].0, &Account::new(9, U256::from(9))).unwrap();
+        assert_ne!(forward.root(), before);
+    }
+
+    #[test]
+    fn test_trie_reads_persisted_nodes() {
+        let db = Arc::new(InMemoryDB::new());
+        let (alice, bob) = (Address::random(), Address::random());
+        let mut trie = StateTrie::new(empty_root(), Arc::new(db.clone()));
+        trie.insert(&alice, &Account::new(1, U256::from(10))).unwrap();
+        trie.insert(&bob, &Account::new(2, U256::from(20))).unwrap();
+        db.update_trie(1, trie.root(), trie.dirty_nodes()).unwrap();
+
+        let root = db.get_trie_root(1).unwrap().unwrap();
+        let reopened = StateTrie::new(root, Arc::new(db.clone()));
+        let proof = reopened.proof(&alice).unwrap();
+        assert!(verify_account_proof(&alice, Some(&Account::new(1, U256::from(10))), &proof, root));
+
+        let orphan = StateTrie::new(root, Arc::new(Arc::new(InMemoryDB::new())));
+        assert!(matches!(orphan.proof(&alice), Err(TrieError::MissingNode(_))));
+    }
+}
@@ -1,4 +1,4 @@
-use primitives::types::TxHash;
+use primitives::{error::MultisigError, types::{ChainId, TxHash}};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -52,4 +52,20 @@ pub enum InvalidPoolTransactionError {
     NonceIsNotConsistent,
     #[error("Transaction used coinbase_addr(0x0000...)")]
     UsingCoinbaseAddr,
+    #[error("Transaction is signed for another chain")]
+    InvalidChainId(ChainId),
+    #[error("Transaction data length is out of range")]
+    InvalidDataLength(usize),
+    #[error("Transaction fee does not cover its data")]
+    NotEnoughDataFee(u128),
+    #[error("Invalid multisig policy")]
+    InvalidMultisigPolicy(MultisigError),
+    #[error("Sender is not a multisig account")]
+    MultisigNotFound,
+    #[error("Not enough multisig owners signed")]
+    MultisigThresholdNotMet,
+    #[error("Batch transfer has no outputs or too many outputs")]
+    InvalidOutputCount(usize),
+    #[error("Data key is reserved for multisig policies")]
+    ReservedDataKey,
 }
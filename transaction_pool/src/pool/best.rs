@@ -1,9 +1,17 @@
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::Arc,
+};
 
-use crate::{pool::pending::PendingTransaction, validator::validtx::ValidPoolTransaction};
+use crate::{
+    identifier::SenderId, pool::pending::PendingTransaction,
+    validator::validtx::ValidPoolTransaction,
+};
 
 pub struct BestTransactions {
     pub independent: BTreeSet<PendingTransaction>,
+    // Senders whose remaining transactions are skipped
+    pub invalid: HashSet<SenderId>,
 }
 
 impl BestTransactions {
@@ -11,6 +19,11 @@ impl BestTransactions {
         let res = self.independent.pop_last();
         res
     }
+
+    /// Skips the later transactions of the sender, which would fail on a nonce gap
+    pub fn mark_invalid(&mut self, transaction: &ValidPoolTransaction) {
+        self.invalid.insert(transaction.sender());
+    }
 }
 
 impl Iterator for BestTransactions {
@@ -24,7 +37,10 @@ impl Iterator for BestTransactions {
                     return None;
                 }
             };
+            if self.invalid.contains(&best.transaction.sender()) {
+                continue;
+            }
             return Some(best.transaction.clone());
         }
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{collections::{BTreeMap, HashSet}, sync::Arc};
 
 use crate::{identifier::TransactionId, ordering::PintOrdering, pool::best::BestTransactions, validator::validtx::ValidPoolTransaction};
 
@@ -59,7 +59,8 @@ impl PendingPool {
 
     pub fn best(&self) -> BestTransactions {
         BestTransactions {
-            independent: self.independent.values().cloned().collect()
+            independent: self.independent.values().cloned().collect(),
+            invalid: HashSet::new(),
         }
     }
 }
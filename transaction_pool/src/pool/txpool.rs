@@ -283,7 +283,7 @@ pub struct PoolInternalTransaction {
 #[cfg(test)]
 mod tests {
     use k256::{ecdsa::{RecoveryId, Signature as ECDSASig, SigningKey}, EncodedPoint};
-    use primitives::{signature::Signature, transaction::{SignedTransaction, Transaction}, types::{Address, U256}};
+    use primitives::{signature::Signature, transaction::{SignedTransaction, Transaction, TxTransfer}, types::{Address, U256}};
     use sha2::{Digest, Sha256};
 
     use crate::{mock::MockValidator};
@@ -310,13 +310,14 @@ mod tests {
         let receiver = Address::from_byte(receiver.try_into().unwrap());
         dbg!(receiver.get_addr_hex());
 
-        let tx = Transaction {
+        let tx = Transaction::Transfer(TxTransfer {
             chain_id: 0,
             nonce,
             to: receiver,
             fee,
             value,
-        };
+            data: Vec::new(),
+        });
 
         let tx_hash = tx.encode_for_signing();
         let digest = Sha256::new_with_prefix(tx_hash.hash());
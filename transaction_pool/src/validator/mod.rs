@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
-use primitives::{transaction::{Recovered, Tx}, types::{TxHash, COINBASE_ADDR, U256}};
+use primitives::{multisig::{MultisigPolicy, MULTISIG_POLICY_KEY}, transaction::{Recovered, Transaction, Tx, MAX_BATCH_OUTPUTS, MAX_TX_DATA_LEN}, types::{TxHash, COINBASE_ADDR, U256}};
 use provider::{DatabaseTrait, Provider, ProviderFactory};
 
 use crate::{error::InvalidPoolTransactionError, identifier::{TransactionId, TransactionOrigin}, validator::validtx::ValidPoolTransaction};
 
 pub mod validtx;
 
+/// Minimum fee for each byte of transaction data
+pub const MIN_FEE_PER_DATA_BYTE: u128 = 1;
+
 #[derive(Debug)]
 pub struct Validator<DB: DatabaseTrait> {
     inner: Arc<ValidatorInner<DB>>,
@@ -59,6 +62,14 @@ impl<DB: DatabaseTrait> ValidatorInner<DB> {
     }
 
     fn validate_one_no_state(&self, transaction: Recovered) -> Result<Recovered, TransactionValidationOutcome> {
+        if transaction.chain_id() != self.provider.chain_id() {
+            let chain_id = transaction.chain_id();
+            return Err(TransactionValidationOutcome::Invalid{
+                transaction,
+                error: InvalidPoolTransactionError::InvalidChainId(chain_id)
+            });
+        }
+
         if transaction.fee() <= 0 {
             return Err(TransactionValidationOutcome::Invalid{
                 transaction: transaction, 
@@ -73,6 +84,52 @@ impl<DB: DatabaseTrait> ValidatorInner<DB> {
             });
         }
 
+        let data_len = transaction.data().len();
+        if data_len > MAX_TX_DATA_LEN {
+            return Err(TransactionValidationOutcome::Invalid{
+                transaction,
+                error: InvalidPoolTransactionError::InvalidDataLength(data_len)
+            });
+        }
+
+        // Data is stored by every node forever, so it has to pay for its size
+        let min_fee = MIN_FEE_PER_DATA_BYTE * data_len as u128;
+        if transaction.fee() < min_fee {
+            return Err(TransactionValidationOutcome::Invalid{
+                transaction,
+                error: InvalidPoolTransactionError::NotEnoughDataFee(min_fee)
+            });
+        }
+
+        match transaction.tx().transaction() {
+            Transaction::Transfer(_) | Transaction::MultisigTransfer(_) => {}
+            Transaction::Data(tx) => {
+                if tx.key == MULTISIG_POLICY_KEY {
+                    return Err(TransactionValidationOutcome::Invalid{
+                        transaction,
+                        error: InvalidPoolTransactionError::ReservedDataKey
+                    });
+                }
+            }
+            Transaction::BatchTransfer(tx) => {
+                let count = tx.outputs.len();
+                if count == 0 || count > MAX_BATCH_OUTPUTS {
+                    return Err(TransactionValidationOutcome::Invalid{
+                        transaction,
+                        error: InvalidPoolTransactionError::InvalidOutputCount(count)
+                    });
+                }
+            }
+            Transaction::RegisterMultisig(tx) => {
+                if let Err(e) = tx.policy().validate() {
+                    return Err(TransactionValidationOutcome::Invalid{
+                        transaction,
+                        error: InvalidPoolTransactionError::InvalidMultisigPolicy(e)
+                    });
+                }
+            }
+        }
+
         Ok(transaction)
     }
 
@@ -85,6 +142,28 @@ impl<DB: DatabaseTrait> ValidatorInner<DB> {
             }
         };
 
+        // Owners who signed must meet the threshold of the policy stored on chain
+        if let Transaction::MultisigTransfer(tx) = transaction.tx().transaction() {
+            let policy = match state.storage(tx.from, MULTISIG_POLICY_KEY) {
+                Ok(raw) => raw
+                    .and_then(|raw| MultisigPolicy::decode(&raw).ok())
+                    .filter(|policy| policy.validate().is_ok()),
+                Err(_err) => {
+                    return TransactionValidationOutcome::UnexpectedError(transaction.hash());
+                }
+            };
+            let error = match policy {
+                None => Some(InvalidPoolTransactionError::MultisigNotFound),
+                Some(policy) if !policy.is_approved_by(transaction.approvals()) => {
+                    Some(InvalidPoolTransactionError::MultisigThresholdNotMet)
+                }
+                Some(_) => None,
+            };
+            if let Some(error) = error {
+                return TransactionValidationOutcome::Invalid { transaction, error };
+            }
+        }
+
         // Checks nonce >= on_chain_node
         if transaction.nonce() < account.nonce {
             return TransactionValidationOutcome::Invalid{
@@ -137,38 +216,44 @@ impl TransactionValidationOutcome {
 #[cfg(test)]
 mod tests {
     use database::{immemorydb::InMemoryDB, mdbx::MDBX};
-    use primitives::{transaction::SignedTransaction, types::{Account, U256}};
+    use primitives::{test_utils::sign, transaction::{SignedTransaction, TxData, TxMultisigTransfer, TxRegisterMultisig}, types::{Account, Address, B256, U256}};
 
     use crate::identifier::TransactionOrigin;
 
     use super::*;
 
     fn new_transaction() -> SignedTransaction {
-        // let tx = Transaction {chain_id: 0, nonce: 0, to: receiver, fee: 1, value: U256::from(1)};
+        // let tx = Transaction::Transfer(TxTransfer {chain_id: 0, nonce: 0, to: receiver, fee: 1, value: U256::from(1)});
         // sender: 28dcb1338b900419cd613a8fb273ae36e7ec2b1d, receiver: 0534501c34f5a0f3fa43dc5d78e619be7edfa21a
-        let raw = "000000000000000000000000000000000534501c34f5a0f3fa43dc5d78e619be7edfa21a000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001edb54ca220c37699400def4d371e024ef94c7b62cd88d65d6e544807f957174c6aed902279a2abcd976584998e4c2b886e2a252ed375d44000f842e0d362fcea00";
+        let raw = "0102000000a401010000005900000000000000000000000000000000000534501c34f5a0f3fa43dc5d78e619be7edfa21a000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001000000000f3be41970c8d4fb664fb9e3f517e93d29d22b17a30e67b4c19ce7a558394a9e1a0c16f9dde8f6c8943fae12029789c1975887dfb6252dc65220c5a3140a9aa10100000000";
         let data = hex::decode(raw).unwrap();
         let (signed_tx, _) = SignedTransaction::decode(&data).unwrap();
         signed_tx
     }
 
     fn new_zero_fee_transaction() -> SignedTransaction {
-        // let tx = Transaction {chain_id: 0, nonce: 0, to: receiver, fee: 1, value: U256::from(1)};
+        // let tx = Transaction::Transfer(TxTransfer {chain_id: 0, nonce: 0, to: receiver, fee: 0, value: U256::from(1)});
         // sender: 28dcb1338b900419cd613a8fb273ae36e7ec2b1d, receiver: 0534501c34f5a0f3fa43dc5d78e619be7edfa21a
-        let raw = "000000000000000000000000000000000534501c34f5a0f3fa43dc5d78e619be7edfa21a000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001edb54ca220c37699400def4d371e024ef94c7b62cd88d65d6e544807f957174c6aed902279a2abcd976584998e4c2b886e2a252ed375d44000f842e0d362fcea00";
+        let raw = "0102000000a401010000005900000000000000000000000000000000000534501c34f5a0f3fa43dc5d78e619be7edfa21a000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000001ea3e61ffc9143e0c23744dfa61b860a06176518da11526432ae9c553f7f935434ad05270b1aa6524e3fea3c5cd570ac28a563c5fc7c31208ea9d3c379fee1db0100000000";
         let data = hex::decode(raw).unwrap();
         let (signed_tx, _) = SignedTransaction::decode(&data).unwrap();
         signed_tx
     }
 
+    fn new_data_transaction(data: Vec<u8>, fee: u128) -> SignedTransaction {
+        sign("pint", Transaction::Data(TxData { chain_id: 0, nonce: 0, fee, key: B256::repeat_byte(1), data })).tx().clone()
+    }
+
     #[test]
     fn test_validate_pending_transaction() {
         let transaction = new_transaction();
         let recovered = transaction.into_recovered().unwrap();
-        let mut db = MDBX::genesis_state();
+        let path = std::env::temp_dir().join(format!("pint-validator-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let mut db = MDBX::genesis_state(&path, 0).unwrap();
         db.add_account(recovered.signer(), Account::new(recovered.nonce(), U256::MAX)).unwrap();
 
-        let provider = ProviderFactory::new(db);
+        let provider = ProviderFactory::new(db, 0);
         let validator = Validator::new(provider);
 
         let outcome: TransactionValidationOutcome =
@@ -176,6 +261,8 @@ mod tests {
 
         assert!(outcome.is_valid());
         dbg!(outcome);
+        drop(validator);
+        std::fs::remove_dir_all(&path).unwrap();
     }
 
     #[test]
@@ -188,7 +275,7 @@ mod tests {
         let db = Arc::new(db);
 
 
-        let provider = ProviderFactory::new(db);
+        let provider = ProviderFactory::new(db, 0);
         let validator = Validator::new(provider);
 
         let outcome: TransactionValidationOutcome =
@@ -207,7 +294,7 @@ mod tests {
 
         let db = Arc::new(db);
 
-        let provider = ProviderFactory::new(db);
+        let provider = ProviderFactory::new(db, 0);
         let validator = Validator::new(provider);
 
         let outcome: TransactionValidationOutcome =
@@ -226,7 +313,7 @@ mod tests {
 
         let db = Arc::new(db);
 
-        let provider = ProviderFactory::new(db);
+        let provider = ProviderFactory::new(db, 0);
         let validator = Validator::new(provider);
 
         let outcome: TransactionValidationOutcome =
@@ -235,4 +322,92 @@ mod tests {
         assert!(!outcome.is_valid());
         dbg!(outcome);
     }
+
+    #[test]
+    fn test_validate_cross_chain_transaction() {
+        // Signed for chain 0, replayed on chain 1
+        let transaction = new_transaction();
+        let recovered = transaction.into_recovered().unwrap();
+        let mut db = InMemoryDB::new();
+        db.add_account(recovered.signer(), Account::new(recovered.nonce(), U256::MAX)).unwrap();
+
+        let db = Arc::new(db);
+
+        let provider = ProviderFactory::new(db, 1);
+        let validator = Validator::new(provider);
+
+        let outcome: TransactionValidationOutcome =
+            validator.validate_transaction(TransactionOrigin::External, recovered.clone());
+
+        assert!(matches!(
+            outcome,
+            TransactionValidationOutcome::Invalid { error: InvalidPoolTransactionError::InvalidChainId(0), .. }
+        ));
+    }
+
+    #[test]
+    fn test_validate_data_transaction() {
+        let db = Arc::new(InMemoryDB::new());
+        let provider = ProviderFactory::new(db, 0);
+        let validator = Validator::new(provider);
+
+        let recovered = new_data_transaction(b"hello pint".to_vec(), 10).into_recovered().unwrap();
+        let outcome = validator.validate_transaction(TransactionOrigin::External, recovered);
+        assert!(outcome.is_valid());
+
+        let recovered = new_data_transaction(vec![0; MAX_TX_DATA_LEN + 1], u128::MAX).into_recovered().unwrap();
+        let outcome = validator.validate_transaction(TransactionOrigin::External, recovered);
+        assert!(matches!(
+            outcome,
+            TransactionValidationOutcome::Invalid { error: InvalidPoolTransactionError::InvalidDataLength(_), .. }
+        ));
+
+        // Only a multisig registration writes the policy key
+        let recovered = sign("pint", Transaction::Data(TxData { chain_id: 0, nonce: 0, fee: 10, key: MULTISIG_POLICY_KEY, data: b"policy".to_vec() }));
+        let outcome = validator.validate_transaction(TransactionOrigin::External, recovered);
+        assert!(matches!(
+            outcome,
+            TransactionValidationOutcome::Invalid { error: InvalidPoolTransactionError::ReservedDataKey, .. }
+        ));
+    }
+
+    #[test]
+    fn test_validate_data_fee() {
+        let db = Arc::new(InMemoryDB::new());
+        let provider = ProviderFactory::new(db, 0);
+        let validator = Validator::new(provider);
+
+        // 10 bytes of data needs at least 10 fee
+        let recovered = new_data_transaction(b"hello pint".to_vec(), 9).into_recovered().unwrap();
+        let outcome = validator.validate_transaction(TransactionOrigin::External, recovered);
+        assert!(matches!(
+            outcome,
+            TransactionValidationOutcome::Invalid { error: InvalidPoolTransactionError::NotEnoughDataFee(10), .. }
+        ));
+    }
+
+    #[test]
+    fn test_validate_multisig_transactions() {
+        let db = Arc::new(InMemoryDB::new());
+        let provider = ProviderFactory::new(db, 0);
+        let validator = Validator::new(provider);
+
+        let owners = vec![Address::random(), Address::random()];
+        let recovered = sign("pint", Transaction::RegisterMultisig(TxRegisterMultisig { chain_id: 0, nonce: 0, fee: 1, threshold: 3, owners }));
+        let outcome = validator.validate_transaction(TransactionOrigin::External, recovered);
+        assert!(matches!(
+            outcome,
+            TransactionValidationOutcome::Invalid { error: InvalidPoolTransactionError::InvalidMultisigPolicy(_), .. }
+        ));
+
+        // No policy is stored for this address
+        let from = Address::random();
+        let recovered = sign("pint", Transaction::MultisigTransfer(TxMultisigTransfer { chain_id: 0, nonce: 0, from, to: Address::random(), fee: 1, value: U256::from(1), data: Vec::new() }));
+        assert_eq!(recovered.signer(), from);
+        let outcome = validator.validate_transaction(TransactionOrigin::External, recovered);
+        assert!(matches!(
+            outcome,
+            TransactionValidationOutcome::Invalid { error: InvalidPoolTransactionError::MultisigNotFound, .. }
+        ));
+    }
 }
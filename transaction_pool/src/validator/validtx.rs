@@ -1,4 +1,4 @@
-use primitives::{transaction::{Recovered, Tx}, types::{TxHash, U256}};
+use primitives::{transaction::{Recovered, Tx, TxType}, types::{TxHash, U256}};
 
 use crate::identifier::{SenderId, TransactionId, TransactionOrigin};
 
@@ -35,6 +35,10 @@ impl ValidPoolTransaction {
 }
 
 impl Tx for ValidPoolTransaction {
+    fn tx_type(&self) -> TxType {
+        self.tx().tx_type()
+    }
+
     fn chain_id(&self) -> primitives::types::ChainId {
         self.tx().chain_id()
     }
@@ -43,7 +47,7 @@ impl Tx for ValidPoolTransaction {
         self.tx().nonce()
     }
 
-    fn to(&self) -> primitives::types::Address {
+    fn to(&self) -> Option<primitives::types::Address> {
         self.tx().to()
     }
 
@@ -54,4 +58,8 @@ impl Tx for ValidPoolTransaction {
     fn value(&self) -> U256 {
         self.tx().value()
     }
+
+    fn data(&self) -> &[u8] {
+        self.tx().data()
+    }
 }